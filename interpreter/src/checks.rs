@@ -0,0 +1,51 @@
+//! Checks which only depend on the code itself, so they run once when a program is compiled
+//! instead of every time a block is evaluated.
+
+use crate::error::{ErrorCode, KetamineError};
+use lexer::Pos;
+use parser::ast::{Statement, AST};
+use parser::visit::{walk_code, Visitor};
+
+/// Checks a parsed program, returning the first error it finds.
+pub(crate) fn check(code: &[Pos<Statement>]) -> Result<(), KetamineError> {
+    let mut checker = Checker { error: None };
+    checker.visit_code(code);
+    checker.error.map_or(Ok(()), Err)
+}
+
+struct Checker {
+    error: Option<KetamineError>,
+}
+
+impl Visitor for Checker {
+    fn visit_code(&mut self, code: &[Pos<Statement>]) {
+        if self.error.is_some() {
+            return;
+        }
+        // A variable can't be declared with the name of a function declared in the same block,
+        // since the function is defined before the variable.
+        let functions = code
+            .iter()
+            .filter_map(|statement| statement.value.declaration()?.name.as_ref())
+            .collect::<Vec<_>>();
+        for statement in code {
+            if let AST::VariableDeclaration(declaration) = statement.value.inner() {
+                let name = &declaration.name;
+                if functions
+                    .iter()
+                    .any(|function| function.value.0 == name.value.0)
+                {
+                    let msg = format!("can't redeclare function `{}`", name.value.0);
+                    self.error = Some(KetamineError::runtime_with_code(
+                        ErrorCode::ReadonlyAssignment,
+                        name.start,
+                        name.end,
+                        msg,
+                    ));
+                    return;
+                }
+            }
+        }
+        walk_code(self, code);
+    }
+}
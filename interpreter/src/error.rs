@@ -0,0 +1,367 @@
+use lexer::LexingError;
+use parser::error::{ErrorKind, ParseError};
+use report::Diagnostic;
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Any error which can occur while lexing, parsing or evaluating a script.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KetamineError {
+    Lex(LexingError),
+    /// Every syntax error of a program, in source order. The first error is the one described
+    /// by `code`, `span` and `message`.
+    Parse {
+        first: ParseError,
+        rest: Vec<ParseError>,
+    },
+    Runtime(Box<RuntimeError>),
+}
+
+/// A stable identifier for a class of errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    IllegalToken,
+    UnexpectedToken,
+    UnexpectedEnd,
+    Runtime,
+    TypeMismatch,
+    InvalidOperands,
+    InvalidIndex,
+    NotCallable,
+    DuplicateKey,
+    ReadonlyAssignment,
+    ArityMismatch,
+    PatternMismatch,
+    InvalidAssignment,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::IllegalToken => "E0001",
+            ErrorCode::UnexpectedToken => "E0101",
+            ErrorCode::UnexpectedEnd => "E0102",
+            ErrorCode::Runtime => "E0200",
+            ErrorCode::TypeMismatch => "E0201",
+            ErrorCode::InvalidOperands => "E0202",
+            ErrorCode::InvalidIndex => "E0203",
+            ErrorCode::NotCallable => "E0204",
+            ErrorCode::DuplicateKey => "E0205",
+            ErrorCode::ReadonlyAssignment => "E0206",
+            ErrorCode::ArityMismatch => "E0207",
+            ErrorCode::PatternMismatch => "E0208",
+            ErrorCode::InvalidAssignment => "E0209",
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A byte range within the source code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
+
+/// A secondary span which adds context to an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A function call which was active when a runtime error occurred.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// The name of the called function, if known.
+    pub name: Option<String>,
+    /// The location of the call.
+    pub span: Span,
+}
+
+/// An error which occurred while evaluating a script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// The location of the error. Errors raised by native functions don't have a location until
+    /// they are attributed to the call site.
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    /// The calls which were active when the error occurred, innermost first.
+    pub stack: Vec<Frame>,
+}
+
+impl KetamineError {
+    /// Creates a generic runtime error at the given location.
+    pub fn runtime(start: usize, end: usize, message: impl Into<String>) -> Self {
+        KetamineError::runtime_with_code(ErrorCode::Runtime, start, end, message)
+    }
+
+    pub fn runtime_with_code(
+        code: ErrorCode,
+        start: usize,
+        end: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        KetamineError::Runtime(Box::new(RuntimeError {
+            code,
+            message: message.into(),
+            span: Some(Span::new(start, end)),
+            labels: vec![],
+            notes: vec![],
+            stack: vec![],
+        }))
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            KetamineError::Lex(..) => ErrorCode::IllegalToken,
+            KetamineError::Parse { first, .. } => match first.kind() {
+                ErrorKind::Missing(..) => ErrorCode::UnexpectedEnd,
+                ErrorKind::Unexpected { .. } => ErrorCode::UnexpectedToken,
+            },
+            KetamineError::Runtime(err) => err.code,
+        }
+    }
+
+    /// The primary location of the error.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            KetamineError::Lex(err) => Some(Span::new(err.location(), err.location() + 1)),
+            KetamineError::Parse { first, .. } => Some(Span::new(first.start, first.end)),
+            KetamineError::Runtime(err) => err.span,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            KetamineError::Lex(err) => err.to_string(),
+            KetamineError::Parse { first, .. } => first.to_string(),
+            KetamineError::Runtime(err) => err.message.clone(),
+        }
+    }
+
+    /// Secondary locations which add context to the error.
+    pub fn labels(&self) -> Vec<Label> {
+        match self {
+            KetamineError::Lex(..) => vec![],
+            KetamineError::Parse { first, .. } => first
+                .notes()
+                .iter()
+                .map(|note| Label {
+                    span: Span::new(note.start, note.end),
+                    message: note.value.to_owned(),
+                })
+                .collect(),
+            KetamineError::Runtime(err) => err.labels.clone(),
+        }
+    }
+
+    pub fn notes(&self) -> &[String] {
+        match self {
+            KetamineError::Runtime(err) => &err.notes,
+            _ => &[],
+        }
+    }
+
+    /// The calls which were active when the error occurred, innermost first.
+    pub fn stack(&self) -> &[Frame] {
+        match self {
+            KetamineError::Runtime(err) => &err.stack,
+            _ => &[],
+        }
+    }
+
+    /// Errors which were found together with this one, like the remaining syntax errors of a
+    /// program.
+    pub fn related(&self) -> Vec<KetamineError> {
+        match self {
+            KetamineError::Parse { rest, .. } => {
+                rest.iter().cloned().map(KetamineError::from).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn with_label(mut self, start: usize, end: usize, message: impl Into<String>) -> Self {
+        if let KetamineError::Runtime(err) = &mut self {
+            err.labels.push(Label {
+                span: Span::new(start, end),
+                message: message.into(),
+            });
+        }
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        if let KetamineError::Runtime(err) = &mut self {
+            err.notes.push(note.into());
+        }
+        self
+    }
+
+    /// Attributes an error without a location, like one raised by a native function, to the
+    /// given location.
+    pub(crate) fn or_span(mut self, start: usize, end: usize) -> Self {
+        if let KetamineError::Runtime(err) = &mut self {
+            err.span.get_or_insert(Span::new(start, end));
+        }
+        self
+    }
+
+    /// Attaches the call stack of the interpreter to an error which doesn't have one yet, i.e.
+    /// to an error which was raised within the innermost call.
+    pub(crate) fn with_stack(mut self, stack: &[Frame]) -> Self {
+        if let KetamineError::Runtime(err) = &mut self {
+            if err.stack.is_empty() {
+                err.stack = stack.iter().rev().cloned().collect();
+            }
+        }
+        self
+    }
+}
+
+impl KetamineError {
+    /// Converts this error into a diagnostic which can be rendered using the `report` crate.
+    pub fn diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(self.message()).with_code(self.code());
+        if let Some(Span { start, end }) = self.span() {
+            diagnostic = diagnostic.with_label(report::Label::primary(start, end));
+        }
+        for label in self.labels() {
+            let Span { start, end } = label.span;
+            diagnostic = diagnostic
+                .with_label(report::Label::secondary(start, end).with_message(label.message));
+        }
+        for frame in self.stack() {
+            // Functions called by the host program don't have a call site.
+            let call_site = (frame.span.start < frame.span.end).then_some(frame.span.start);
+            diagnostic = diagnostic.with_frame(report::Frame::new(frame.name.clone(), call_site));
+        }
+        for note in self.notes() {
+            diagnostic = diagnostic.with_note(note);
+        }
+        diagnostic
+    }
+
+    /// Converts this error and all related errors into diagnostics.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let related = self.related();
+        std::iter::once(self)
+            .chain(&related)
+            .map(KetamineError::diagnostic)
+            .collect()
+    }
+}
+
+impl Display for KetamineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.message())?;
+        match self.related().len() {
+            0 => Ok(()),
+            1 => write!(f, " (and 1 more error)"),
+            more => write!(f, " (and {} more errors)", more),
+        }
+    }
+}
+
+impl std::error::Error for KetamineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KetamineError::Lex(err) => Some(err),
+            KetamineError::Parse { first, .. } => Some(first),
+            KetamineError::Runtime(..) => None,
+        }
+    }
+}
+
+/// Native functions report errors as plain messages. They are attributed to the location of
+/// the call once they leave the native function.
+impl From<String> for KetamineError {
+    fn from(message: String) -> Self {
+        KetamineError::Runtime(Box::new(RuntimeError {
+            code: ErrorCode::Runtime,
+            message,
+            span: None,
+            labels: vec![],
+            notes: vec![],
+            stack: vec![],
+        }))
+    }
+}
+
+impl From<&str> for KetamineError {
+    fn from(message: &str) -> Self {
+        KetamineError::from(message.to_owned())
+    }
+}
+
+impl From<LexingError> for KetamineError {
+    fn from(err: LexingError) -> Self {
+        KetamineError::Lex(err)
+    }
+}
+
+impl From<ParseError> for KetamineError {
+    fn from(err: ParseError) -> Self {
+        KetamineError::Parse {
+            first: err,
+            rest: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ErrorCode, Interpreter, KetamineError, Span};
+
+    #[test]
+    fn report_every_parse_error() {
+        let src = "a = = 1;\nb = 2;\nc = (1";
+        let err = Interpreter::new().eval(src).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnexpectedToken);
+        assert_eq!(err.span(), Some(Span::new(4, 5)));
+        assert_eq!(
+            err.to_string(),
+            format!("{} (and 1 more error)", err.message())
+        );
+
+        let related = err.related();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].code(), ErrorCode::UnexpectedEnd);
+        assert_eq!(related[0].span().map(|span| span.start), Some(src.len()));
+        assert_eq!(err.diagnostics().len(), 2);
+        assert!(matches!(err, KetamineError::Parse { ref rest, .. } if rest.len() == 1));
+    }
+
+    #[test]
+    fn describe_errors() {
+        let err = Interpreter::new().eval("x = 1;\nx = @").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::IllegalToken);
+        assert_eq!(err.span(), Some(Span::new(11, 12)));
+        assert!(err.related().is_empty());
+
+        let err = Interpreter::new().eval("x = 1 + ").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnexpectedEnd);
+        assert_eq!(err.to_string(), err.message());
+
+        let err = Interpreter::new().eval("x = 1;\ny = x + true").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidOperands);
+        assert_eq!(err.code().to_string(), "E0202");
+        assert_eq!(err.span(), Some(Span::new(11, 19)));
+        assert_eq!(err.diagnostics().len(), 1);
+    }
+}
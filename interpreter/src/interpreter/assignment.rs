@@ -1,51 +1,335 @@
-use crate::values::{Object, Value};
-use crate::{Eval, Evaluate, Interpreter};
-use parser::ast::{Assignment, AST};
+use crate::scope::Binding;
+use crate::values::{Array, Dictionary, Object, Value};
+use crate::{ErrorCode, Eval, Evaluate, Interpreter, KetamineError};
+use parser::ast::{
+    Assignment, Destructuring, Literal, Pattern, PatternElement, VariableDeclaration, VariableKind,
+    AST,
+};
 use parser::Pos;
 
-impl Evaluate for Pos<Assignment> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+use std::ops::Deref;
+use std::rc::Rc;
+
+impl Interpreter {
+    /// Assigns a value to a variable. If the variable doesn't exist yet, it is defined in the
+    /// scope of the current function call or in the globals.
+    pub(crate) fn assign_var(
+        &mut self,
+        name: Pos<String>,
+        value: Value,
+    ) -> Result<(), KetamineError> {
+        match self.scope.resolve(&name.value) {
+            Binding::Variable(scope) => self.scope.set_var(scope, name.value, value),
+            Binding::Unbound => {
+                self.scope.push_var(name.value, value, false);
+            }
+            binding => return Err(readonly_error(binding, name, "assign to")),
+        }
+        Ok(())
+    }
+
+    /// Defines a variable in the innermost scope, replacing a variable of the same name in that
+    /// scope unless it is a constant.
+    pub(crate) fn declare_var(
+        &mut self,
+        name: Pos<String>,
+        value: Value,
+        constant: bool,
+    ) -> Result<(), KetamineError> {
+        match self.scope.resolve_declaration(&name.value) {
+            Binding::Variable(..) | Binding::Unbound => {
+                self.scope.declare_var(name.value, value, constant);
+                Ok(())
+            }
+            binding => Err(readonly_error(binding, name, "redeclare")),
+        }
+    }
+
+    /// Binds the parts of `value` to the names in `pattern` using `bind`, or fails if the value
+    /// doesn't match the pattern. The defaults of the elements are evaluated after the elements
+    /// before them are matched, so they can refer to them.
+    pub(crate) fn bind_pattern<F>(
+        &mut self,
+        pattern: &Pos<Pattern>,
+        value: Value,
+        bind: &mut F,
+    ) -> Result<(), KetamineError>
+    where
+        F: FnMut(&mut Interpreter, Pos<String>, Value) -> Result<(), KetamineError>,
+    {
+        let mut bindings = vec![];
+        self.scope(true, |interpreter| {
+            interpreter.match_pattern(pattern, value, false, &mut bindings)
+        })??;
+        for (name, value) in bindings {
+            bind(self, name, value)?;
+        }
+        Ok(())
+    }
+
+    /// Matches `value` against `pattern`, collecting the names it binds in `bindings` and
+    /// defining them in the current scope. The inner error explains why the value doesn't match,
+    /// in which case some of the names may already be defined. Unless the match is `exact`,
+    /// missing elements and fields without a default are `null` and extra elements are ignored.
+    pub(crate) fn match_pattern(
+        &mut self,
+        pattern: &Pos<Pattern>,
+        value: Value,
+        exact: bool,
+        bindings: &mut Vec<(Pos<String>, Value)>,
+    ) -> Result<Result<(), KetamineError>, KetamineError> {
+        match (&pattern.value, value) {
+            (Pattern::Ident(ident), value) => {
+                self.scope.push_var(ident.0.clone(), value.clone(), true);
+                let name = Pos::new(pattern.start, pattern.end, ident.0.clone());
+                bindings.push((name, value));
+            }
+            (Pattern::Wildcard, _) => {}
+            (Pattern::Literal(literal), value) => {
+                let literal = match literal {
+                    Literal::Int(int) => Value::Integer(*int),
+                    Literal::Float(float) => Value::Float(*float),
+                    Literal::Bool(boolean) => Value::Boolean(*boolean),
+                    Literal::String(string) => Value::String(string.clone()),
+                    Literal::Null => Value::Null,
+                };
+                if !literal.equal(&value) {
+                    return Ok(Err(mismatch(pattern, &value)));
+                }
+            }
+            (Pattern::Type(name), value) => {
+                if value.type_name() != name {
+                    return Ok(Err(mismatch(pattern, &value)));
+                }
+            }
+            (Pattern::Or(alternatives), value) => {
+                for alternative in alternatives {
+                    let len = bindings.len();
+                    let matched = self.scope(true, |interpreter| {
+                        interpreter.match_pattern(alternative, value.clone(), exact, bindings)
+                    })?;
+                    if matched.is_ok() {
+                        for (name, value) in &bindings[len..] {
+                            self.scope.push_var(name.value.clone(), value.clone(), true);
+                        }
+                        return Ok(Ok(()));
+                    }
+                    bindings.truncate(len);
+                }
+                return Ok(Err(mismatch(pattern, &value)));
+            }
+            (Pattern::Array(elements), sequence @ (Value::Array(..) | Value::Range(..))) => {
+                let mut values: Box<dyn Iterator<Item = Value>> = match &sequence {
+                    Value::Range(range) => Box::new(range.iter()),
+                    Value::Array(array) => Box::new(array.0.deref().borrow().clone().into_iter()),
+                    _ => unreachable!(),
+                };
+                for element in elements {
+                    let value = match element.value.rest {
+                        true => Some(Value::Array(Array::new(values.by_ref().collect()))),
+                        false => self.value_or_default(&element.value, values.next(), exact)?,
+                    };
+                    let value = match value {
+                        Some(value) => value,
+                        None => return Ok(Err(mismatch(pattern, &sequence))),
+                    };
+                    let element = &element.value.pattern;
+                    if let Err(err) = self.match_pattern(element, value, exact, bindings)? {
+                        return Ok(Err(err));
+                    }
+                }
+                if exact && values.next().is_some() {
+                    return Ok(Err(mismatch(pattern, &sequence)));
+                }
+            }
+            (Pattern::Object(elements), Value::Dictionary(dict)) => {
+                let fields = dict.0.deref().borrow().clone();
+                let mut remaining = fields.clone();
+                for element in elements {
+                    let value = match &element.value.key {
+                        Some(key) => {
+                            remaining.remove(&key.value.0);
+                            let field = fields.get(&key.value.0).cloned();
+                            self.value_or_default(&element.value, field, exact)?
+                        }
+                        None => {
+                            let rest = Dictionary::default();
+                            for (key, value) in remaining.drain() {
+                                rest.insert(key, value);
+                            }
+                            Some(Value::Dictionary(rest))
+                        }
+                    };
+                    let value = match value {
+                        Some(value) => value,
+                        None => return Ok(Err(mismatch(pattern, &Value::Dictionary(dict)))),
+                    };
+                    let element = &element.value.pattern;
+                    if let Err(err) = self.match_pattern(element, value, exact, bindings)? {
+                        return Ok(Err(err));
+                    }
+                }
+            }
+            (Pattern::Array(..), other) | (Pattern::Object(..), other) => {
+                let expected = match pattern.value {
+                    Pattern::Array(..) => "an array",
+                    _ => "an object",
+                };
+                let msg = format!(
+                    "can't destructure a value of type {} as {}",
+                    other.type_name(),
+                    expected
+                );
+                return Ok(Err(KetamineError::runtime_with_code(
+                    ErrorCode::TypeMismatch,
+                    pattern.start,
+                    pattern.end,
+                    msg,
+                )));
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    /// Returns the value of an element, or its default if it is missing. Missing elements without
+    /// a default are `null`, or don't match at all if the match is `exact`.
+    fn value_or_default(
+        &mut self,
+        element: &PatternElement,
+        value: Option<Value>,
+        exact: bool,
+    ) -> Result<Option<Value>, KetamineError> {
+        match (value, &element.default) {
+            (Some(value), _) => Ok(Some(value)),
+            (None, Some(default)) => Ok(Some(default.eval(self)?.into_value())),
+            (None, None) if exact => Ok(None),
+            (None, None) => Ok(Some(Value::Null)),
+        }
+    }
+}
+
+fn mismatch(pattern: &Pos<Pattern>, value: &Value) -> KetamineError {
+    let msg = format!(
+        "the value `{}` doesn't match this pattern",
+        value.to_string()
+    );
+    KetamineError::runtime_with_code(ErrorCode::PatternMismatch, pattern.start, pattern.end, msg)
+}
+
+fn readonly_error(binding: Binding, name: Pos<String>, action: &str) -> KetamineError {
+    let kind = match binding {
+        Binding::Constant => "constant",
+        _ => "readonly global",
+    };
+    let msg = format!("can't {} {} `{}`", action, kind, name.value);
+    KetamineError::runtime_with_code(ErrorCode::ReadonlyAssignment, name.start, name.end, msg)
+}
+
+/// Anonymous functions are named after the variable they are assigned to.
+fn name_function(value: &mut Value, name: &str) {
+    if let Value::Function(function) = value {
+        function.name = Some(Rc::from(name));
+    }
+}
+
+impl Evaluate for Pos<&VariableDeclaration> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
+        let VariableDeclaration { kind, name, value } = self.value;
+        let value = match value {
+            Some(value) => {
+                let names_function =
+                    matches!(&*value.value, AST::Function(function) if function.name.is_none());
+                let mut value = match value.eval(interpreter)? {
+                    Eval::Value(value) => value,
+                    instruction => return Ok(instruction),
+                };
+                if names_function {
+                    name_function(&mut value, &name.value.0);
+                }
+                value
+            }
+            None => Value::Null,
+        };
+        let constant = kind.value == VariableKind::Const;
+        let name = Pos::new(name.start, name.end, name.value.0.clone());
+        interpreter.declare_var(name, value, constant)?;
+        Ok(Eval::Value(Value::Null))
+    }
+}
+
+impl Evaluate for Pos<&Destructuring> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
+        let Destructuring { pattern, value } = self.value;
+        let value = match value.eval(interpreter)? {
+            Eval::Value(value) => value,
+            instruction => return Ok(instruction),
+        };
+        interpreter.bind_pattern(pattern, value, &mut |interpreter, name, value| {
+            interpreter.assign_var(name, value)
+        })?;
+        Ok(Eval::Value(Value::Null))
+    }
+}
+
+impl Evaluate for Pos<&Assignment> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
             value: Assignment { receiver, value },
         } = self;
 
-        let value = match value.eval(interpreter)? {
+        let names_function = matches!(
+            (&*receiver.value, &*value.value),
+            (AST::Ident(..), AST::Function(function)) if function.name.is_none()
+        );
+        let mut value = match value.eval(interpreter)? {
             Eval::Value(value) => value,
             instruction => return Ok(instruction),
         };
+        if let (true, AST::Ident(ident)) = (names_function, &*receiver.value) {
+            name_function(&mut value, &ident.0);
+        }
 
-        match *receiver.value {
+        match &*receiver.value {
             AST::Ident(ident) => {
-                interpreter.scope.push_var(ident.0, value, false);
+                let name = Pos::new(receiver.start, receiver.end, ident.0.clone());
+                interpreter.assign_var(name, value)?;
             }
             AST::FieldAccess(access) => {
                 let object = access
                     .value
                     .eval(interpreter)?
                     .try_into_value()
-                    .map_err(|err| Pos::new(start, end, err))?;
+                    .map_err(|err| KetamineError::runtime(start, end, err))?;
                 object
-                    .set_field(access.field.value, value)
-                    .map_err(|err| Pos::new(start, end, err))?;
+                    .set_field(access.field.value.clone(), value)
+                    .map_err(|err| KetamineError::runtime(start, end, err))?;
             }
             AST::Index(index) => {
                 let idx_val = index
                     .value
                     .eval(interpreter)?
                     .try_into_value()
-                    .map_err(|err| Pos::new(start, end, err))?;
+                    .map_err(|err| KetamineError::runtime(start, end, err))?;
                 let idx_idx = index
                     .index
                     .eval(interpreter)?
                     .try_into_value()
-                    .map_err(|err| Pos::new(start, end, err))?;
+                    .map_err(|err| KetamineError::runtime(start, end, err))?;
                 idx_val
                     .set_index(idx_idx, value)
-                    .map_err(|err| Pos::new(start, end, err))?;
+                    .map_err(|err| KetamineError::runtime(start, end, err))?;
+            }
+            _ => {
+                let msg = "can only assign to variables, fields and indices";
+                return Err(KetamineError::runtime_with_code(
+                    ErrorCode::InvalidAssignment,
+                    receiver.start,
+                    receiver.end,
+                    msg,
+                ));
             }
-            other => panic!("{:?}", other),
         };
         Ok(Eval::Value(Value::Null))
     }
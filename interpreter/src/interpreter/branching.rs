@@ -1,11 +1,11 @@
 use lexer::Pos;
-use parser::ast::{If, IfBranch};
+use parser::ast::{If, IfBranch, Match, MatchArm, AST};
 
 use crate::values::{Object, Value};
-use crate::{Eval, Evaluate, Interpreter};
+use crate::{ErrorCode, Eval, Evaluate, Interpreter, KetamineError};
 
-impl Evaluate for Pos<If> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&If> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             value:
                 If {
@@ -25,7 +25,7 @@ impl Evaluate for Pos<If> {
             }
         }
         if let Some(else_branch) = else_branch {
-            else_branch.eval(interpreter)
+            interpreter.scope(true, |interpreter| else_branch.eval(interpreter))
         } else {
             Ok(Eval::Value(Value::Null))
         }
@@ -33,32 +33,101 @@ impl Evaluate for Pos<If> {
 }
 
 fn evaluate_if(
-    branch: Pos<IfBranch>,
+    branch: &Pos<IfBranch>,
     scope: &mut Interpreter,
-) -> Result<Option<Eval>, Pos<String>> {
+) -> Result<Option<Eval>, KetamineError> {
     let Pos {
         value: IfBranch { condition, body },
         ..
     } = branch;
 
+    if evaluate_condition(condition, scope)? {
+        let result = scope.scope(true, |scope| body.eval(scope))?;
+        Ok(Some(result))
+    } else {
+        Ok(None)
+    }
+}
+
+fn evaluate_condition(
+    condition: &Pos<Box<AST>>,
+    scope: &mut Interpreter,
+) -> Result<bool, KetamineError> {
     let cond_start = condition.start;
     let cond_end = condition.end;
     let cond = condition
         .eval(scope)?
         .try_into_value()
-        .map_err(|err| Pos::new(cond_start, cond_end, err))?;
+        .map_err(|err| KetamineError::runtime(cond_start, cond_end, err))?;
 
-    let condition = match cond {
-        Value::Boolean(cond) => cond,
+    match cond {
+        Value::Boolean(cond) => Ok(cond),
         other => {
             let msg = format!("expected boolean, got {}", other.type_name());
-            return Err(Pos::new(cond_start, cond_end, msg));
+            Err(KetamineError::runtime(cond_start, cond_end, msg))
+        }
+    }
+}
+
+impl Evaluate for Pos<&Match> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
+        let Pos {
+            start,
+            end,
+            value: Match { value, arms },
+        } = self;
+
+        let value = match value.eval(interpreter)? {
+            Eval::Value(value) => value,
+            instruction => return Ok(instruction),
+        };
+        for arm in arms {
+            let result = interpreter.scope(true, |interpreter| {
+                evaluate_arm(arm, value.clone(), interpreter)
+            })?;
+            if let Some(res) = result {
+                return Ok(res);
+            }
+        }
+        let msg = format!(
+            "no arm of this `match` matches the value `{}`",
+            value.to_string()
+        );
+        Err(KetamineError::runtime_with_code(
+            ErrorCode::PatternMismatch,
+            start,
+            end,
+            msg,
+        ))
+    }
+}
+
+/// Evaluates the body of the arm if the value matches its pattern exactly and the guard holds.
+/// The names bound by the pattern are defined in the current scope.
+fn evaluate_arm(
+    arm: &Pos<MatchArm>,
+    value: Value,
+    scope: &mut Interpreter,
+) -> Result<Option<Eval>, KetamineError> {
+    let Pos {
+        value: MatchArm {
+            pattern,
+            guard,
+            body,
+        },
+        ..
+    } = arm;
+
+    if scope
+        .match_pattern(pattern, value, true, &mut vec![])?
+        .is_err()
+    {
+        return Ok(None);
+    }
+    if let Some(guard) = guard {
+        if !evaluate_condition(guard, scope)? {
+            return Ok(None);
         }
-    };
-    if condition {
-        let result = body.eval(scope)?;
-        Ok(Some(result))
-    } else {
-        Ok(None)
     }
+    Ok(Some(body.eval(scope)?))
 }
@@ -1,10 +1,10 @@
 use crate::values::{Object, Value};
-use crate::{Eval, Evaluate, Interpreter};
+use crate::{ErrorCode, Eval, Evaluate, Interpreter, KetamineError};
 use lexer::Pos;
 use parser::ast::{BinaryOperation, BinaryOperator, UnaryOperation, UnaryOperator};
 
-impl Evaluate for Pos<BinaryOperation> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&BinaryOperation> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
@@ -15,26 +15,13 @@ impl Evaluate for Pos<BinaryOperation> {
         let lhs = lhs
             .eval(interpreter)?
             .try_into_value()
-            .map_err(|err| Pos::new(start, end, err))?;
+            .map_err(|err| KetamineError::runtime(start, end, err))?;
         let rhs = rhs
             .eval(interpreter)?
             .try_into_value()
-            .map_err(|err| Pos::new(start, end, err))?;
+            .map_err(|err| KetamineError::runtime(start, end, err))?;
 
-        let result = match &operator {
-            BinaryOperator::Add => lhs.plus(&rhs),
-            BinaryOperator::Sub => lhs.minus(&rhs),
-            BinaryOperator::Mul => lhs.multiply(&rhs),
-            BinaryOperator::Div => lhs.divide(&rhs),
-            BinaryOperator::Eq => Ok(Value::Boolean(lhs.equal(&rhs))),
-            BinaryOperator::NotEq => Ok(Value::Boolean(!lhs.equal(&rhs))),
-            BinaryOperator::GreaterThan => Ok(Value::Boolean(lhs.greater_than(&rhs))),
-            BinaryOperator::LessThan => Ok(Value::Boolean(lhs.less_than(&rhs))),
-            BinaryOperator::GreaterEqThan => Ok(Value::Boolean(!lhs.less_than(&rhs))),
-            BinaryOperator::LessEqThan => Ok(Value::Boolean(!lhs.greater_than(&rhs))),
-        };
-
-        let result = result.map_err(|_| {
+        let result = apply_binary(operator, &lhs, &rhs).map_err(|_| {
             let (v1, v2) = operator.verb();
             let msg = format!(
                 "can't {} {} {} {}",
@@ -43,19 +30,15 @@ impl Evaluate for Pos<BinaryOperation> {
                 v2,
                 lhs.type_name()
             );
-            Pos {
-                start,
-                end,
-                value: msg,
-            }
+            KetamineError::runtime_with_code(ErrorCode::InvalidOperands, start, end, msg)
         })?;
 
         Ok(Eval::Value(result))
     }
 }
 
-impl Evaluate for Pos<UnaryOperation> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&UnaryOperation> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
@@ -65,24 +48,54 @@ impl Evaluate for Pos<UnaryOperation> {
         let value = value
             .eval(interpreter)?
             .try_into_value()
-            .map_err(|err| Pos::new(start, end, err))?;
+            .map_err(|err| KetamineError::runtime(start, end, err))?;
 
-        match op.value {
-            UnaryOperator::Minus => match value {
-                Value::Integer(int) => Ok(Eval::Value(Value::Integer(-int))),
-                Value::Float(float) => Ok(Eval::Value(Value::Float(-float))),
-                _ => {
-                    let msg = format!("can't apply unary minus to {}", value.type_name());
-                    Err(Pos::new(start, end, msg))
-                }
-            },
-            UnaryOperator::Negate => match value {
-                Value::Boolean(boolean) => Ok(Eval::Value(Value::Boolean(!boolean))),
-                _ => {
-                    let msg = format!("can't negate {}", value.type_name());
-                    Err(Pos::new(start, end, msg))
-                }
-            },
+        match apply_unary(&op.value, &value) {
+            Some(result) => Ok(Eval::Value(result)),
+            None => {
+                let msg = match op.value {
+                    UnaryOperator::Minus => {
+                        format!("can't apply unary minus to {}", value.type_name())
+                    }
+                    UnaryOperator::Negate => format!("can't negate {}", value.type_name()),
+                };
+                Err(KetamineError::runtime_with_code(
+                    ErrorCode::InvalidOperands,
+                    start,
+                    end,
+                    msg,
+                ))
+            }
         }
     }
 }
+
+/// Applies a binary operator to two values, failing if the operator is not defined for them.
+pub(crate) fn apply_binary(
+    operator: BinaryOperator,
+    lhs: &Value,
+    rhs: &Value,
+) -> Result<Value, ()> {
+    match operator {
+        BinaryOperator::Add => lhs.plus(rhs),
+        BinaryOperator::Sub => lhs.minus(rhs),
+        BinaryOperator::Mul => lhs.multiply(rhs),
+        BinaryOperator::Div => lhs.divide(rhs),
+        BinaryOperator::Eq => Ok(Value::Boolean(lhs.equal(rhs))),
+        BinaryOperator::NotEq => Ok(Value::Boolean(!lhs.equal(rhs))),
+        BinaryOperator::GreaterThan => Ok(Value::Boolean(lhs.greater_than(rhs))),
+        BinaryOperator::LessThan => Ok(Value::Boolean(lhs.less_than(rhs))),
+        BinaryOperator::GreaterEqThan => Ok(Value::Boolean(!lhs.less_than(rhs))),
+        BinaryOperator::LessEqThan => Ok(Value::Boolean(!lhs.greater_than(rhs))),
+    }
+}
+
+/// Applies a unary operator to a value, returning `None` if it is not defined for the value.
+pub(crate) fn apply_unary(operator: &UnaryOperator, value: &Value) -> Option<Value> {
+    match (operator, value) {
+        (UnaryOperator::Minus, Value::Integer(int)) => Some(Value::Integer(-int)),
+        (UnaryOperator::Minus, Value::Float(float)) => Some(Value::Float(-float)),
+        (UnaryOperator::Negate, Value::Boolean(boolean)) => Some(Value::Boolean(!boolean)),
+        _ => None,
+    }
+}
@@ -1,10 +1,10 @@
 use crate::values::Value;
-use crate::{Eval, Evaluate, Interpreter};
+use crate::{Eval, Evaluate, Interpreter, KetamineError};
 use parser::ast::{Break, Return};
 use parser::Pos;
 
-impl Evaluate for Pos<Return> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&Return> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
@@ -15,14 +15,14 @@ impl Evaluate for Pos<Return> {
             Some(value) => value
                 .eval(interpreter)?
                 .try_into_value()
-                .map_err(|err| Pos::new(start, end, err))?,
+                .map_err(|err| KetamineError::runtime(start, end, err))?,
         };
         Ok(Eval::Return(value))
     }
 }
 
-impl Evaluate for Pos<Break> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&Break> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
@@ -33,7 +33,7 @@ impl Evaluate for Pos<Break> {
             Some(value) => value
                 .eval(interpreter)?
                 .try_into_value()
-                .map_err(|err| Pos::new(start, end, err))?,
+                .map_err(|err| KetamineError::runtime(start, end, err))?,
         };
         Ok(Eval::Break(value))
     }
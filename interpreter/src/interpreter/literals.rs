@@ -1,10 +1,10 @@
-use crate::values::{Array, Dictionary, Value};
-use crate::{Eval, Evaluate, Interpreter, ObjectConversion};
+use crate::values::{self, Array, Dictionary, Value};
+use crate::{ErrorCode, Eval, Evaluate, Interpreter, KetamineError, ObjectConversion};
 use lexer::Pos;
 use parser::ast::{List, Object, Range};
 
-impl Evaluate for Pos<List> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&List> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
@@ -15,7 +15,7 @@ impl Evaluate for Pos<List> {
             let element = element
                 .eval(interpreter)?
                 .try_into_value()
-                .map_err(|err| Pos::new(start, end, err))?;
+                .map_err(|err| KetamineError::runtime(start, end, err))?;
             elements.push(element);
         }
         let array = Array::new(elements);
@@ -23,8 +23,8 @@ impl Evaluate for Pos<List> {
     }
 }
 
-impl Evaluate for Pos<Object> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&Object> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
@@ -35,28 +35,40 @@ impl Evaluate for Pos<Object> {
             let value = v
                 .eval(interpreter)?
                 .try_into_value()
-                .map_err(|err| Pos::new(start, end, err))?;
-            if let Some(..) = object.insert(k.value.0, value) {
+                .map_err(|err| KetamineError::runtime(start, end, err))?;
+            if let Some(..) = object.insert(k.value.0.clone(), value) {
                 let msg = "duplicate key in object literal".to_owned();
-                return Err(Pos::new(start, end, msg));
+                return Err(KetamineError::runtime_with_code(
+                    ErrorCode::DuplicateKey,
+                    start,
+                    end,
+                    msg,
+                ));
             }
         }
         Ok(Eval::Value(Value::Dictionary(object)))
     }
 }
 
-impl Evaluate for Pos<Range> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&Range> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
-            value: Range { from, to },
+            value:
+                Range {
+                    from,
+                    to,
+                    inclusive,
+                },
         } = self;
         let from = from.eval(interpreter)?.into_value();
-        let from = i64::try_convert_from(&from).map_err(|msg| Pos::new(start, end, msg))?;
+        let from =
+            i64::try_convert_from(&from).map_err(|msg| KetamineError::runtime(start, end, msg))?;
         let to = to.eval(interpreter)?.into_value();
-        let to = i64::try_convert_from(&to).map_err(|msg| Pos::new(start, end, msg))?;
-        let array = (from..to).map(Value::Integer).collect::<Vec<_>>();
-        Ok(Eval::Value(Value::Array(Array::new(array))))
+        let to =
+            i64::try_convert_from(&to).map_err(|msg| KetamineError::runtime(start, end, msg))?;
+        let range = values::Range::new(from, to, *inclusive);
+        Ok(Eval::Value(Value::Range(range)))
     }
 }
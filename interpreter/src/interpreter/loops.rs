@@ -1,10 +1,10 @@
 use crate::values::{Object, Value};
-use crate::{Eval, Evaluate, Interpreter};
+use crate::{Eval, Evaluate, Interpreter, KetamineError};
 use lexer::Pos;
 use parser::ast::{ForLoop, WhileLoop};
 
-impl Evaluate for Pos<ForLoop> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&ForLoop> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
@@ -21,12 +21,16 @@ impl Evaluate for Pos<ForLoop> {
             other => return Ok(other),
         }
         .iterator()
-        .map_err(|err| Pos::new(start, end, err))?;
+        .map_err(|err| KetamineError::runtime(start, end, err))?;
 
         interpreter.scope(true, |interpreter| {
             for element in iterator {
-                interpreter.scope.push_var(&binding.value.0, element, true);
-                match body.clone().eval(interpreter)? {
+                interpreter.bind_pattern(binding, element, &mut |interpreter, name, value| {
+                    interpreter.scope.push_var(name.value, value, true);
+                    Ok(())
+                })?;
+                // Every iteration has its own scope for the variables declared in the body.
+                match interpreter.scope(true, |interpreter| body.eval(interpreter))? {
                     ret @ Eval::Return(..) => return Ok(ret),
                     Eval::Break(val) => return Ok(Eval::Value(val)),
                     Eval::Continue => continue,
@@ -38,15 +42,15 @@ impl Evaluate for Pos<ForLoop> {
     }
 }
 
-impl Evaluate for Pos<WhileLoop> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&WhileLoop> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             value: WhileLoop { condition, body },
             ..
         } = self;
 
-        while let Value::Boolean(true) = condition.clone().eval(interpreter)?.into_value() {
-            match body.clone().eval(interpreter)? {
+        while let Value::Boolean(true) = condition.eval(interpreter)?.into_value() {
+            match interpreter.scope(true, |interpreter| body.eval(interpreter))? {
                 ret @ Eval::Return(..) => return Ok(ret),
                 Eval::Break(val) => return Ok(Eval::Value(val)),
                 Eval::Continue => continue,
@@ -1,9 +1,12 @@
-use crate::scope::ScopeStack;
+use crate::library::{Capability, Library, MissingCapability, Profile};
+use crate::scope::{Global, ScopeStack};
 use crate::values::Object;
-use crate::values::{Dictionary, HasPrototype, NativeFunction, Value};
-use crate::{Evaluate, HasTypeName, ObjectConversion};
-use parser::ast::Statement;
-use parser::{Parse, Pos};
+use crate::values::{Dictionary, HasPrototype, IntoArgs, NativeFunction, Value};
+use crate::{Evaluate, Frame, HasTypeName, KetamineError, ObjectConversion, Script};
+use report::{SourceId, SourceMap};
+
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
 
 mod assignment;
 mod branching;
@@ -12,72 +15,324 @@ mod instructions;
 mod literals;
 mod loops;
 
+pub(crate) use expression::{apply_binary, apply_unary};
+
 pub struct Interpreter {
-    pub scope: ScopeStack,
+    pub(crate) scope: ScopeStack,
     pub(crate) integer_proto: Dictionary,
     pub(crate) float_proto: Dictionary,
     pub(crate) boolean_proto: Dictionary,
     pub(crate) string_proto: Dictionary,
     pub(crate) array_proto: Dictionary,
+    pub(crate) range_proto: Dictionary,
     pub(crate) object_proto: Dictionary,
     pub(crate) function_proto: Dictionary,
     pub(crate) null_proto: Dictionary,
+    /// Whether code is optimized before it is evaluated.
+    optimize: bool,
+    /// Whether calling a script function with the wrong number of arguments is an error.
+    pub(crate) strict_arity: bool,
+    profile: Profile,
+    /// The capabilities which the globals and prototype functions registered right now may need.
+    /// They are only registered if the profile grants all of them.
+    required: Vec<Capability>,
+    /// The source code of the compiled programs. The positions in the syntax trees, and
+    /// therefore in errors, are offsets into this map.
+    pub(crate) sources: SourceMap,
+    /// The handles of the files in `sources`. Scripts and functions hold them, and a file is
+    /// removed once its handle is dropped.
+    source_handles: HashMap<SourceId, Weak<SourceId>>,
+    /// The calls of script functions which are currently active, outermost first.
+    pub(crate) call_stack: Vec<Frame>,
 }
 
 impl Interpreter {
+    /// Creates an interpreter which grants every capability to its libraries.
     pub fn new() -> Self {
-        let mut scope = ScopeStack::readonly_root();
-
-        let integer_proto = Dictionary::default();
-        let float_proto = Dictionary::default();
-        let boolean_proto = Dictionary::default();
-        let string_proto = Dictionary::default();
-        let array_proto = Dictionary::default();
-        let object_proto = Dictionary::default();
-        let function_proto = Dictionary::default();
-        let null_proto = Dictionary::default();
-
-        scope.push_var("$integer", Value::Dictionary(integer_proto.clone()), true);
-        scope.push_var("$float", Value::Dictionary(float_proto.clone()), true);
-        scope.push_var("$boolean", Value::Dictionary(boolean_proto.clone()), true);
-        scope.push_var("$string", Value::Dictionary(string_proto.clone()), true);
-        scope.push_var("$array", Value::Dictionary(array_proto.clone()), true);
-        scope.push_var("$object", Value::Dictionary(object_proto.clone()), true);
-        scope.push_var("$function", Value::Dictionary(function_proto.clone()), true);
-        scope.push_var("$null", Value::Dictionary(null_proto.clone()), true);
-
-        scope.push_scope(false);
-
-        Interpreter {
-            scope,
-            integer_proto,
-            float_proto,
-            boolean_proto,
-            string_proto,
-            array_proto,
-            object_proto,
-            function_proto,
-            null_proto,
+        Interpreter::with_profile(Profile::trusted())
+    }
+
+    /// Creates an interpreter which only grants the capabilities of the profile to its
+    /// libraries.
+    pub fn with_profile(profile: Profile) -> Self {
+        let mut interpreter = Interpreter {
+            scope: ScopeStack::new_global(),
+            integer_proto: Dictionary::default(),
+            float_proto: Dictionary::default(),
+            boolean_proto: Dictionary::default(),
+            string_proto: Dictionary::default(),
+            array_proto: Dictionary::default(),
+            range_proto: Dictionary::default(),
+            object_proto: Dictionary::default(),
+            function_proto: Dictionary::default(),
+            null_proto: Dictionary::default(),
+            optimize: false,
+            strict_arity: false,
+            profile,
+            required: vec![],
+            sources: SourceMap::new(),
+            source_handles: HashMap::new(),
+            call_stack: vec![],
+        };
+
+        let prototypes = interpreter
+            .prototypes()
+            .map(|(name, proto)| (name, proto.clone()));
+        for (name, proto) in prototypes.iter() {
+            let proto = Value::Dictionary(Dictionary::clone(proto));
+            interpreter.scope.set_global(name.to_string(), proto, true);
+        }
+        interpreter
+    }
+
+    /// The prototypes of the types, together with the names of the globals holding them.
+    pub(crate) fn prototypes(&self) -> [(&'static str, &Dictionary); 9] {
+        [
+            ("$integer", &self.integer_proto),
+            ("$float", &self.float_proto),
+            ("$boolean", &self.boolean_proto),
+            ("$string", &self.string_proto),
+            ("$array", &self.array_proto),
+            ("$range", &self.range_proto),
+            ("$object", &self.object_proto),
+            ("$function", &self.function_proto),
+            ("$null", &self.null_proto),
+        ]
+    }
+
+    /// Enables the optimizer, which simplifies the code before evaluating it. It folds constant
+    /// expressions and removes dead branches; see the `optimizer` module.
+    pub fn with_optimizer(mut self, enabled: bool) -> Self {
+        self.optimize = enabled;
+        self
+    }
+
+    /// Makes calls of script functions fail if they pass too few or too many arguments.
+    /// Parameters with a default value are optional, and a rest parameter accepts any number
+    /// of arguments. Without it, extra arguments are dropped and missing ones are `null`.
+    pub fn with_strict_arity(mut self, enabled: bool) -> Self {
+        self.strict_arity = enabled;
+        self
+    }
+
+    pub fn eval(&mut self, src: &str) -> Result<Value, KetamineError> {
+        let script = self.compile(src)?;
+        self.run(&script)
+    }
+
+    /// Like `eval`, but errors are reported against a file with the given name.
+    pub fn eval_file(
+        &mut self,
+        name: impl Into<String>,
+        src: &str,
+    ) -> Result<Value, KetamineError> {
+        let script = self.compile_file(name, src)?;
+        self.run(&script)
+    }
+
+    /// Lexes, parses and checks a program, and optimizes it if the optimizer is enabled.
+    pub fn compile(&mut self, src: &str) -> Result<Script, KetamineError> {
+        self.compile_source(None, src)
+    }
+
+    /// Like `compile`, but errors are reported against a file with the given name.
+    pub fn compile_file(
+        &mut self,
+        name: impl Into<String>,
+        src: &str,
+    ) -> Result<Script, KetamineError> {
+        self.compile_source(Some(name.into()), src)
+    }
+
+    fn compile_source(&mut self, name: Option<String>, src: &str) -> Result<Script, KetamineError> {
+        let source = self.add_source(name, src);
+        let tokens = lexer::tokenize_at(src, self.sources[*source].offset())?;
+        let (code, mut errors) = parser::parse_recovering(&tokens);
+        if !errors.is_empty() {
+            let first = errors.remove(0);
+            return Err(KetamineError::Parse {
+                first,
+                rest: errors,
+            });
+        }
+        let mut statements = code.value;
+        crate::checks::check(&statements)?;
+        if self.optimize {
+            statements = crate::optimizer::optimize(statements);
+        }
+        Ok(Script {
+            code: Rc::new(statements),
+            source,
+        })
+    }
+
+    /// The source code of the programs compiled by this interpreter. Use it to render errors
+    /// using `report::Renderer::render_sources_string`.
+    ///
+    /// A file is kept as long as a `Script` or a function compiled from it exists. Once that is
+    /// no longer the case, it's removed when the next program is compiled, so errors should be
+    /// rendered before compiling more code.
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    /// Adds a file to the sources, which is kept while the returned handle or a clone of it
+    /// exists.
+    pub(crate) fn add_source(&mut self, name: Option<String>, src: &str) -> Rc<SourceId> {
+        let sources = &mut self.sources;
+        self.source_handles.retain(|id, handle| {
+            let used = handle.strong_count() > 0;
+            if !used {
+                sources.remove(*id);
+            }
+            used
+        });
+        let source = Rc::new(self.sources.add(name, src));
+        self.source_handles.insert(*source, Rc::downgrade(&source));
+        source
+    }
+
+    /// The handle of the file containing `offset`, e.g. for a function defined at the offset.
+    pub(crate) fn source_at(&self, offset: usize) -> Option<Rc<SourceId>> {
+        let id = self.sources.lookup(offset)?;
+        self.source_handles.get(&id)?.upgrade()
+    }
+
+    /// Runs a compiled program.
+    pub fn run(&mut self, script: &Script) -> Result<Value, KetamineError> {
+        Ok(script.code.eval(self)?.into_value())
+    }
+
+    /// The calls of script functions which are currently active, outermost first. Native
+    /// functions can use it to find out where they were called from.
+    pub fn call_stack(&self) -> &[Frame] {
+        &self.call_stack
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Whether libraries may register functions which need the capability.
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.profile.allows(capability)
+    }
+
+    /// Registers the globals and prototype functions of a library. Everything the library
+    /// registers is assumed to need all of its capabilities, and fails with `MissingCapability`
+    /// unless the profile grants them. Libraries can use `requiring` to register functions which need fewer.
+    pub fn register(&mut self, library: &dyn Library) {
+        let required = std::mem::replace(&mut self.required, library.capabilities().to_vec());
+        library.register(self);
+        self.required = required;
+    }
+
+    /// Registers globals and prototype functions which only need the given capabilities, so
+    /// they are registered even if the profile doesn't grant the other capabilities of the
+    /// library.
+    pub fn requiring<T>(
+        &mut self,
+        capabilities: &[Capability],
+        register: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let required = std::mem::replace(&mut self.required, capabilities.to_vec());
+        let result = register(self);
+        self.required = required;
+        result
+    }
+
+    /// Checks that the profile grants the capabilities needed by what is registered right now.
+    fn may_register(&self) -> Result<(), MissingCapability> {
+        match self
+            .required
+            .iter()
+            .find(|capability| !self.allows(**capability))
+        {
+            Some(capability) => Err(MissingCapability(*capability)),
+            None => Ok(()),
         }
     }
 
-    pub fn eval(&mut self, src: &str) -> Result<Value, Pos<String>> {
-        let tokens = lexer::tokenize(src)
-            .map_err(|err| Pos::new(err.0, err.0, "could not tokenize".to_owned()))?;
-        let mut tokens = &tokens[..];
-        let statements = <Vec<Pos<Statement>>>::parse(0, &mut tokens)
-            .map_err(|err| err.map(|x| x.into_inner().to_string()))?
-            .value;
+    /// Defines a global variable which scripts can read and reassign, returning the previous
+    /// value of the global.
+    pub fn set_global(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Object,
+    ) -> Result<Option<Value>, MissingCapability> {
+        self.may_register()?;
+        Ok(self
+            .scope
+            .set_global(name.into(), value.into_value(), false))
+    }
+
+    /// Defines a global variable which scripts can read, but not reassign. The value itself can
+    /// still be modified if it is an array or an object.
+    pub fn set_readonly_global(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Object,
+    ) -> Result<Option<Value>, MissingCapability> {
+        self.may_register()?;
+        Ok(self.scope.set_global(name.into(), value.into_value(), true))
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.scope.get_global(name).cloned()
+    }
 
-        Ok(statements.eval(self)?.into_value())
+    pub fn remove_global(&mut self, name: &str) -> Option<Value> {
+        self.scope.remove_global(name)
     }
 
-    pub fn prototype_function<O, I, F>(&mut self, ident: I, function: F)
+    /// Returns all global variables, including those defined by libraries and the prototypes
+    /// of the types, in no particular order.
+    pub fn globals(&self) -> impl Iterator<Item = Global<'_>> {
+        self.scope.globals()
+    }
+
+    /// Looks up a global function, defined either by a script or by a library.
+    pub fn get_function(&self, name: &str) -> Option<Value> {
+        match self.get_global(name)? {
+            function @ Value::Function(..) | function @ Value::NativeFunction(..) => Some(function),
+            _ => None,
+        }
+    }
+
+    /// Calls a function from Rust, converting the arguments into values and the returned value
+    /// into `R`. Use `Value` as `R` to accept any value.
+    pub fn call_function<A, R>(
+        &mut self,
+        function: &Value,
+        this: Value,
+        args: A,
+    ) -> Result<R, KetamineError>
+    where
+        A: IntoArgs,
+        R: ObjectConversion + HasTypeName,
+    {
+        match function {
+            Value::Function(..) | Value::NativeFunction(..) => {}
+            other => {
+                let msg = format!("can't call a value of type {}", other.type_name());
+                return Err(msg.into());
+            }
+        }
+        let result = function.call(0, 0, self, this, args.into_args())?;
+        Ok(R::try_get_as(result)?)
+    }
+
+    pub fn prototype_function<O, I, F>(
+        &mut self,
+        ident: I,
+        function: F,
+    ) -> Result<(), MissingCapability>
     where
         O: Object + HasPrototype + HasTypeName + ObjectConversion,
         I: Into<String>,
-        F: Fn(&mut Interpreter, O, Vec<Value>) -> Result<Value, String> + 'static,
+        F: Fn(&mut Interpreter, O, Vec<Value>) -> Result<Value, KetamineError> + 'static,
     {
+        self.may_register()?;
         let proto = O::get_prototype(self);
         let function = NativeFunction::new(
             move |inter: &mut Interpreter, this: Value, args: Vec<Value>| {
@@ -85,15 +340,18 @@ impl Interpreter {
             },
         );
         proto.insert(ident.into(), Value::NativeFunction(function));
+        Ok(())
     }
 
-    pub fn prototype_field<O, I>(&mut self, ident: I, value: Value)
+    pub fn prototype_field<O, I>(&mut self, ident: I, value: Value) -> Result<(), MissingCapability>
     where
         O: Object + HasPrototype + ObjectConversion,
         I: Into<String>,
     {
+        self.may_register()?;
         let proto = O::get_prototype(self);
         proto.insert(ident.into(), value);
+        Ok(())
     }
 
     pub(crate) fn get_proto(&self, val: &Value) -> &Dictionary {
@@ -103,6 +361,7 @@ impl Interpreter {
             Value::Float(_) => &self.float_proto,
             Value::Boolean(_) => &self.boolean_proto,
             Value::Array(_) => &self.array_proto,
+            Value::Range(_) => &self.range_proto,
             Value::Dictionary(_) => &self.object_proto,
             Value::Function(_) => &self.function_proto,
             Value::NativeFunction(_) => &self.function_proto,
@@ -1,31 +1,397 @@
 #![deny(unused_mut, unreachable_patterns)]
 
 use lexer::Pos;
-use parser::ast::{Call, FieldAccess, Ident, Index, Parentheses, Statement, AST};
+use parser::ast::{Call, FieldAccess, Ident, Index, Invalid, Parentheses, Spread, Statement, AST};
 
 use std::fmt::Debug;
+use std::rc::Rc;
 
+mod checks;
+mod error;
 mod interpreter;
 pub mod library;
+pub mod optimizer;
 mod scope;
+mod script;
 #[cfg(feature = "serialize")]
 mod serialization;
+#[cfg(feature = "snapshot")]
+mod snapshot;
 mod values;
 
+pub use crate::error::*;
 pub use crate::interpreter::*;
 pub use crate::scope::*;
+pub use crate::script::*;
+#[cfg(feature = "snapshot")]
+pub use crate::snapshot::SnapshotError;
 pub use crate::values::*;
-use std::rc::Rc;
 
 #[cfg(test)]
 mod tests {
-    use crate::library::Library;
     use crate::values::Object;
 
-    use crate::Interpreter;
+    use crate::{ErrorCode, Interpreter, Value};
+    use report::Renderer;
     use std::io::stdout;
     use std::time::Instant;
 
+    #[test]
+    fn call_script_function() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register(&crate::library::StandardLibrary);
+        interpreter
+            .eval("greet = function(name, n) { \"hello \" + name + \" #\" + n * 2 };")
+            .unwrap();
+        let greet = interpreter.get_function("greet").unwrap();
+        let greeting: String = interpreter
+            .call_function(&greet, Value::Null, ("world".to_owned(), 2))
+            .unwrap();
+        assert_eq!(greeting, "hello world #4");
+
+        let err = interpreter
+            .call_function::<_, i64>(&greet, Value::Null, ("world".to_owned(), 1))
+            .unwrap_err();
+        assert_eq!(
+            err.message(),
+            "mismatched type: expected integer, got string"
+        );
+        assert!(interpreter.get_function("missing").is_none());
+        assert!(interpreter.get_function("$string").is_none());
+    }
+
+    #[test]
+    fn globals() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("counter", 1).unwrap();
+        interpreter.set_readonly_global("limit", 10).unwrap();
+        interpreter
+            .eval("counter = counter + limit; f = function() { limit = 0; }; g = function(limit) { limit = 1; limit }")
+            .unwrap();
+        assert_eq!(interpreter.get_global("counter"), Some(Value::Integer(11)));
+        assert_eq!(interpreter.eval("g(5)").unwrap(), Value::Integer(1));
+
+        let err = interpreter.eval("f()").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ReadonlyAssignment);
+        assert_eq!(err.message(), "can't assign to readonly global `limit`");
+        assert!(interpreter.eval("$string = 1").is_err());
+
+        let global = interpreter.globals().find(|global| global.name == "limit");
+        assert!(global.unwrap().readonly);
+        assert_eq!(
+            interpreter.set_global("limit", 20),
+            Ok(Some(Value::Integer(10)))
+        );
+        interpreter.eval("limit = limit + 1").unwrap();
+        assert_eq!(interpreter.remove_global("limit"), Some(Value::Integer(21)));
+        assert_eq!(interpreter.get_global("limit"), None);
+    }
+
+    #[test]
+    fn report_errors_against_their_source() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register(&crate::library::StandardLibrary);
+        interpreter
+            .eval_file("lib.ket", "add = function(a, b) {\n    a + b\n};")
+            .unwrap();
+        let err = interpreter
+            .eval_file("main.ket", "add(1, 2);\nadd(true, 1)")
+            .unwrap_err();
+        let sources = interpreter.sources();
+        let location = sources.location(err.span().unwrap().start).unwrap();
+        assert_eq!(location.to_string(), "lib.ket:2:5");
+        let rendered = Renderer::plain().render_sources_string(&err.diagnostic(), sources);
+        assert!(rendered.contains(" --> lib.ket:2:5\n"), "{}", rendered);
+
+        let err = interpreter.eval("x = 1;\neval(\"1 +\")").unwrap_err();
+        let location = interpreter.sources().location(err.span().unwrap().start);
+        assert_eq!(location.unwrap().to_string(), "<eval>:1:4");
+
+        let err = interpreter.eval("eval(\"null()\")").unwrap_err();
+        let location = interpreter.sources().location(err.span().unwrap().start);
+        assert_eq!(location.unwrap().to_string(), "<eval>:1:1");
+    }
+
+    #[test]
+    fn drop_unused_sources() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register(&crate::library::StandardLibrary);
+        interpreter.eval("inc = function(x) { x + 1 };").unwrap();
+        interpreter.eval("1").unwrap();
+        let files = interpreter.sources().files().count();
+        for _ in 0..100 {
+            interpreter.eval("1").unwrap();
+        }
+        interpreter
+            .eval("i = 0; while (i < 100) { eval(\"i = i + 1\") }")
+            .unwrap();
+        // Unused sources are dropped when the next program is compiled.
+        interpreter.eval("1").unwrap();
+        assert_eq!(interpreter.sources().files().count(), files);
+
+        // The source of a function is kept while the function exists.
+        let err = interpreter.eval("inc(true)").unwrap_err();
+        let location = interpreter.sources().location(err.span().unwrap().start);
+        assert_eq!(location.unwrap().to_string(), "1:21");
+        interpreter.eval("inc = null;").unwrap();
+        interpreter.eval("1").unwrap();
+        assert_eq!(interpreter.sources().files().count(), files - 1);
+    }
+
+    #[test]
+    fn attach_call_stack_to_errors() {
+        let mut interpreter = Interpreter::new();
+        let src = "add = function(a, b) { a + b };\n\
+                   math = { sum: function(list) { list[0] + add(list[1], list[2]) } };\n\
+                   run = function() { (math.sum)([1, 2, true]) };\n\
+                   run()";
+        let err = interpreter.eval(src).unwrap_err();
+        let frames = err
+            .stack()
+            .iter()
+            .map(|frame| {
+                (
+                    frame.name.as_deref(),
+                    &src[frame.span.start..frame.span.end],
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            frames,
+            vec![
+                (Some("add"), "add(list[1], list[2])"),
+                (None, "(math.sum)([1, 2, true])"),
+                (Some("run"), "run()"),
+            ]
+        );
+        assert!(interpreter.call_stack().is_empty());
+
+        let run = interpreter.get_function("run").unwrap();
+        let err = interpreter
+            .call_function::<_, Value>(&run, Value::Null, ())
+            .unwrap_err();
+        let rendered =
+            Renderer::plain().render_sources_string(&err.diagnostic(), interpreter.sources());
+        assert!(
+            rendered.contains(
+                "in function, called at 3:20\n               in `run`, called by the host\n"
+            ),
+            "{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn declare_named_functions() {
+        let mut interpreter = Interpreter::new();
+        let src = "result = [fib(10), is_even(4)];\n\
+                   function fib(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };\n\
+                   function is_even(n) { if (n == 0) { true } else { is_odd(n - 1) } };\n\
+                   function is_odd(n) { if (n == 0) { false } else { is_even(n - 1) } };\n\
+                   square = function(x) { x * x };\n\
+                   alias = square;\n\
+                   [result, alias, function named() {}, named]";
+        let result = interpreter.eval(src).unwrap().to_string();
+        assert_eq!(
+            result,
+            "[[55, true], function square(x) {..}, function named() {..}, null]"
+        );
+        assert_eq!(interpreter.eval("function f() {}").unwrap(), Value::Null);
+        // Evaluating the same function twice creates equal values.
+        let src = "function outer() { function inner() {}; [inner, function() {}] }; \
+                   [outer()[0] == outer()[0], outer()[1] == outer()[1]]";
+        let result = interpreter.eval(src).unwrap().to_string();
+        assert_eq!(result, "[true, true]");
+
+        interpreter.set_readonly_global("limit", 1).unwrap();
+        let err = interpreter.eval("function limit() {}").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ReadonlyAssignment);
+    }
+
+    #[test]
+    fn declare_local_variables() {
+        let mut interpreter = Interpreter::new();
+        let src = "x = 1;\n\
+                   function bump() { x = x + 1; local = true; };\n\
+                   bump(); bump();\n\
+                   let y = 10;\n\
+                   if (true) { let y = 20; y = y + 1; z = y; };\n\
+                   for (i in 0..3) { let last = i; total = last; };\n\
+                   const limit = 3;\n\
+                   [x, y, z, last, total, local, limit]";
+        let result = interpreter.eval(src).unwrap().to_string();
+        assert_eq!(result, "[3, 10, 21, null, 2, null, 3]");
+
+        let err = interpreter.eval("limit = 4").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ReadonlyAssignment);
+        assert_eq!(err.message(), "can't assign to constant `limit`");
+        let err = interpreter.eval("const limit = 4").unwrap_err();
+        assert_eq!(err.message(), "can't redeclare constant `limit`");
+        let src = "function f() { const limit = 5; let x = limit; x = x + 1; x }; f()";
+        assert_eq!(interpreter.eval(src).unwrap(), Value::Integer(6));
+
+        interpreter.set_readonly_global("host", 1).unwrap();
+        let err = interpreter.eval("let host = 2").unwrap_err();
+        assert_eq!(err.message(), "can't redeclare readonly global `host`");
+        assert!(interpreter.eval("if (true) { let host = 2; host }").is_ok());
+
+        let err = interpreter
+            .eval("const c = 1; function c() {}; print(c);")
+            .unwrap_err();
+        assert_eq!(err.message(), "can't redeclare function `c`");
+        assert_eq!(err.code(), ErrorCode::ReadonlyAssignment);
+        // The check doesn't depend on running the block.
+        let src = "function f() { if (false) { let g = 1; function g() {} } }";
+        let err = Interpreter::new().compile(src).unwrap_err();
+        assert_eq!(err.message(), "can't redeclare function `g`");
+        let span = err.span().unwrap();
+        assert_eq!(&src[span.start..span.end], "g");
+        let src = "function g() {}; if (true) { let g = 2; g }";
+        assert_eq!(interpreter.eval(src).unwrap(), Value::Integer(2));
+    }
+
+    #[test]
+    fn destructure_values() {
+        let mut interpreter = Interpreter::new();
+        let src = "[a, b] = [1, 2]; [a, b] = [b, a];\n\
+                   {name, age: years = 0, ...rest} = {name: \"Bob\", city: \"Paris\"};\n\
+                   [x, [y] = [x + 1], ...more] = [1];\n\
+                   total = 0;\n\
+                   for ({key, value} in {p: 1, q: 2}) { total = total + value; };\n\
+                   [a, b, name, years, rest.city, y, more, total, key]";
+        let result = interpreter.eval(src).unwrap().to_string();
+        assert_eq!(result, "[2, 1, Bob, 0, Paris, 2, [], 3, null]");
+
+        let err = interpreter.eval("{length} = [1, 2]").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TypeMismatch);
+        assert_eq!(
+            err.message(),
+            "can't destructure a value of type array as an object"
+        );
+        let src = "[c, d, ...others] = 0..5; pairs = [];\n\
+                   for ([k, v] in [0..2, 5..=6]) { pairs[k] = v; };\n\
+                   [c, d, others, pairs]";
+        let result = interpreter.eval(src).unwrap().to_string();
+        assert_eq!(result, "[0, 1, [2, 3, 4], [1, null, null, null, null, 6]]");
+        let err = interpreter.eval("for ([k, v] in [1]) {}").unwrap_err();
+        assert_eq!(
+            err.message(),
+            "can't destructure a value of type integer as an array"
+        );
+        let err = interpreter.eval("1 = 2;").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidAssignment);
+        assert!(interpreter.eval("f = function() { 1 }; f() = 2").is_err());
+    }
+
+    #[test]
+    fn match_values() {
+        let mut interpreter = Interpreter::new();
+        let src = "describe = function(v) {\n\
+                       match (v) {\n\
+                           0 => { \"zero\" },\n\
+                           1 | 2 => { \"small\" },\n\
+                           n if n < 0 => { \"negative\" },\n\
+                           $integer => { \"big\" },\n\
+                           [a, b] => { a + b },\n\
+                           [first, ...rest] => { rest },\n\
+                           {kind: \"circle\", r} => { r * 2 },\n\
+                           {kind: \"rect\", w, h = w} => { w * h },\n\
+                           _ => { null },\n\
+                       }\n\
+                   };\n\
+                   [describe(0), describe(2), describe(-1), describe(7), describe([1, 2]),\n\
+                    describe([1, 2, 3]), describe([]), describe({kind: \"circle\", r: 3}),\n\
+                    describe({kind: \"rect\", w: 4}), describe({kind: \"rect\"}), describe(\"x\")]";
+        let result = interpreter.eval(src).unwrap().to_string();
+        assert_eq!(
+            result,
+            "[zero, small, negative, big, 3, [2, 3], null, 6, 16, null, null]"
+        );
+
+        let src = "[match (0..2) { [x, y] => { x + y }, _ => { null } },\n\
+                    match (0..=2) { [x, y] => { null }, [x, ...rest] => { rest } }]";
+        let result = interpreter.eval(src).unwrap().to_string();
+        assert_eq!(result, "[1, [1, 2]]");
+
+        let err = interpreter.eval("match (3) { 1 => {} }").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::PatternMismatch);
+        assert_eq!(
+            err.message(),
+            "no arm of this `match` matches the value `3`"
+        );
+        let err = interpreter.eval("match (3) { n if n => {} }").unwrap_err();
+        assert_eq!(err.message(), "expected boolean, got integer");
+        let err = interpreter.eval("[a, 1] = [1, 2]").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::PatternMismatch);
+        assert_eq!(err.message(), "the value `2` doesn't match this pattern");
+    }
+
+    #[test]
+    fn lazy_ranges() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register(&crate::library::StandardLibrary);
+        let src = "total = 0;\n\
+                   for (i in 0..1000000000) { if (i == 4) { break; }; total = total + i; };\n\
+                   for (i in 1..0) { total = total + 100; };\n\
+                   evens = (0..=10).step(2);\n\
+                   a = [10, 11, 12, 13]; s = \"hello\";\n\
+                   [total, evens, evens.length(), evens.contains(4), evens.contains(5), 3..0,\n\
+                    (3..0).length(), (3..=0).step(-2), a[1..3], a[2..=10], a[3..0],\n\
+                    a[(3..0).step(-1)], s[1..=3], s[(4..=0).step(-1)], evens[2], evens[1..3],\n\
+                    0..3 == [0, 1, 2], (0..1000000000).length()]";
+        let result = interpreter.eval(src).unwrap().to_string();
+        assert_eq!(
+            result,
+            "[6, (0..=10).step(2), 6, true, false, 3..0, 0, (3..=0).step(-2), [11, 12], \
+             [12, 13], [], [13, 12, 11], ell, olleh, 4, (2..=4).step(2), true, 1000000000]"
+        );
+
+        let err = interpreter.eval("(0..10).step(0)").unwrap_err();
+        assert_eq!(err.message(), "the step of a range can't be zero");
+        let src = "(-9223372036854775807..9223372036854775807).length()";
+        let err = interpreter.eval(src).unwrap_err();
+        assert!(err.message().contains("too many elements"), "{}", err);
+        let src = "(-4611686018427387904..4611686018427387903).length()";
+        assert_eq!(interpreter.eval(src).unwrap(), Value::Integer(i64::MAX));
+
+        let src = "s = \"héllo\"; [s.length(), s[1..3], s[s.length() - 1], s[(4..=0).step(-1)]]";
+        let result = interpreter.eval(src).unwrap().to_string();
+        assert_eq!(result, "[5, él, o, olléh]");
+    }
+
+    #[test]
+    fn bind_arguments_to_parameters() {
+        let mut interpreter = Interpreter::new();
+        let src = "function f(a, b = [a], ...rest) { [a, b, rest] };\n\
+                   args = [1, 2, 3];\n\
+                   [f(1), f(1, 5), f(...args, 4), f(0, ...[]), f(), f, function(x) { x }(1, 2)]";
+        let result = interpreter.eval(src).unwrap().to_string();
+        assert_eq!(
+            result,
+            "[[1, [1], []], [1, 5, []], [1, 2, [3, 4]], [0, [0], []], [null, [null], []], \
+             function f(a, b, ...rest) {..}, 1]"
+        );
+        let err = interpreter.eval("f(...1)").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TypeMismatch);
+        assert_eq!(err.message(), "can't spread a value of type integer");
+
+        let mut interpreter = Interpreter::new().with_strict_arity(true);
+        interpreter
+            .eval("function one(a) {}; function some(a, b = 1) {}; function many(a, ...b) {};")
+            .unwrap();
+        for (src, message) in [
+            ("one()", "expected 1 argument, got 0"),
+            ("one(1, 2)", "expected 1 argument, got 2"),
+            ("some(1, 2, 3)", "expected 1 to 2 arguments, got 3"),
+            ("many()", "expected at least 1 argument, got 0"),
+        ] {
+            let err = interpreter.eval(src).unwrap_err();
+            assert_eq!(err.code(), ErrorCode::ArityMismatch);
+            assert_eq!(err.message(), message, "{}", src);
+        }
+        assert!(interpreter.eval("[one(1), some(1), many(1, 2, 3)]").is_ok());
+    }
+
     #[test]
     fn test() {
         let src = r#"
@@ -52,15 +418,15 @@ mod tests {
         "#;
         let mut interpreter = Interpreter::new();
 
-        crate::library::StandardLibrary.register(&mut interpreter);
-        crate::library::Console.register(&mut interpreter);
+        interpreter.register(&crate::library::StandardLibrary);
+        interpreter.register(&crate::library::Console);
 
         let start = Instant::now();
         match interpreter.eval(src) {
             Ok(result) => println!("==> {}", result.to_string()),
-            Err(err) => {
-                report::report_io(&mut stdout(), src, err.start, err.end, err.value).unwrap()
-            }
+            Err(err) => Renderer::plain()
+                .render_io(&mut stdout(), &err.diagnostic(), src, None)
+                .unwrap(),
         }
         println!(
             "{: >5}s execution time",
@@ -70,30 +436,39 @@ mod tests {
 }
 
 trait Evaluate {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>>;
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError>;
 }
 
-impl Evaluate for Pos<Box<AST>> {
-    fn eval(self, scope: &mut Interpreter) -> Result<Eval, Pos<String>> {
-        self.map(|b| *b).eval(scope)
+impl Evaluate for &Pos<Box<AST>> {
+    fn eval(self, scope: &mut Interpreter) -> Result<Eval, KetamineError> {
+        Pos::new(self.start, self.end, &*self.value).eval(scope)
     }
 }
 
-impl Evaluate for Pos<AST> {
-    fn eval(self, interp: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for &Pos<AST> {
+    fn eval(self, scope: &mut Interpreter) -> Result<Eval, KetamineError> {
+        self.as_ref().eval(scope)
+    }
+}
+
+impl Evaluate for Pos<&AST> {
+    fn eval(self, interp: &mut Interpreter) -> Result<Eval, KetamineError> {
         let (a, b) = (self.start, self.end);
 
         match self.value {
-            AST::Int(int) => Ok(Eval::Value(Value::Integer(int))),
-            AST::Float(float) => Ok(Eval::Value(Value::Float(float))),
-            AST::Bool(boolean) => Ok(Eval::Value(Value::Boolean(boolean))),
-            AST::String(string) => Ok(Eval::Value(Value::String(string))),
+            AST::Int(int) => Ok(Eval::Value(Value::Integer(*int))),
+            AST::Float(float) => Ok(Eval::Value(Value::Float(*float))),
+            AST::Bool(boolean) => Ok(Eval::Value(Value::Boolean(*boolean))),
+            AST::String(string) => Ok(Eval::Value(Value::String(string.clone()))),
             AST::Assignment(assign) => Pos::new(a, b, assign).eval(interp),
+            AST::Destructuring(destructuring) => Pos::new(a, b, destructuring).eval(interp),
+            AST::VariableDeclaration(declaration) => Pos::new(a, b, declaration).eval(interp),
             AST::UnaryOperation(unary) => Pos::new(a, b, unary).eval(interp),
             AST::BinaryOperation(op) => Pos::new(a, b, op).eval(interp),
             AST::ForLoop(for_loop) => Pos::new(a, b, for_loop).eval(interp),
             AST::WhileLoop(while_loop) => Pos::new(a, b, while_loop).eval(interp),
             AST::If(if_expr) => Pos::new(a, b, if_expr).eval(interp),
+            AST::Match(match_expr) => Pos::new(a, b, match_expr).eval(interp),
             AST::Object(object) => Pos::new(a, b, object).eval(interp),
             AST::List(list) => Pos::new(a, b, list).eval(interp),
             AST::Range(range) => Pos::new(a, b, range).eval(interp),
@@ -105,38 +480,49 @@ impl Evaluate for Pos<AST> {
             AST::Index(index) => Pos::new(a, b, index).eval(interp),
             AST::FieldAccess(access) => Pos::new(a, b, access).eval(interp),
             AST::Ident(Ident(ident)) => {
-                let val = interp.scope.get_var(&ident).cloned().unwrap_or(Value::Null);
+                let val = interp.scope.get_var(ident).cloned().unwrap_or(Value::Null);
                 Ok(Eval::Value(val))
             }
             AST::Function(function) => {
-                let function = values::Function {
-                    function: Rc::new(function),
-                };
+                let mut function = values::Function::new(Rc::clone(function));
+                function.source = interp.source_at(a);
                 Ok(Eval::Value(Value::Function(function)))
             }
+            AST::Spread(..) => Err(KetamineError::runtime(
+                a,
+                b,
+                "`...` can only be used in the arguments of a call",
+            )),
+            AST::Invalid(Invalid(error, ..)) => Err(error.clone().into()),
         }
     }
 }
 
-impl Evaluate for Pos<Call> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&Call> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
             value: Call { value, args },
         } = self;
-        let (this, function) = match value.map(|x| *x) {
+        // The name the function is called by, which is shown in stack traces.
+        let name = match &*value.value {
+            AST::Ident(Ident(name)) => Some(name.clone()),
+            AST::FieldAccess(FieldAccess { field, .. }) => Some(field.value.0.clone()),
+            _ => None,
+        };
+        let (this, function) = match &*value.value {
             // value.field()
             // ^this^function^
-            Pos {
-                start,
-                end,
-                value: AST::FieldAccess(FieldAccess { value, field }),
-            } => {
-                let this = value
+            AST::FieldAccess(FieldAccess {
+                value: object,
+                field,
+            }) => {
+                let (start, end) = (value.start, value.end);
+                let this = object
                     .eval(interpreter)?
                     .try_into_value()
-                    .map_err(|err| Pos::new(start, end, err))?;
+                    .map_err(|err| KetamineError::runtime(start, end, err))?;
                 let function = this
                     .get_field(&field.value)
                     .or_else(|| interpreter.get_proto(&this).get_field(&field.value))
@@ -144,27 +530,54 @@ impl Evaluate for Pos<Call> {
                 (this, function)
             }
             // (x)()
-            other => {
-                let function = other
+            _ => {
+                let (start, end) = (value.start, value.end);
+                let function = value
                     .eval(interpreter)?
                     .try_into_value()
-                    .map_err(|err| Pos::new(start, end, err))?;
+                    .map_err(|err| KetamineError::runtime(start, end, err))?;
                 (Value::Null, function)
             }
         };
 
         let mut arg_values = Vec::with_capacity(args.len());
         for arg in args {
-            let arg = arg.eval(interpreter)?.into_value();
-            arg_values.push(arg);
+            match &arg.value {
+                // f(...args)
+                AST::Spread(Spread(array)) => {
+                    let (start, end) = (array.start, array.end);
+                    match array.eval(interpreter)?.into_value() {
+                        Value::Array(array) => arg_values.extend(array.0.borrow().iter().cloned()),
+                        Value::Range(range) => arg_values.extend(range.iter()),
+                        other => {
+                            let msg = format!("can't spread a value of type {}", other.type_name());
+                            return Err(KetamineError::runtime_with_code(
+                                ErrorCode::TypeMismatch,
+                                start,
+                                end,
+                                msg,
+                            ));
+                        }
+                    }
+                }
+                _ => {
+                    let arg = arg.eval(interpreter)?;
+                    arg_values.push(arg.into_value());
+                }
+            }
         }
-        let result = function.call(start, end, interpreter, this, arg_values)?;
+        let result = match &function {
+            Value::Function(function) => {
+                function.call_named(name, start, end, interpreter, this, arg_values)?
+            }
+            function => function.call(start, end, interpreter, this, arg_values)?,
+        };
         Ok(Eval::Value(result))
     }
 }
 
-impl Evaluate for Pos<FieldAccess> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&FieldAccess> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
@@ -173,7 +586,7 @@ impl Evaluate for Pos<FieldAccess> {
         let value = value
             .eval(interpreter)?
             .try_into_value()
-            .map_err(|err| Pos::new(start, end, err))?;
+            .map_err(|err| KetamineError::runtime(start, end, err))?;
         let field = value
             .get_field(&field.value)
             .or_else(|| interpreter.get_proto(&value).get_field(&field.value))
@@ -182,8 +595,8 @@ impl Evaluate for Pos<FieldAccess> {
     }
 }
 
-impl Evaluate for Pos<Index> {
-    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for Pos<&Index> {
+    fn eval(self, interpreter: &mut Interpreter) -> Result<Eval, KetamineError> {
         let Pos {
             start,
             end,
@@ -192,38 +605,57 @@ impl Evaluate for Pos<Index> {
         let value = value
             .eval(interpreter)?
             .try_into_value()
-            .map_err(|err| Pos::new(start, end, err))?;
+            .map_err(|err| KetamineError::runtime(start, end, err))?;
         let idx = index
             .eval(interpreter)?
             .try_into_value()
-            .map_err(|err| Pos::new(start, end, err))?;
+            .map_err(|err| KetamineError::runtime(start, end, err))?;
         let result = value.get_index(&idx).ok_or_else(|| {
             let msg = format!(
                 "can't index into {} using {}",
                 value.type_name(),
                 idx.type_name()
             );
-            Pos::new(start, end, msg)
+            KetamineError::runtime_with_code(ErrorCode::InvalidIndex, start, end, msg)
         })?;
         Ok(Eval::Value(result))
     }
 }
 
-impl Evaluate for Pos<Statement> {
-    fn eval(self, scope: &mut Interpreter) -> Result<Eval, Pos<String>> {
-        self.map(|statement| *statement.into_inner()).eval(scope)
+impl Evaluate for &Pos<Statement> {
+    fn eval(self, scope: &mut Interpreter) -> Result<Eval, KetamineError> {
+        Pos::new(self.start, self.end, self.value.inner()).eval(scope)
     }
 }
 
-impl Evaluate for Vec<Pos<Statement>> {
-    fn eval(self, scope: &mut Interpreter) -> Result<Eval, Pos<String>> {
+impl Evaluate for &[Pos<Statement>] {
+    fn eval(self, scope: &mut Interpreter) -> Result<Eval, KetamineError> {
+        // Declared functions are defined first, so they can be called before their declaration.
+        for statement in self {
+            if let AST::Function(function) = statement.value.inner() {
+                let name = match &function.name {
+                    Some(name) => name.clone(),
+                    None => continue,
+                };
+                let mut function = values::Function::new(Rc::clone(function));
+                function.source = scope.source_at(statement.start);
+                scope.assign_var(name.map(|name| name.0), Value::Function(function))?;
+            }
+        }
+
         let len = self.len();
-        for (idx, statement) in self.into_iter().enumerate() {
+        for (idx, statement) in self.iter().enumerate() {
             let is_unterminated = if let Statement::Unterminated(..) = &statement.value {
                 true
             } else {
                 false
             };
+            if statement.value.declaration().is_some() {
+                if idx == len - 1 && is_unterminated {
+                    return Ok(Eval::Value(Value::Null));
+                }
+                continue;
+            }
             let result = match statement.eval(scope)? {
                 val @ Eval::Value(..) => val,
                 instruction => return Ok(instruction),
@@ -1,19 +1,27 @@
-use crate::library::Library;
+use crate::library::{Capability, Library};
 use crate::values::Object;
 use crate::values::{NativeFunction, Value};
-use crate::Interpreter;
+use crate::{Interpreter, KetamineError};
+
 pub struct Console;
 
 impl Library for Console {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Output, Capability::Input]
+    }
+
     fn register(&self, interpreter: &mut Interpreter) {
-        let print_line = Value::NativeFunction(NativeFunction::new(print_line));
-        interpreter.scope.push_var("print", print_line, false);
-        let read_line = Value::NativeFunction(NativeFunction::new(read_line));
-        interpreter.scope.push_var("read_line", read_line, false);
+        // Each function is left out on its own if the profile doesn't grant its capability.
+        let _ = interpreter.requiring(&[Capability::Output], |interpreter| {
+            interpreter.set_global("print", NativeFunction::new(print_line))
+        });
+        let _ = interpreter.requiring(&[Capability::Input], |interpreter| {
+            interpreter.set_global("read_line", NativeFunction::new(read_line))
+        });
     }
 }
 
-fn print_line(_: &mut Interpreter, _: Value, args: Vec<Value>) -> Result<Value, String> {
+fn print_line(_: &mut Interpreter, _: Value, args: Vec<Value>) -> Result<Value, KetamineError> {
     let content = args
         .into_iter()
         .map(|v| v.to_string())
@@ -23,7 +31,7 @@ fn print_line(_: &mut Interpreter, _: Value, args: Vec<Value>) -> Result<Value,
     Ok(Value::Null)
 }
 
-fn read_line(_: &mut Interpreter, _: Value, _: Vec<Value>) -> Result<Value, String> {
+fn read_line(_: &mut Interpreter, _: Value, _: Vec<Value>) -> Result<Value, KetamineError> {
     let mut line = String::new();
     std::io::stdin()
         .read_line(&mut line)
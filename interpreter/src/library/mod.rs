@@ -3,9 +3,195 @@ pub use standard::*;
 
 use crate::Interpreter;
 
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
 mod console;
 mod standard;
 
 pub trait Library {
+    /// The capabilities the functions of the library may need. Unless the library narrows them
+    /// down using `Interpreter::requiring`, nothing is registered if the interpreter doesn't
+    /// grant all of them.
+    fn capabilities(&self) -> &'static [Capability] {
+        &[]
+    }
+
+    /// Defines the globals and prototype functions of the library. Libraries are registered
+    /// using `Interpreter::register`, which enforces their capabilities: registering something
+    /// the profile doesn't allow fails with `MissingCapability`, which libraries usually ignore
+    /// in order to register the rest.
     fn register(&self, interpreter: &mut Interpreter);
 }
+
+/// Access to the world outside of the interpreter, which a library function may need.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Writing output, e.g. printing to stdout.
+    Output,
+    /// Reading input, e.g. from stdin. Reading may block until input is available.
+    Input,
+    /// Evaluating code which is created at runtime.
+    Eval,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 3] = [Capability::Output, Capability::Input, Capability::Eval];
+}
+
+/// The error returned when something is registered which needs a capability the profile of the
+/// interpreter doesn't grant. Nothing is registered in that case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingCapability(pub Capability);
+
+impl Display for MissingCapability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "the profile doesn't grant the capability {:?}", self.0)
+    }
+}
+
+impl std::error::Error for MissingCapability {}
+
+/// The capabilities granted to the libraries of an interpreter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    granted: HashSet<Capability>,
+}
+
+impl Profile {
+    /// Grants every capability. Use it only for trusted scripts.
+    pub fn trusted() -> Self {
+        Profile {
+            granted: Capability::ALL.iter().copied().collect(),
+        }
+    }
+
+    /// Grants no capability, so scripts can only use functions which need none, like those
+    /// defined by the host.
+    pub fn sandboxed() -> Self {
+        Profile {
+            granted: HashSet::new(),
+        }
+    }
+
+    pub fn with(mut self, capability: Capability) -> Self {
+        self.granted.insert(capability);
+        self
+    }
+
+    pub fn without(mut self, capability: Capability) -> Self {
+        self.granted.remove(&capability);
+        self
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.granted.contains(&capability)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::library::{
+        Capability, Console, Library, MissingCapability, Profile, StandardLibrary,
+    };
+    use crate::{Array, Interpreter, NativeFunction, Value};
+
+    /// A library which doesn't check the capabilities granted to it.
+    struct Careless;
+
+    impl Library for Careless {
+        fn capabilities(&self) -> &'static [Capability] {
+            &[Capability::Input, Capability::Output]
+        }
+
+        fn register(&self, interpreter: &mut Interpreter) {
+            let read = NativeFunction::new(|_, _, _| Ok(Value::String("secret".to_owned())));
+            let _ = interpreter.set_readonly_global("read_stdin", read);
+            let _ = interpreter.prototype_function("secret", |_, _: String, _| Ok(Value::Null));
+            let _ = interpreter.requiring(&[Capability::Output], |interpreter| {
+                interpreter.set_global("verbose", Value::Boolean(true))
+            });
+        }
+    }
+
+    #[test]
+    fn restrict_capabilities() {
+        let profile = Profile::sandboxed().with(Capability::Output);
+        let mut interpreter = Interpreter::with_profile(profile);
+        interpreter.register(&StandardLibrary);
+        interpreter.register(&Console);
+        assert!(interpreter.get_function("print").is_some());
+        assert!(interpreter.get_function("read_line").is_none());
+        assert!(interpreter.get_function("eval").is_none());
+        assert!(interpreter.eval("\"42\".to_int() + 1").is_ok());
+        assert!(interpreter.eval("eval(\"1\")").is_err());
+
+        let mut interpreter = Interpreter::new();
+        interpreter.register(&StandardLibrary);
+        interpreter.register(&Console);
+        assert!(interpreter.get_function("read_line").is_some());
+        assert!(interpreter.get_function("eval").is_some());
+        assert_eq!(
+            Console.capabilities(),
+            &[Capability::Output, Capability::Input]
+        );
+    }
+
+    #[test]
+    fn reassign_library_globals() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register(&StandardLibrary);
+        interpreter.register(&Console);
+        let src = "print = function(x) { x }; eval = 1; read_line = null; [print(2), eval]";
+        let values = vec![Value::Integer(2), Value::Integer(1)];
+        assert_eq!(
+            interpreter.eval(src).unwrap(),
+            Value::Array(Array::new(values))
+        );
+    }
+
+    #[test]
+    fn enforce_declared_capabilities() {
+        let profile = Profile::sandboxed().with(Capability::Output);
+        let mut interpreter = Interpreter::with_profile(profile);
+        interpreter.register(&Careless);
+        assert!(interpreter.get_global("read_stdin").is_none());
+        assert_eq!(interpreter.eval("\"x\".secret").unwrap(), Value::Null);
+        assert_eq!(
+            interpreter.get_global("verbose"),
+            Some(Value::Boolean(true))
+        );
+
+        let mut interpreter = Interpreter::with_profile(Profile::sandboxed());
+        interpreter.register(&Careless);
+        assert!(interpreter.get_global("verbose").is_none());
+
+        let mut interpreter = Interpreter::new();
+        interpreter.register(&Careless);
+        assert!(interpreter.get_function("read_stdin").is_some());
+        assert_ne!(interpreter.eval("\"x\".secret").unwrap(), Value::Null);
+        // The host can always define globals itself.
+        let mut interpreter = Interpreter::with_profile(Profile::sandboxed());
+        assert_eq!(
+            interpreter.set_global("answer", Value::Integer(42)),
+            Ok(None)
+        );
+        assert_eq!(interpreter.get_global("answer"), Some(Value::Integer(42)));
+    }
+
+    #[test]
+    fn report_missing_capabilities() {
+        let profile = Profile::sandboxed().with(Capability::Output);
+        let mut interpreter = Interpreter::with_profile(profile);
+        let result = interpreter.requiring(&[Capability::Output, Capability::Input], |i| {
+            i.set_global("read", Value::Null)
+        });
+        assert_eq!(result, Err(MissingCapability(Capability::Input)));
+        let result = interpreter.requiring(&[Capability::Eval], |i| {
+            i.prototype_function("run", |_, _: String, _| Ok(Value::Null))
+        });
+        assert_eq!(result, Err(MissingCapability(Capability::Eval)));
+        assert!(interpreter.get_global("read").is_none());
+        assert_eq!(interpreter.eval("\"x\".run").unwrap(), Value::Null);
+    }
+}
@@ -1,48 +1,59 @@
 use std::ops::Deref;
 
-use crate::library::Library;
-use crate::values::{Array, Value};
-use crate::{Interpreter, NativeFunction, Object, ObjectConversion};
+use crate::library::{Capability, Library, MissingCapability};
+use crate::values::{Array, Range, Value};
+use crate::{Interpreter, KetamineError, NativeFunction, Object, ObjectConversion};
 
 use std::str::FromStr;
 
 pub struct StandardLibrary;
 
 impl Library for StandardLibrary {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Eval]
+    }
+
     fn register(&self, interpreter: &mut Interpreter) {
-        let eval = NativeFunction::new(eval);
-        interpreter
-            .scope
-            .push_var("eval", Value::NativeFunction(eval), false);
+        // `eval` is left out if the profile doesn't grant `Eval`.
+        let _ = interpreter.requiring(&[Capability::Eval], |interpreter| {
+            interpreter.set_global("eval", NativeFunction::new(eval))
+        });
+        let _ = interpreter.requiring(&[], register_prototypes);
+    }
+}
 
-        interpreter.prototype_function("to_int", parse_string::<i64>);
-        interpreter.prototype_function("to_float", parse_string::<f64>);
-        interpreter.prototype_function("to_boolean", parse_string::<bool>);
+fn register_prototypes(interpreter: &mut Interpreter) -> Result<(), MissingCapability> {
+    interpreter.prototype_function("to_int", parse_string::<i64>)?;
+    interpreter.prototype_function("to_float", parse_string::<f64>)?;
+    interpreter.prototype_function("to_boolean", parse_string::<bool>)?;
 
-        interpreter.prototype_function("length", array_length);
-        interpreter.prototype_function("contains", array_contains);
+    interpreter.prototype_function("length", array_length)?;
+    interpreter.prototype_function("contains", array_contains)?;
 
-        interpreter.prototype_function("length", string_length);
-        interpreter.prototype_function("contains", string_contains);
-    }
+    interpreter.prototype_function("length", string_length)?;
+    interpreter.prototype_function("contains", string_contains)?;
+
+    interpreter.prototype_function("length", range_length)?;
+    interpreter.prototype_function("contains", range_contains)?;
+    interpreter.prototype_function("step", range_step)?;
+    Ok(())
 }
 
 fn parse_string<O: FromStr + Object>(
     _: &mut Interpreter,
     this: String,
     _: Vec<Value>,
-) -> Result<Value, String> {
+) -> Result<Value, KetamineError> {
     Ok(O::from_str(&this).map(O::into_value).unwrap_or(Value::Null))
 }
 
-fn eval(inter: &mut Interpreter, _: Value, mut args: Vec<Value>) -> Result<Value, String> {
+fn eval(inter: &mut Interpreter, _: Value, mut args: Vec<Value>) -> Result<Value, KetamineError> {
     if args.len() != 1 {
-        Err(format!("expected 1 argument, got {}", args.len()))
+        Err(format!("expected 1 argument, got {}", args.len()).into())
     } else {
         let code = std::mem::replace(&mut args[0], Value::Null);
-        inter
-            .eval(&String::try_get_as(code)?)
-            .map_err(|err| format!("evaluation failed: {}", err.value))
+        // Errors keep their location, which is within the evaluated code.
+        inter.eval_file("<eval>", &String::try_get_as(code)?)
     }
 }
 
@@ -50,25 +61,30 @@ fn string_contains(
     _: &mut Interpreter,
     this: String,
     mut args: Vec<Value>,
-) -> Result<Value, String> {
+) -> Result<Value, KetamineError> {
     if args.len() != 1 {
-        return Err(format!("expected 1 argument, got {}", args.len()));
+        return Err(format!("expected 1 argument, got {}", args.len()).into());
     }
     let arg = std::mem::replace(&mut args[0], Value::Null);
     let contains = this.contains(&String::try_get_as(arg)?);
     Ok(Value::Boolean(contains))
 }
 
-fn string_length(_: &mut Interpreter, this: String, _: Vec<Value>) -> Result<Value, String> {
-    Ok(Value::Integer(this.len() as i64))
+/// The number of characters, which is also what indices into a string count.
+fn string_length(_: &mut Interpreter, this: String, _: Vec<Value>) -> Result<Value, KetamineError> {
+    Ok(Value::Integer(this.chars().count() as i64))
 }
 
-fn array_length(_: &mut Interpreter, this: Array, _: Vec<Value>) -> Result<Value, String> {
+fn array_length(_: &mut Interpreter, this: Array, _: Vec<Value>) -> Result<Value, KetamineError> {
     let len = this.0.deref().borrow().len();
     Ok(Value::Integer(len as i64))
 }
 
-fn array_contains(_: &mut Interpreter, this: Array, args: Vec<Value>) -> Result<Value, String> {
+fn array_contains(
+    _: &mut Interpreter,
+    this: Array,
+    args: Vec<Value>,
+) -> Result<Value, KetamineError> {
     let contains = match args.get(0) {
         Some(arg) => arg,
         None => return Ok(Value::Boolean(false)),
@@ -76,3 +92,31 @@ fn array_contains(_: &mut Interpreter, this: Array, args: Vec<Value>) -> Result<
     let result = this.0.deref().borrow().contains(contains);
     Ok(Value::Boolean(result))
 }
+
+fn range_length(_: &mut Interpreter, this: Range, _: Vec<Value>) -> Result<Value, KetamineError> {
+    Ok(Value::Integer(this.length()?))
+}
+
+fn range_contains(
+    _: &mut Interpreter,
+    this: Range,
+    args: Vec<Value>,
+) -> Result<Value, KetamineError> {
+    let contains = match args.first().and_then(i64::convert_from) {
+        Some(int) => this.contains(int),
+        None => false,
+    };
+    Ok(Value::Boolean(contains))
+}
+
+fn range_step(
+    _: &mut Interpreter,
+    this: Range,
+    mut args: Vec<Value>,
+) -> Result<Value, KetamineError> {
+    if args.len() != 1 {
+        return Err(format!("expected 1 argument, got {}", args.len()).into());
+    }
+    let step = std::mem::replace(&mut args[0], Value::Null);
+    Ok(Value::Range(this.with_step(i64::try_get_as(step)?)?))
+}
@@ -0,0 +1,261 @@
+//! Simplifies code before it is evaluated, without changing what it does.
+//!
+//! The optimizer folds operations on literals into a single literal and removes branches of `if`
+//! expressions which can never be taken. Ranges are left as they are, since they are lazy and
+//! therefore cheaper than the list of their elements. Operations which fail are left as they
+//! are, so that they fail at runtime with the same error.
+
+use crate::interpreter::{apply_binary, apply_unary};
+use crate::values::Value;
+use lexer::Pos;
+use parser::ast::{
+    BinaryOperation, BinaryOperator, CodeBlock, If, IfBranch, Parentheses, UnaryOperation, AST,
+};
+use parser::visit::{fold_expr, Fold};
+
+/// Optimizes a parsed program.
+pub fn optimize(code: CodeBlock) -> CodeBlock {
+    Optimizer.fold_code(code)
+}
+
+struct Optimizer;
+
+impl Fold for Optimizer {
+    fn fold_expr(&mut self, expr: Pos<AST>) -> Pos<AST> {
+        let Pos { start, end, value } = fold_expr(self, expr);
+        let value = match value {
+            AST::BinaryOperation(operation) => fold_binary(operation),
+            AST::UnaryOperation(operation) => fold_unary(operation),
+            AST::Parentheses(Parentheses(inner)) if constant(&inner.value).is_some() => {
+                *inner.value
+            }
+            AST::If(if_expr) => fold_if(if_expr),
+            other => other,
+        };
+        Pos::new(start, end, value)
+    }
+}
+
+/// The value of a literal.
+fn constant(ast: &AST) -> Option<Value> {
+    match ast {
+        AST::Int(int) => Some(Value::Integer(*int)),
+        AST::Float(float) => Some(Value::Float(*float)),
+        AST::Bool(boolean) => Some(Value::Boolean(*boolean)),
+        AST::String(string) => Some(Value::String(string.clone())),
+        _ => None,
+    }
+}
+
+/// The literal of a value, if the value can be written as one.
+fn literal(value: Value) -> Option<AST> {
+    match value {
+        Value::Integer(int) => Some(AST::Int(int)),
+        // There are no literals for infinity and NaN.
+        Value::Float(float) if float.is_finite() => Some(AST::Float(float)),
+        Value::Boolean(boolean) => Some(AST::Bool(boolean)),
+        Value::String(string) => Some(AST::String(string)),
+        _ => None,
+    }
+}
+
+/// Whether integer arithmetic would overflow, which panics instead of returning an error.
+fn overflows(operator: BinaryOperator, lhs: &Value, rhs: &Value) -> bool {
+    match (operator, lhs, rhs) {
+        (BinaryOperator::Add, Value::Integer(lhs), Value::Integer(rhs)) => {
+            lhs.checked_add(*rhs).is_none()
+        }
+        (BinaryOperator::Sub, Value::Integer(lhs), Value::Integer(rhs)) => {
+            lhs.checked_sub(*rhs).is_none()
+        }
+        (BinaryOperator::Mul, Value::Integer(lhs), Value::Integer(rhs)) => {
+            lhs.checked_mul(*rhs).is_none()
+        }
+        _ => false,
+    }
+}
+
+fn fold_binary(operation: BinaryOperation) -> AST {
+    if let (Some(lhs), Some(rhs)) = (
+        constant(&operation.lhs.value),
+        constant(&operation.rhs.value),
+    ) {
+        let operator = operation.op.value;
+        if !overflows(operator, &lhs, &rhs) {
+            if let Some(folded) = apply_binary(operator, &lhs, &rhs).ok().and_then(literal) {
+                return folded;
+            }
+        }
+    }
+    AST::BinaryOperation(operation)
+}
+
+fn fold_unary(operation: UnaryOperation) -> AST {
+    match constant(&operation.value.value) {
+        Some(Value::Integer(i64::MIN)) => {}
+        Some(value) => {
+            if let Some(folded) = apply_unary(&operation.op.value, &value).and_then(literal) {
+                return folded;
+            }
+        }
+        None => {}
+    }
+    AST::UnaryOperation(operation)
+}
+
+/// Removes branches whose condition is `false` and everything after a branch whose condition
+/// is `true`.
+fn fold_if(if_expr: If) -> AST {
+    let If {
+        if_branch,
+        else_if_branches,
+        else_branch,
+    } = if_expr;
+
+    // The span of the first condition, which replaces the conditions if they are all removed.
+    let span = (
+        if_branch.value.condition.start,
+        if_branch.value.condition.end,
+    );
+    let mut branches: Vec<Pos<IfBranch>> = vec![];
+    let mut else_branch = else_branch;
+    for branch in std::iter::once(if_branch).chain(else_if_branches) {
+        match &*branch.value.condition.value {
+            AST::Bool(false) => continue,
+            AST::Bool(true) if branches.is_empty() => {
+                else_branch = None;
+                branches.push(branch);
+                break;
+            }
+            AST::Bool(true) => {
+                // The branches before it must still be checked, so it becomes the else branch.
+                else_branch = Some(branch.value.body);
+                break;
+            }
+            _ => branches.push(branch),
+        }
+    }
+
+    let mut branches = branches.into_iter();
+    match branches.next() {
+        Some(if_branch) => AST::If(If {
+            if_branch,
+            else_if_branches: branches.collect(),
+            else_branch,
+        }),
+        // No condition is left to be checked, so the else branch is always taken.
+        None => {
+            let (start, end) = span;
+            let condition = Pos::new(start, end, Box::new(AST::Bool(true)));
+            let body = else_branch.unwrap_or_default();
+            AST::If(If {
+                if_branch: Pos::new(start, end, IfBranch { condition, body }),
+                else_if_branches: vec![],
+                else_branch: None,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::library::StandardLibrary;
+    use crate::optimizer::optimize;
+    use crate::values::Object;
+    use crate::{Interpreter, KetamineError};
+    use parser::ast::{Statement, AST};
+
+    fn optimized(src: &str) -> Vec<AST> {
+        let tokens = lexer::tokenize(src).unwrap();
+        let code = parser::parse(&tokens).unwrap().value;
+        optimize(code)
+            .into_iter()
+            .map(|statement| *statement.value.into_inner())
+            .collect()
+    }
+
+    fn run(src: &str, optimize: bool) -> Result<String, KetamineError> {
+        let mut interpreter = Interpreter::new().with_optimizer(optimize);
+        interpreter.register(&StandardLibrary);
+        interpreter.eval(src).map(|value| value.to_string())
+    }
+
+    #[test]
+    fn fold_constants() {
+        let code = optimized(
+            "60 * 60 * 24; \"a\" + \"b\" + 1; -(2.5); !(1 < 2); 1 / 0; 1 + true; (0 + 1)..=(2 * 3); \
+             if (false) { 1 } else if (x) { 2 } else if (true) { 3 } else { 4 };",
+        );
+        assert!(matches!(code[0], AST::Int(86400)));
+        assert!(matches!(&code[1], AST::String(string) if string == "ab1"));
+        assert!(matches!(code[2], AST::Float(float) if float == -2.5));
+        assert!(matches!(code[3], AST::Bool(false)));
+        assert!(matches!(code[4], AST::BinaryOperation(..)));
+        assert!(matches!(code[5], AST::BinaryOperation(..)));
+        assert!(matches!(
+            &code[6],
+            AST::Range(range) if matches!(
+                (&*range.from.value, &*range.to.value),
+                (AST::Int(1), AST::Int(6))
+            )
+        ));
+        match &code[7] {
+            AST::If(if_expr) => {
+                assert!(matches!(
+                    *if_expr.if_branch.value.condition.value,
+                    AST::Ident(..)
+                ));
+                assert!(if_expr.else_if_branches.is_empty());
+                let else_branch = if_expr.else_branch.as_ref().unwrap();
+                assert!(matches!(
+                    &else_branch[0].value,
+                    Statement::Unterminated(value) if matches!(**value, AST::Int(3))
+                ));
+            }
+            other => panic!("expected an if expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keep_positions_of_removed_branches() {
+        let src = "if (1 > 2) { 1 } else { 2 };";
+        match &optimized(src)[0] {
+            AST::If(if_expr) => {
+                let condition = &if_expr.if_branch.value.condition;
+                assert!(matches!(*condition.value, AST::Bool(true)));
+                assert_eq!(&src[condition.start..condition.end], "1 > 2");
+                let branch = &if_expr.if_branch;
+                assert_eq!((branch.start, branch.end), (condition.start, condition.end));
+            }
+            other => panic!("expected an if expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn equivalent_to_unoptimized() {
+        let sources = [
+            "60 * 60 * 24",
+            "x = 2; [x * (3 + 4), (1 + 2) * x, \"a\" + \"b\" + x, 1.5 * 2 - 1 / 4]",
+            "[1 == 1.0, 2 >= 3, \"a\" != \"b\", !(1 < 2), -(-(3)), -2.5 * 2]",
+            "if (false) { 1 }",
+            "if (false) { 1 } else { 2 }",
+            "x = 5; if (false) { 1 } else if (x > 3) { 2 } else if (true) { 3 } else { 4 }",
+            "if (true) { 1 } else { 2 }",
+            "if (1 + 1 == 2) { \"yes\" } else { \"no\" }",
+            "match (1 + 1) { 2 if 2 > 1 => { 2 * 3 }, [x] => { x }, _ => { 0 } }",
+            "total = 0; for (i in 0..10) { total = total + i * (2 + 3); }; total",
+            "[0..0, 3..1, (2 - 1)..(2 + 2)]",
+            "f = function(n) { if (false) { return 0; }; n * (10 - 8) }; f(21)",
+            "i = 0; while (true) { i = i + 1; if (i > 2 * 5) { break i; }; }",
+            "1 + true",
+            "-\"a\"",
+            "!(1 + 2)",
+            "if (1) { 2 }",
+            "x = 9223372036854775807; x + 0",
+            "0..1.5",
+        ];
+        for src in sources.iter() {
+            assert_eq!(run(src, true), run(src, false), "{}", src);
+        }
+    }
+}
@@ -1,15 +1,48 @@
 use crate::values::Value;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+/// The scope of the readonly globals, which is the outermost scope.
+const READONLY_GLOBALS: usize = 0;
+/// The scope of the globals which scripts can reassign.
+const GLOBALS: usize = 1;
 
 #[derive(Debug)]
-pub struct ScopeStack {
+pub(crate) struct ScopeStack {
     scopes: Vec<Scope>,
 }
 
+/// A global variable, as returned by `Interpreter::globals`.
+#[derive(Debug, Clone, Copy)]
+pub struct Global<'a> {
+    pub name: &'a str,
+    pub value: &'a Value,
+    /// Whether the variable was defined by the host as readonly, so scripts can't reassign it.
+    pub readonly: bool,
+    /// Whether the variable was declared by a script using `const`.
+    pub constant: bool,
+}
+
+/// The variable an assignment or a declaration refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Binding {
+    /// A variable which can be reassigned, in the scope with the given index.
+    Variable(usize),
+    Constant,
+    ReadonlyGlobal,
+    /// There is no such variable yet.
+    Unbound,
+}
+
+/// Scopes are either the scope of a function call or of the globals, or readonly. Readonly
+/// scopes are the scopes of blocks, which only hold variables declared within them, like the
+/// binding of a for loop or variables declared with `let`. Other variables are defined in the
+/// enclosing scope which is not readonly.
 #[derive(Debug)]
 struct Scope {
     variables: HashMap<String, Value>,
+    /// The variables which were declared using `const`.
+    constants: HashSet<String>,
     readonly: bool,
 }
 
@@ -17,15 +50,18 @@ impl Scope {
     pub fn new(readonly: bool) -> Self {
         Scope {
             variables: HashMap::new(),
+            constants: HashSet::new(),
             readonly,
         }
     }
 }
 
 impl ScopeStack {
-    pub fn readonly_root() -> ScopeStack {
-        let root = Scope::new(true);
-        ScopeStack { scopes: vec![root] }
+    /// Creates the scopes of the global variables. Readonly globals are defined in the
+    /// outermost scope, the other globals in the scope within it.
+    pub fn new_global() -> ScopeStack {
+        let scopes = vec![Scope::new(true), Scope::new(false)];
+        ScopeStack { scopes }
     }
 
     pub fn push_var(&mut self, ident: impl Into<String>, var: Value, force: bool) -> Option<Value> {
@@ -38,6 +74,65 @@ impl ScopeStack {
         scope.variables.insert(ident.into(), var)
     }
 
+    /// Finds the variable an assignment to `ident` refers to. Assignments see the variables of
+    /// the enclosing blocks up to the innermost function call, and the globals, but not the
+    /// variables of the calling functions.
+    pub fn resolve(&self, ident: &str) -> Binding {
+        let function = self
+            .scopes
+            .iter()
+            .rposition(|scope| !scope.readonly)
+            .unwrap();
+        (function..self.scopes.len())
+            .rev()
+            .chain([GLOBALS, READONLY_GLOBALS])
+            .find_map(|idx| self.binding(idx, ident))
+            .unwrap_or(Binding::Unbound)
+    }
+
+    /// Finds the variable a declaration of `ident` would replace. Declarations in the scope of
+    /// the globals can't replace readonly globals.
+    pub fn resolve_declaration(&self, ident: &str) -> Binding {
+        let innermost = self.scopes.len() - 1;
+        let globals = match innermost {
+            GLOBALS => Some(READONLY_GLOBALS),
+            _ => None,
+        };
+        std::iter::once(innermost)
+            .chain(globals)
+            .find_map(|idx| self.binding(idx, ident))
+            .unwrap_or(Binding::Unbound)
+    }
+
+    fn binding(&self, idx: usize, ident: &str) -> Option<Binding> {
+        let scope = &self.scopes[idx];
+        if !scope.variables.contains_key(ident) {
+            None
+        } else if idx == READONLY_GLOBALS {
+            Some(Binding::ReadonlyGlobal)
+        } else if scope.constants.contains(ident) {
+            Some(Binding::Constant)
+        } else {
+            Some(Binding::Variable(idx))
+        }
+    }
+
+    /// Replaces the value of a variable in the scope with the given index.
+    pub fn set_var(&mut self, scope: usize, ident: impl Into<String>, var: Value) {
+        self.scopes[scope].variables.insert(ident.into(), var);
+    }
+
+    /// Defines a variable in the innermost scope, even if it is readonly.
+    pub fn declare_var(&mut self, ident: impl Into<String>, var: Value, constant: bool) {
+        let ident = ident.into();
+        let scope = self.scopes.last_mut().unwrap();
+        match constant {
+            true => scope.constants.insert(ident.clone()),
+            false => scope.constants.remove(&ident),
+        };
+        scope.variables.insert(ident, var);
+    }
+
     pub fn get_var(&self, ident: &str) -> Option<&Value> {
         self.scopes
             .iter()
@@ -46,12 +141,62 @@ impl ScopeStack {
             .next()
     }
 
-    pub fn push_scope(&mut self, readonly: bool) {
-        let scope = Scope {
-            variables: HashMap::new(),
-            readonly,
+    /// Defines a global variable, replacing a global of the same name.
+    pub fn set_global(&mut self, ident: String, var: Value, readonly: bool) -> Option<Value> {
+        let (scope, other) = match readonly {
+            true => (READONLY_GLOBALS, GLOBALS),
+            false => (GLOBALS, READONLY_GLOBALS),
         };
-        self.scopes.push(scope);
+        let previous = self.scopes[other].variables.remove(&ident);
+        self.scopes[GLOBALS].constants.remove(&ident);
+        self.scopes[scope].variables.insert(ident, var).or(previous)
+    }
+
+    /// Defines a global variable like `set_global`, which scripts can't reassign since it was
+    /// declared using `const`.
+    #[cfg(feature = "snapshot")]
+    pub fn set_constant_global(&mut self, ident: String, var: Value) -> Option<Value> {
+        self.scopes[GLOBALS].constants.insert(ident.clone());
+        let previous = self.scopes[READONLY_GLOBALS].variables.remove(&ident);
+        self.scopes[GLOBALS]
+            .variables
+            .insert(ident, var)
+            .or(previous)
+    }
+
+    pub fn get_global(&self, ident: &str) -> Option<&Value> {
+        self.scopes[GLOBALS]
+            .variables
+            .get(ident)
+            .or_else(|| self.scopes[READONLY_GLOBALS].variables.get(ident))
+    }
+
+    pub fn remove_global(&mut self, ident: &str) -> Option<Value> {
+        self.scopes[GLOBALS].constants.remove(ident);
+        self.scopes[GLOBALS]
+            .variables
+            .remove(ident)
+            .or_else(|| self.scopes[READONLY_GLOBALS].variables.remove(ident))
+    }
+
+    pub fn globals(&self) -> impl Iterator<Item = Global<'_>> {
+        let readonly = self.scopes[READONLY_GLOBALS].variables.iter();
+        let mutable = self.scopes[GLOBALS].variables.iter();
+        let constants = &self.scopes[GLOBALS].constants;
+        let readonly = readonly.map(|(name, value)| (name, value, true));
+        let mutable = mutable.map(|(name, value)| (name, value, false));
+        readonly
+            .chain(mutable)
+            .map(move |(name, value, readonly)| Global {
+                name,
+                value,
+                readonly,
+                constant: constants.contains(name),
+            })
+    }
+
+    pub fn push_scope(&mut self, readonly: bool) {
+        self.scopes.push(Scope::new(readonly));
     }
 
     pub fn pop_scope(&mut self) {
@@ -0,0 +1,43 @@
+use lexer::Pos;
+use parser::ast::{CodeBlock, Statement};
+use report::SourceId;
+
+use std::rc::Rc;
+
+/// A parsed program, created by `Interpreter::compile`. It can be run any number of times
+/// without lexing and parsing the source again. Running it evaluates the syntax tree in place,
+/// and clones share the tree.
+#[derive(Debug, Clone)]
+pub struct Script {
+    pub(crate) code: Rc<CodeBlock>,
+    pub(crate) source: Rc<SourceId>,
+}
+
+impl Script {
+    pub fn code(&self) -> &[Pos<Statement>] {
+        &self.code
+    }
+
+    /// The source code of the program within `Interpreter::sources`.
+    pub fn source(&self) -> SourceId {
+        *self.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Interpreter, Value};
+
+    #[test]
+    fn run_compiled_script() {
+        let mut interpreter = Interpreter::new();
+        let script = interpreter
+            .compile("count = if (count == null) { 1 } else { count + 1 }; count * 2")
+            .unwrap();
+        let copy = script.clone();
+        assert_eq!(interpreter.run(&script).unwrap(), Value::Integer(2));
+        assert_eq!(interpreter.run(&copy).unwrap(), Value::Integer(4));
+        assert_eq!(interpreter.run(&script).unwrap(), Value::Integer(6));
+        assert!(interpreter.compile("x = ").is_err());
+    }
+}
@@ -0,0 +1,509 @@
+//! Snapshots of the global state of an interpreter.
+//!
+//! A snapshot is a JSON document containing the globals and the prototypes of an interpreter,
+//! and all values reachable from them. Arrays, objects and functions are stored once in a heap
+//! and referenced by their index, so shared references and cycles survive a round trip.
+//!
+//! Script functions are stored as formatted source code and parsed again when the snapshot is
+//! restored. Native functions can't be stored; they are referenced by the global or prototype
+//! field which holds them instead, e.g. `print` or `$string.length`. They are looked up in the
+//! interpreter which restores the snapshot, which must therefore register the same libraries.
+//!
+//! Snapshots are only available with the `snapshot` feature, which adds a dependency on `serde_json`.
+
+use crate::values::{Array, Dictionary, Function, NativeFunction, Range, Value};
+use crate::Interpreter;
+use lexer::Pos;
+use parser::ast::{Ident, Statement, AST};
+use parser::fmt::{format_code, FormatOptions};
+use serde_json::{json, Map, Number, Value as Json};
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// The version of the snapshots written by this interpreter. Snapshots of older versions can be
+/// restored as well, since they only lack some kinds of values:
+///
+/// 1. The original format.
+/// 2. Adds constants.
+/// 3. Adds ranges.
+const VERSION: u64 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotError(pub String);
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+fn error<T>(message: impl Into<String>) -> Result<T, SnapshotError> {
+    Err(SnapshotError(message.into()))
+}
+
+impl Interpreter {
+    /// Serializes the globals and prototypes of the interpreter, and all values reachable from
+    /// them. Fails if a native function is not held by any global or prototype.
+    pub fn snapshot(&self) -> Result<String, SnapshotError> {
+        let mut writer = Writer {
+            heap: vec![],
+            ids: HashMap::new(),
+            natives: natives(self),
+        };
+
+        let mut prototypes = Map::new();
+        for (name, proto) in self.prototypes().iter() {
+            let proto = writer.value(&Value::Dictionary(Dictionary::clone(proto)))?;
+            prototypes.insert(name.to_string(), proto);
+        }
+        let mut globals = Map::new();
+        let mut readonly = Map::new();
+        let mut constants = Map::new();
+        for global in self.globals() {
+            if prototypes.contains_key(global.name) {
+                continue;
+            }
+            let value = writer.value(global.value)?;
+            match (global.readonly, global.constant) {
+                (true, _) => readonly.insert(global.name.to_owned(), value),
+                (false, true) => constants.insert(global.name.to_owned(), value),
+                (false, false) => globals.insert(global.name.to_owned(), value),
+            };
+        }
+
+        let snapshot = json!({
+            "version": VERSION,
+            "heap": writer.heap,
+            "prototypes": prototypes,
+            "globals": globals,
+            "readonly": readonly,
+            "constants": constants,
+        });
+        Ok(snapshot.to_string())
+    }
+
+    /// Replaces the globals and prototypes of the interpreter by those of a snapshot. If the
+    /// snapshot can't be restored, the interpreter is left unchanged.
+    pub fn restore(&mut self, snapshot: &str) -> Result<(), SnapshotError> {
+        let snapshot: Json = serde_json::from_str(snapshot)
+            .map_err(|err| SnapshotError(format!("invalid snapshot: {}", err)))?;
+        let version = match snapshot["version"].as_u64() {
+            Some(version) if (1..=VERSION).contains(&version) => version,
+            _ => return error("unsupported snapshot version"),
+        };
+        let heap = match snapshot["heap"].as_array() {
+            Some(heap) => heap,
+            None => return error("invalid snapshot: missing heap"),
+        };
+
+        let mut reader = Reader {
+            objects: Vec::with_capacity(heap.len()),
+            natives: natives(self)
+                .into_iter()
+                .map(|(_, (path, native))| (path, native))
+                .collect(),
+        };
+
+        // The prototypes are kept, so values of the interpreter keep using them.
+        let mut prototypes = vec![];
+        for (name, proto) in self.prototypes().iter() {
+            let id = snapshot["prototypes"][name]["ref"].as_u64();
+            match id {
+                Some(id) => prototypes.push((id as usize, Dictionary::clone(proto))),
+                None => return error(format!("invalid snapshot: missing prototype `{}`", name)),
+            }
+        }
+
+        // Arrays and objects are created before their contents, since they may contain cycles.
+        for (id, entry) in heap.iter().enumerate() {
+            let object = if entry.get("array").is_some() {
+                Value::Array(Array::new(vec![]))
+            } else if entry.get("object").is_some() {
+                match prototypes.iter().find(|(proto_id, _)| *proto_id == id) {
+                    Some((_, proto)) => Value::Dictionary(proto.clone()),
+                    None => Value::Dictionary(Dictionary::default()),
+                }
+            } else if let Some(Json::String(src)) = entry.get("function") {
+                Value::Function(parse_function(self, src)?)
+            } else {
+                return error(format!("invalid snapshot: invalid heap entry {}", id));
+            };
+            reader.objects.push(object);
+        }
+
+        let mut contents = Vec::with_capacity(heap.len());
+        for entry in heap {
+            if let Some(Json::Array(elements)) = entry.get("array") {
+                let elements = elements
+                    .iter()
+                    .map(|element| reader.value(element))
+                    .collect::<Result<Vec<_>, _>>()?;
+                contents.push(Contents::Array(elements));
+            } else if let Some(Json::Object(pairs)) = entry.get("object") {
+                let mut object = HashMap::with_capacity(pairs.len());
+                for (key, value) in pairs {
+                    object.insert(key.clone(), reader.value(value)?);
+                }
+                contents.push(Contents::Object(object));
+            } else {
+                contents.push(Contents::Function);
+            }
+        }
+        let mut globals = vec![];
+        let no_constants = Map::new();
+        let kinds = [
+            ("globals", GlobalKind::Global),
+            ("readonly", GlobalKind::Readonly),
+            ("constants", GlobalKind::Constant),
+        ];
+        for (key, kind) in kinds.iter() {
+            let variables = match (snapshot[key].as_object(), kind) {
+                (Some(variables), _) => variables,
+                // Constants were added in version 2.
+                (None, GlobalKind::Constant) if version < 2 => &no_constants,
+                (None, _) => return error(format!("invalid snapshot: missing {}", key)),
+            };
+            for (name, value) in variables {
+                globals.push((name.clone(), reader.value(value)?, *kind));
+            }
+        }
+
+        // Everything has been read, so the snapshot can't fail anymore.
+        for (object, contents) in reader.objects.iter().zip(contents) {
+            match (object, contents) {
+                (Value::Array(array), Contents::Array(elements)) => {
+                    *array.0.borrow_mut() = elements;
+                }
+                (Value::Dictionary(dictionary), Contents::Object(pairs)) => {
+                    *dictionary.0.borrow_mut() = pairs;
+                }
+                _ => {}
+            }
+        }
+        let names = self
+            .globals()
+            .map(|global| global.name.to_owned())
+            .filter(|name| !prototypes_contain(self, name))
+            .collect::<Vec<_>>();
+        for name in names {
+            self.scope.remove_global(&name);
+        }
+        for (name, value, kind) in globals {
+            match kind {
+                GlobalKind::Global => self.scope.set_global(name, value, false),
+                GlobalKind::Readonly => self.scope.set_global(name, value, true),
+                GlobalKind::Constant => self.scope.set_constant_global(name, value),
+            };
+        }
+        Ok(())
+    }
+}
+
+/// How a global is stored in a snapshot.
+#[derive(Debug, Clone, Copy)]
+enum GlobalKind {
+    Global,
+    Readonly,
+    Constant,
+}
+
+fn prototypes_contain(interpreter: &Interpreter, name: &str) -> bool {
+    interpreter
+        .prototypes()
+        .iter()
+        .any(|(proto, _)| *proto == name)
+}
+
+/// The native functions held by globals and prototypes, by their address.
+fn natives(interpreter: &Interpreter) -> HashMap<*const (), (String, NativeFunction)> {
+    let mut natives = HashMap::new();
+    let mut add = |path: String, value: &Value| {
+        if let Value::NativeFunction(native) = value {
+            let address = Rc::as_ptr(&native.0) as *const ();
+            natives
+                .entry(address)
+                .or_insert_with(|| (path, native.clone()));
+        }
+    };
+    // Readonly globals and prototype fields are preferred as names, since they are usually
+    // defined by libraries rather than by scripts.
+    let mut globals = interpreter.globals().collect::<Vec<_>>();
+    globals.sort_by_key(|global| (!global.readonly, global.name));
+    let (readonly, mutable) = globals.split_at(globals.partition_point(|global| global.readonly));
+    for global in readonly {
+        add(global.name.to_owned(), global.value);
+    }
+    for (name, proto) in interpreter.prototypes().iter() {
+        let proto = proto.0.deref().borrow();
+        let mut fields = proto.iter().collect::<Vec<_>>();
+        fields.sort_by_key(|(field, _)| *field);
+        for (field, value) in fields {
+            add(format!("{}.{}", name, field), value);
+        }
+    }
+    for global in mutable {
+        add(global.name.to_owned(), global.value);
+    }
+    natives
+}
+
+/// Parses a function as a new source, which is dropped again if the snapshot isn't restored.
+fn parse_function(interpreter: &mut Interpreter, src: &str) -> Result<Function, SnapshotError> {
+    let invalid = || SnapshotError(format!("invalid function `{}`", src));
+    let source = interpreter.add_source(Some("<snapshot>".to_owned()), src);
+    let offset = interpreter.sources[*source].offset();
+    let tokens = lexer::tokenize_at(src, offset).map_err(|_| invalid())?;
+    let mut code = parser::parse(&tokens).map_err(|_| invalid())?.value;
+    match code.pop().map(|statement| *statement.value.into_inner()) {
+        Some(AST::Function(function)) if code.is_empty() => {
+            let mut function = Function::new(function);
+            function.source = Some(source);
+            Ok(function)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+struct Writer {
+    heap: Vec<Json>,
+    /// The indices of arrays, objects and functions in the heap, by their address.
+    ids: HashMap<*const (), usize>,
+    natives: HashMap<*const (), (String, NativeFunction)>,
+}
+
+impl Writer {
+    fn value(&mut self, value: &Value) -> Result<Json, SnapshotError> {
+        let json = match value {
+            Value::Null => Json::Null,
+            Value::Boolean(boolean) => Json::Bool(*boolean),
+            Value::Integer(int) => Json::from(*int),
+            Value::Float(float) => match Number::from_f64(*float) {
+                Some(number) => json!({ "float": number }),
+                None => json!({ "float": float.to_string() }),
+            },
+            Value::String(string) => Json::String(string.clone()),
+            Value::Range(range) => json!({
+                "range": [range.start, range.end, range.step, range.inclusive]
+            }),
+            Value::Array(array) => self.reference(Rc::as_ptr(&array.0) as *const (), value)?,
+            Value::Dictionary(object) => {
+                self.reference(Rc::as_ptr(&object.0) as *const (), value)?
+            }
+            Value::Function(function) => {
+                self.reference(Rc::as_ptr(&function.function) as *const (), value)?
+            }
+            Value::NativeFunction(native) => {
+                let address = Rc::as_ptr(&native.0) as *const ();
+                match self.natives.get(&address) {
+                    Some((path, _)) => json!({ "native": path }),
+                    None => return error("can't serialize a native function without a name"),
+                }
+            }
+        };
+        Ok(json)
+    }
+
+    /// Adds an array, object or function to the heap, unless it has been added before.
+    fn reference(&mut self, address: *const (), value: &Value) -> Result<Json, SnapshotError> {
+        if let Some(id) = self.ids.get(&address) {
+            return Ok(json!({ "ref": id }));
+        }
+        let id = self.heap.len();
+        self.ids.insert(address, id);
+        // The entry is reserved before writing the contents, which may refer to it.
+        self.heap.push(Json::Null);
+        let entry = match value {
+            Value::Array(array) => {
+                let elements = array
+                    .0
+                    .deref()
+                    .borrow()
+                    .iter()
+                    .map(|element| self.value(element))
+                    .collect::<Result<Vec<_>, _>>()?;
+                json!({ "array": elements })
+            }
+            Value::Dictionary(object) => {
+                let mut pairs = Map::new();
+                for (key, value) in object.0.deref().borrow().iter() {
+                    pairs.insert(key.clone(), self.value(value)?);
+                }
+                json!({ "object": pairs })
+            }
+            Value::Function(function) => {
+                let mut ast = parser::ast::Function::clone(&function.function);
+                // Names which were given by an assignment are only stored in the value.
+                ast.name = function
+                    .name()
+                    .map(|name| Pos::new(0, 0, Ident(name.to_owned())));
+                let ast = AST::from(ast);
+                let statement = Pos::new(0, 0, Statement::Unterminated(Box::new(ast)));
+                let src = format_code(&[statement], &FormatOptions::default());
+                json!({ "function": src })
+            }
+            _ => unreachable!(),
+        };
+        self.heap[id] = entry;
+        Ok(json!({ "ref": id }))
+    }
+}
+
+enum Contents {
+    Array(Vec<Value>),
+    Object(HashMap<String, Value>),
+    Function,
+}
+
+struct Reader {
+    objects: Vec<Value>,
+    natives: HashMap<String, NativeFunction>,
+}
+
+impl Reader {
+    fn value(&self, json: &Json) -> Result<Value, SnapshotError> {
+        let value = match json {
+            Json::Null => Value::Null,
+            Json::Bool(boolean) => Value::Boolean(*boolean),
+            Json::Number(number) => match number.as_i64() {
+                Some(int) => Value::Integer(int),
+                None => return error(format!("invalid snapshot: invalid integer {}", number)),
+            },
+            Json::String(string) => Value::String(string.clone()),
+            Json::Object(object) => {
+                if let Some(float) = object.get("float") {
+                    match float {
+                        Json::Number(number) => Value::Float(number.as_f64().unwrap_or_default()),
+                        Json::String(float) => match float.parse() {
+                            Ok(float) => Value::Float(float),
+                            Err(..) => return error("invalid snapshot: invalid float"),
+                        },
+                        _ => return error("invalid snapshot: invalid float"),
+                    }
+                } else if let Some(range) = object.get("range") {
+                    match serde_json::from_value(range.clone()) {
+                        Ok((start, end, step, inclusive)) if step != 0 => Value::Range(Range {
+                            start,
+                            end,
+                            inclusive,
+                            step,
+                        }),
+                        _ => return error("invalid snapshot: invalid range"),
+                    }
+                } else if let Some(id) = object.get("ref").and_then(Json::as_u64) {
+                    match self.objects.get(id as usize) {
+                        Some(object) => object.clone(),
+                        None => {
+                            return error(format!("invalid snapshot: invalid reference {}", id))
+                        }
+                    }
+                } else if let Some(Json::String(path)) = object.get("native") {
+                    match self.natives.get(path) {
+                        Some(native) => Value::NativeFunction(native.clone()),
+                        None => return error(format!("unknown native function `{}`", path)),
+                    }
+                } else {
+                    return error("invalid snapshot: invalid value");
+                }
+            }
+            Json::Array(..) => return error("invalid snapshot: invalid value"),
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VERSION;
+    use crate::library::{Console, StandardLibrary};
+    use crate::{Interpreter, Object, Value};
+    use serde_json::Value as Json;
+
+    fn interpreter() -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.register(&StandardLibrary);
+        interpreter.register(&Console);
+        interpreter
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let mut original = interpreter();
+        original.set_readonly_global("limit", 3).unwrap();
+        let src = r#"
+            list = [1, 2.5, "three", true, null, 0.0 / 0.0];
+            o = { list: list, again: list, say: print };
+            o.self = o;
+            list[4] = o;
+            counter = {
+                count: 0,
+                increment: function(by) { this.count = this.count + by; this.count }
+            };
+            counter.increment(2);
+            $string.shout = function() { this + "!" };
+            length = $string.length;
+            const answer = 42;
+            evens = (10..=0).step(-2);
+        "#;
+        original.eval(src).unwrap();
+        let snapshot = original.snapshot().unwrap();
+
+        let mut restored = interpreter();
+        restored.set_global("unrelated", 1).unwrap();
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(restored.get_global("unrelated"), None);
+        assert_eq!(restored.get_global("limit"), Some(Value::Integer(3)));
+        assert!(restored.eval("limit = 4").is_err());
+        assert_eq!(restored.get_global("answer"), Some(Value::Integer(42)));
+        assert!(restored.eval("answer = 0").is_err());
+
+        let check = r#"
+            o.again[0] = 42;
+            [list[0], o.self.self.list[1], list[4].again[2], list[5] == list[5],
+             counter.increment(1), "hi".shout(), "four".length(), evens]
+        "#;
+        let result = restored.eval(check).unwrap();
+        assert_eq!(
+            result.to_string(),
+            "[42, 2.5, three, false, 3, hi!, 4, (10..=0).step(-2)]"
+        );
+        let say = restored.get_global("o").unwrap().get_field("say");
+        assert!(matches!(say, Some(Value::NativeFunction(..))));
+        let length = restored.get_global("length");
+        assert!(matches!(length, Some(Value::NativeFunction(..))));
+
+        let mut sandboxed = Interpreter::new();
+        let err = sandboxed.restore(&snapshot).unwrap_err();
+        assert!(err.0.starts_with("unknown native function"), "{}", err);
+        assert_eq!(sandboxed.get_global("list"), None);
+    }
+
+    #[test]
+    fn restore_older_versions() {
+        let mut original = interpreter();
+        original
+            .eval("list = [1, \"two\"]; list[2] = list;")
+            .unwrap();
+        let mut snapshot: Json = serde_json::from_str(&original.snapshot().unwrap()).unwrap();
+        // The first version didn't contain constants.
+        snapshot["version"] = Json::from(1);
+        snapshot.as_object_mut().unwrap().remove("constants");
+
+        let mut restored = interpreter();
+        restored.restore(&snapshot.to_string()).unwrap();
+        let result = restored.eval("[list[1], list[2][0]]").unwrap();
+        assert_eq!(result.to_string(), "[two, 1]");
+
+        snapshot["version"] = Json::from(2);
+        let err = restored.restore(&snapshot.to_string()).unwrap_err();
+        assert_eq!(err.0, "invalid snapshot: missing constants");
+
+        snapshot["version"] = Json::from(VERSION + 1);
+        let err = restored.restore(&snapshot.to_string()).unwrap_err();
+        assert_eq!(err.0, "unsupported snapshot version");
+    }
+}
@@ -32,10 +32,10 @@ impl Object for Array {
     }
 
     fn equal(&self, other: &Value) -> bool {
-        if let Value::Array(arr) = other {
-            *arr.0.deref().borrow() == *self.0.deref().borrow()
-        } else {
-            false
+        match other {
+            Value::Array(arr) => *arr.0.deref().borrow() == *self.0.deref().borrow(),
+            Value::Range(range) => range.equal(&Value::Array(self.clone())),
+            _ => false,
         }
     }
 
@@ -66,6 +66,15 @@ impl Object for Array {
 
                 Some(Value::Array(Array::new(sub_array)))
             }
+            Value::Range(range) => {
+                let sub_array = range
+                    .indices(this.len())
+                    .iter()
+                    .flat_map(i64::get_as)
+                    .map(|idx| this[idx as usize].clone())
+                    .collect::<Vec<_>>();
+                Some(Value::Array(Array::new(sub_array)))
+            }
             _ => None,
         }
     }
@@ -1,4 +1,5 @@
 use crate::values::{Object, Value};
+use crate::{HasTypeName, ObjectConversion};
 
 impl Object for bool {
     fn type_name(&self) -> &'static str {
@@ -28,3 +29,27 @@ impl Object for bool {
         }
     }
 }
+
+impl HasTypeName for bool {
+    fn type_name() -> &'static str {
+        "boolean"
+    }
+}
+
+impl ObjectConversion for bool {
+    fn get_as(value: Value) -> Option<Self> {
+        if let Value::Boolean(boolean) = value {
+            Some(boolean)
+        } else {
+            None
+        }
+    }
+
+    fn convert_from(value: &Value) -> Option<Self> {
+        if let Value::Boolean(boolean) = value {
+            Some(*boolean)
+        } else {
+            None
+        }
+    }
+}
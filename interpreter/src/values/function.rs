@@ -1,7 +1,7 @@
-use crate::values::{Object, Value};
-use crate::{Evaluate, Interpreter};
+use crate::values::{Array, Object, Value};
+use crate::{ErrorCode, Evaluate, Frame, Interpreter, KetamineError, Span};
+use report::SourceId;
 
-use parser::Pos;
 use std::cell::RefCell;
 use std::fmt::{Debug, Formatter, Result as FmtResult};
 use std::ops::Deref;
@@ -10,6 +10,10 @@ use std::rc::Rc;
 #[derive(Debug, Clone)]
 pub struct Function {
     pub function: Rc<parser::ast::Function>,
+    /// The name the function was declared with, or the variable it was first assigned to.
+    pub name: Option<Rc<str>>,
+    /// Keeps the source code of the function in `Interpreter::sources`.
+    pub(crate) source: Option<Rc<SourceId>>,
 }
 
 impl Object for Function {
@@ -26,10 +30,16 @@ impl Object for Function {
             .function
             .params
             .iter()
-            .map(|arg| arg.value.0.as_str())
-            .collect::<Vec<&str>>()
+            .map(|param| match param.value.rest {
+                true => format!("...{}", param.value.name.value.0),
+                false => param.value.name.value.0.clone(),
+            })
+            .collect::<Vec<String>>()
             .join(", ");
-        format!("function({}) {{..}}", args)
+        match self.name() {
+            Some(name) => format!("function {}({}) {{..}}", name, args),
+            None => format!("function({}) {{..}}", args),
+        }
     }
 
     fn equal(&self, other: &Value) -> bool {
@@ -50,32 +60,127 @@ impl Object for Function {
 
     fn call(
         &self,
-        _start: usize,
-        _end: usize,
+        start: usize,
+        end: usize,
         interpreter: &mut Interpreter,
         this: Value,
-        mut args: Vec<Value>,
-    ) -> Result<Value, Pos<String>> {
-        interpreter.scope.push_scope(false);
-        interpreter.scope.push_var("this".to_owned(), this, false);
+        args: Vec<Value>,
+    ) -> Result<Value, KetamineError> {
+        self.call_named(None, start, end, interpreter, this, args)
+    }
+}
+
+impl Function {
+    /// Creates a function value which shares the syntax tree of the function.
+    pub fn new(function: Rc<parser::ast::Function>) -> Self {
+        let name = function
+            .name
+            .as_ref()
+            .map(|name| Rc::from(name.value.0.as_str()));
+        Function {
+            function,
+            name,
+            source: None,
+        }
+    }
 
-        let arg_len = args.len().min(self.function.params.len());
-        for (idx, arg) in args.drain(..arg_len).enumerate() {
-            let param = self.function.params.get(idx).unwrap();
-            interpreter
-                .scope
-                .push_var(param.value.0.clone(), arg, false);
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Calls the function, recording the call in the call stack of the interpreter. Anonymous
+    /// functions are recorded under the name they were called by.
+    pub(crate) fn call_named(
+        &self,
+        name: Option<String>,
+        start: usize,
+        end: usize,
+        interpreter: &mut Interpreter,
+        this: Value,
+        args: Vec<Value>,
+    ) -> Result<Value, KetamineError> {
+        if interpreter.strict_arity {
+            self.check_arity(start, end, args.len())?;
         }
+        interpreter.call_stack.push(Frame {
+            name: self.name().map(str::to_owned).or(name),
+            span: Span::new(start, end),
+        });
+        interpreter.scope.push_scope(false);
+        interpreter.scope.push_var("this".to_owned(), this, false);
 
-        let result = self.function.body.clone().eval(interpreter);
+        let result = self
+            .bind_params(interpreter, args)
+            .and_then(|_| self.function.body.eval(interpreter));
         interpreter.scope.pop_scope();
+        let result = result.map_err(|err| err.with_stack(&interpreter.call_stack));
+        interpreter.call_stack.pop();
         Ok(result?.into_value())
     }
+
+    /// Defines the parameters in the scope of the call. Defaults are evaluated in that scope,
+    /// so they can refer to the parameters before them.
+    fn bind_params(
+        &self,
+        interpreter: &mut Interpreter,
+        args: Vec<Value>,
+    ) -> Result<(), KetamineError> {
+        let mut args = args.into_iter();
+        for param in &self.function.params {
+            let value = match (&param.value.default, param.value.rest) {
+                (_, true) => Value::Array(Array::new(args.by_ref().collect())),
+                (default, false) => match (args.next(), default) {
+                    (Some(arg), _) => arg,
+                    (None, Some(default)) => default.eval(interpreter)?.into_value(),
+                    (None, None) => Value::Null,
+                },
+            };
+            let name = param.value.name.value.0.clone();
+            interpreter.scope.push_var(name, value, false);
+        }
+        Ok(())
+    }
+
+    /// The number of arguments the function accepts, and `None` as the maximum if it has a
+    /// rest parameter.
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        let params = &self.function.params;
+        let required = params
+            .iter()
+            .filter(|param| param.value.default.is_none() && !param.value.rest)
+            .count();
+        match params.iter().any(|param| param.value.rest) {
+            true => (required, None),
+            false => (required, Some(params.len())),
+        }
+    }
+
+    fn check_arity(&self, start: usize, end: usize, len: usize) -> Result<(), KetamineError> {
+        let plural = |count: usize| match count {
+            1 => "argument",
+            _ => "arguments",
+        };
+        let expected = match self.arity() {
+            (min, Some(max)) if min <= len && len <= max => return Ok(()),
+            (min, None) if min <= len => return Ok(()),
+            (min, Some(max)) if min == max => format!("{} {}", min, plural(min)),
+            (min, Some(max)) => format!("{} to {} arguments", min, max),
+            (min, None) => format!("at least {} {}", min, plural(min)),
+        };
+        let msg = format!("expected {}, got {}", expected, len);
+        let err = KetamineError::runtime_with_code(ErrorCode::ArityMismatch, start, end, msg);
+        match self.name() {
+            Some(name) => {
+                Err(err.with_note(format!("`{}` is declared as {}", name, self.to_string())))
+            }
+            None => Err(err),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct NativeFunction(
-    pub Rc<RefCell<dyn Fn(&mut Interpreter, Value, Vec<Value>) -> Result<Value, String>>>,
+    pub Rc<RefCell<dyn Fn(&mut Interpreter, Value, Vec<Value>) -> Result<Value, KetamineError>>>,
 );
 
 impl Object for NativeFunction {
@@ -98,9 +203,9 @@ impl Object for NativeFunction {
         inter: &mut Interpreter,
         this: Value,
         args: Vec<Value>,
-    ) -> Result<Value, Pos<String>> {
+    ) -> Result<Value, KetamineError> {
         let function = self.0.deref().borrow();
-        function(inter, this, args).map_err(|x| Pos::new(start, end, x))
+        function(inter, this, args).map_err(|err| err.or_span(start, end))
     }
 }
 
@@ -112,8 +217,39 @@ impl Debug for NativeFunction {
 
 impl NativeFunction {
     pub fn new(
-        closure: impl Fn(&mut Interpreter, Value, Vec<Value>) -> Result<Value, String> + 'static,
+        closure: impl Fn(&mut Interpreter, Value, Vec<Value>) -> Result<Value, KetamineError> + 'static,
     ) -> Self {
         NativeFunction(Rc::new(RefCell::new(closure)))
     }
 }
+
+/// The arguments of a function called from Rust, e.g. a tuple of values or a `Vec<Value>`.
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Value>;
+}
+
+impl IntoArgs for Vec<Value> {
+    fn into_args(self) -> Vec<Value> {
+        self
+    }
+}
+
+macro_rules! impl_into_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: Object),*> IntoArgs for ($($arg,)*) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<Value> {
+                let ($($arg,)*) = self;
+                vec![$($arg.into_value()),*]
+            }
+        }
+    };
+}
+
+impl_into_args!();
+impl_into_args!(A);
+impl_into_args!(A, B);
+impl_into_args!(A, B, C);
+impl_into_args!(A, B, C, D);
+impl_into_args!(A, B, C, D, E);
+impl_into_args!(A, B, C, D, E, F);
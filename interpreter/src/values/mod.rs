@@ -5,6 +5,7 @@ mod float;
 mod function;
 mod integer;
 mod null;
+mod range;
 mod string;
 
 pub use array::*;
@@ -14,11 +15,11 @@ pub use float::*;
 pub use function::*;
 pub use integer::*;
 pub use null::*;
+pub use range::*;
 pub use string::*;
 
-use crate::Interpreter;
+use crate::{ErrorCode, Interpreter, KetamineError};
 use parser::ast::Ident;
-use parser::Pos;
 
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[derive(Debug, Clone)]
@@ -28,6 +29,7 @@ pub enum Value {
     Float(f64),
     Boolean(bool),
     Array(Array),
+    Range(Range),
     Dictionary(Dictionary),
     Function(Function),
     NativeFunction(NativeFunction),
@@ -42,6 +44,7 @@ impl Value {
             Value::Float(float) => float as &dyn Object,
             Value::Boolean(bool) => bool as &dyn Object,
             Value::Array(array) => array as &dyn Object,
+            Value::Range(range) => range as &dyn Object,
             Value::Dictionary(object) => object as &dyn Object,
             Value::Null => &() as &dyn Object,
             Value::Function(function) => function as &dyn Object,
@@ -119,9 +122,14 @@ pub trait Object {
         _scope: &mut Interpreter,
         _this: Value,
         _args: Vec<Value>,
-    ) -> Result<Value, Pos<String>> {
+    ) -> Result<Value, KetamineError> {
         let msg = format!("can't call a value of type {}", self.type_name());
-        Err(Pos::new(start, end, msg))
+        Err(KetamineError::runtime_with_code(
+            ErrorCode::NotCallable,
+            start,
+            end,
+            msg,
+        ))
     }
     fn get_index(&self, _idx: &Value) -> Option<Value> {
         None
@@ -190,7 +198,7 @@ impl Object for Value {
         scope: &mut Interpreter,
         this: Value,
         args: Vec<Value>,
-    ) -> Result<Value, Pos<String>> {
+    ) -> Result<Value, KetamineError> {
         self.as_dyn().call(start, end, scope, this, args)
     }
 
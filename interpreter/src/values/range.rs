@@ -0,0 +1,224 @@
+use crate::values::{Dictionary, HasPrototype, Object, Value};
+use crate::{HasTypeName, Interpreter, ObjectConversion};
+
+use std::convert::TryFrom;
+
+/// A sequence of integers like `0..10`, `0..=10` or `(10..0).step(-2)`, whose elements are
+/// computed when they are used.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub start: i64,
+    pub end: i64,
+    /// Whether `end` is part of the range, as in `0..=10`.
+    pub inclusive: bool,
+    /// The difference between consecutive elements, which is negative if the range is
+    /// descending. It is never zero.
+    pub step: i64,
+}
+
+impl Range {
+    /// Creates a range with a step of one. It is empty if `end` is less than `start`.
+    pub fn new(start: i64, end: i64, inclusive: bool) -> Self {
+        Range {
+            start,
+            end,
+            inclusive,
+            step: 1,
+        }
+    }
+
+    /// Returns a range with the same bounds, whose elements are `step` apart. A negative step
+    /// makes the range descending, as in `(10..0).step(-1)`.
+    pub fn with_step(self, step: i64) -> Result<Self, String> {
+        if step == 0 {
+            return Err("the step of a range can't be zero".to_owned());
+        }
+        Ok(Range { step, ..self })
+    }
+
+    /// The number of elements. It is computed in 128 bits, since it can exceed `i64::MAX`.
+    fn len(&self) -> i128 {
+        let (start, end, step) = (self.start as i128, self.end as i128, self.step as i128);
+        let distance = match step > 0 {
+            true => end - start,
+            false => start - end,
+        } + self.inclusive as i128;
+        match distance > 0 {
+            true => ceil_div(distance, step.abs()),
+            false => 0,
+        }
+    }
+
+    /// The number of elements, unless it doesn't fit into an integer, as for
+    /// `i64::MIN..i64::MAX`.
+    pub fn length(&self) -> Result<i64, String> {
+        i64::try_from(self.len())
+            .map_err(|_| format!("the range {} has too many elements", self.to_string()))
+    }
+
+    /// The element at `idx`, if the range has that many elements.
+    pub fn get(&self, idx: i128) -> Option<i64> {
+        match (0..self.len()).contains(&idx) {
+            true => Some((self.start as i128 + idx * self.step as i128) as i64),
+            false => None,
+        }
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        let offset = value as i128 - self.start as i128;
+        let step = self.step as i128;
+        offset % step == 0 && self.get(offset / step).is_some()
+    }
+
+    /// The elements of the range which are valid indices into a sequence of `len` elements, in
+    /// the order of the range. Since the elements are monotonic, they are a range themselves.
+    pub fn indices(&self, len: usize) -> Range {
+        let (start, step, len) = (self.start as i128, self.step as i128, len as i128);
+        // The positions of the first and after the last element within `0..len`.
+        let (first, last) = match step > 0 {
+            true => (ceil_div(-start, step), ceil_div(len - start, step)),
+            false => (ceil_div(start - len + 1, -step), ceil_div(start + 1, -step)),
+        };
+        let (first, last) = (first.max(0), last.min(self.len()));
+        match (self.get(first), self.get(last - 1)) {
+            (Some(start), Some(end)) if first < last => Range {
+                start,
+                end,
+                inclusive: true,
+                step: self.step,
+            },
+            _ => Range::new(0, 0, false),
+        }
+    }
+
+    pub fn iter(&self) -> RangeIterator {
+        RangeIterator {
+            range: *self,
+            pos: 0,
+        }
+    }
+}
+
+/// Divides, rounding towards positive infinity. `divisor` must be positive.
+fn ceil_div(dividend: i128, divisor: i128) -> i128 {
+    (dividend + divisor - 1).div_euclid(divisor)
+}
+
+impl Object for Range {
+    fn type_name(&self) -> &'static str {
+        <Self as HasTypeName>::type_name()
+    }
+
+    fn into_value(self) -> Value {
+        Value::Range(self)
+    }
+
+    fn to_string(&self) -> String {
+        let operator = match self.inclusive {
+            true => "..=",
+            false => "..",
+        };
+        let range = format!("{}{}{}", self.start, operator, self.end);
+        match self.step {
+            1 => range,
+            step => format!("({}).step({})", range, step),
+        }
+    }
+
+    /// Ranges are equal to ranges and arrays with the same elements.
+    fn equal(&self, other: &Value) -> bool {
+        match other {
+            Value::Range(other) => {
+                let len = self.len();
+                len == other.len()
+                    && (len == 0 || self.start == other.start)
+                    && (len <= 1 || self.step == other.step)
+            }
+            Value::Array(array) => {
+                let array = array.0.borrow();
+                array.len() as i128 == self.len()
+                    && array
+                        .iter()
+                        .zip(self.iter())
+                        .all(|(element, int)| element.equal(&int))
+            }
+            _ => false,
+        }
+    }
+
+    fn plus(&self, other: &Value) -> Result<Value, ()> {
+        match other {
+            Value::String(string) => Ok(Value::String(format!("{}{}", self.to_string(), string))),
+            _ => Err(()),
+        }
+    }
+
+    fn get_index(&self, idx: &Value) -> Option<Value> {
+        match idx {
+            Value::Integer(idx) => Some(self.get(*idx as i128).map_or(Value::Null, Value::Integer)),
+            Value::Range(range) => {
+                let indices = range.indices(self.len().min(usize::MAX as i128) as usize);
+                let (first, last) = (indices.start as i128, indices.end as i128);
+                let range = match (self.get(first), self.get(last)) {
+                    (Some(start), Some(end)) if indices.len() > 0 => Range {
+                        start,
+                        end,
+                        inclusive: true,
+                        step: self.step.saturating_mul(indices.step),
+                    },
+                    _ => Range::new(0, 0, false),
+                };
+                Some(Value::Range(range))
+            }
+            _ => None,
+        }
+    }
+
+    fn iterator(&self) -> Result<Box<dyn Iterator<Item = Value>>, String> {
+        Ok(Box::new(self.iter()))
+    }
+}
+
+pub struct RangeIterator {
+    range: Range,
+    pos: i128,
+}
+
+impl Iterator for RangeIterator {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let element = self.range.get(self.pos)?;
+        self.pos += 1;
+        Some(Value::Integer(element))
+    }
+}
+
+impl HasPrototype for Range {
+    fn get_prototype(interpreter: &Interpreter) -> &Dictionary {
+        &interpreter.range_proto
+    }
+}
+
+impl HasTypeName for Range {
+    fn type_name() -> &'static str {
+        "range"
+    }
+}
+
+impl ObjectConversion for Range {
+    fn get_as(value: Value) -> Option<Self> {
+        match value {
+            Value::Range(range) => Some(range),
+            _ => None,
+        }
+    }
+
+    fn convert_from(value: &Value) -> Option<Self> {
+        match value {
+            Value::Range(range) => Some(*range),
+            _ => None,
+        }
+    }
+}
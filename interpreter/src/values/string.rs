@@ -56,6 +56,16 @@ impl Object for String {
                 }
                 Some(Value::String(out))
             }
+            Value::Range(range) => {
+                let chars = self.chars().collect::<Vec<_>>();
+                let out = range
+                    .indices(chars.len())
+                    .iter()
+                    .flat_map(i64::get_as)
+                    .map(|idx| chars[idx as usize])
+                    .collect();
+                Some(Value::String(out))
+            }
             _ => None,
         }
     }
@@ -0,0 +1,70 @@
+//! Checks that running a compiled program doesn't copy it. The tests live in their own binary,
+//! since they replace the global allocator by one which counts allocations.
+
+use interpreter::{Interpreter, Script};
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Counts the allocations of each thread, so that tests can check how much a piece of code
+/// allocates while other tests run in parallel.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+/// Compiles a program twice, once with code which is never executed at each `{}`, and checks
+/// that running both programs allocates the same amount.
+fn assert_unused_code_is_free(template: &str) {
+    let mut interpreter = Interpreter::new();
+    let unused = "y = [1, 2, 3]; z = { a: y[0] };".repeat(100);
+    let compile = |interpreter: &mut Interpreter, code: &str| -> Script {
+        let src = template.replace("{}", &format!("if (false) {{ {} }};", code));
+        interpreter.compile(&src).unwrap()
+    };
+    let small = compile(&mut interpreter, "");
+    let large = compile(&mut interpreter, &unused);
+
+    let expected = interpreter.run(&small).unwrap();
+    assert_eq!(interpreter.run(&large).unwrap(), expected);
+    let small_allocations = allocations(|| drop(interpreter.run(&small)));
+    let large_allocations = allocations(|| drop(interpreter.run(&large)));
+    assert_eq!(small_allocations, large_allocations, "{}", template);
+}
+
+#[test]
+fn run_without_copying_the_program() {
+    assert_unused_code_is_free("x = 1; {} [x, x + 1]");
+}
+
+#[test]
+fn run_functions_without_copying_them() {
+    assert_unused_code_is_free(
+        "function f(x) { {} x + 1 };\n\
+         g = function(x) { {} x * 2 };\n\
+         total = 0;\n\
+         for (i in 0..3) { function h(y) { {} y }; k = function() { {} i }; total = total + h(k()) };\n\
+         [f(1), g(2), total]",
+    );
+}
@@ -0,0 +1,408 @@
+use interpreter::library::{Console, StandardLibrary};
+use interpreter::{Array, Interpreter, KetamineError, Value};
+use lint::{LintConfig, LintKind};
+use parser::ast::CodeBlock;
+use parser::fmt::{FormatError, FormatOptions};
+use parser::Pos;
+use report::{Diagnostic, Level, Renderer, SourceMap};
+
+use std::io::{stderr, stdin, stdout, IsTerminal, Read, Write};
+use std::process::exit;
+
+mod repl;
+
+const USAGE: &str = "\
+Usage: ketamine [OPTIONS] [FILE] [ARGS]...
+
+Runs the script FILE, or the script read from stdin if FILE is `-`. Without FILE,
+starts an interactive REPL if stdin is a terminal and reads the script from stdin
+otherwise. ARGS are passed to the script in the global `args` array.
+
+Options:
+    --tokens      print the tokens of the script instead of running it
+    --ast         print the syntax tree of the script instead of running it
+    --fmt         print the formatted script instead of running it
+    --lint        check the script for likely bugs instead of running it
+    --optimize    fold constant expressions before running the script or REPL input
+    --allow <lint>, --warn <lint>, --deny <lint>
+                  set the level of a lint, which is one of undefined-variable,
+                  unreachable-code, break-outside-loop, duplicate-key and
+                  shadowed-parameter
+    --indent <n>  indent formatted code by n spaces (default: 4)
+    --tabs        indent formatted code using tabs
+    --width <n>   break formatted lines longer than n columns (default: 100)
+    --no-color    don't color error messages
+    -h, --help    print this message
+    -V, --version print the version
+
+Exit codes:
+    0    the script ran successfully
+    1    the script failed to lex, parse or run, or a denied lint was found
+    2    invalid usage, or the script could not be read";
+
+/// The script failed to lex, parse or run.
+const EXIT_FAILURE: i32 = 1;
+/// The arguments were invalid, or the script could not be read.
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Run,
+    Tokens,
+    Ast,
+    Fmt,
+    Lint,
+    Help,
+    Version,
+}
+
+struct Options {
+    mode: Mode,
+    color: bool,
+    format: FormatOptions,
+    lints: LintConfig,
+    /// Whether code is optimized before it is run.
+    optimize: bool,
+    /// The path of the script, or `None` to read it from stdin.
+    file: Option<String>,
+    /// Whether stdin was explicitly requested using `-`.
+    stdin: bool,
+    args: Vec<String>,
+}
+
+fn main() {
+    exit(run_cli(std::env::args().skip(1), &mut stderr()));
+}
+
+/// Runs the command line interface with the given arguments, reporting errors to `err`.
+/// Returns the exit code of the process.
+fn run_cli(args: impl Iterator<Item = String>, err: &mut impl Write) -> i32 {
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(msg) => {
+            writeln!(err, "error: {}\n\n{}", msg, USAGE).unwrap();
+            return EXIT_USAGE;
+        }
+    };
+
+    let renderer = Renderer::default().with_color(options.color);
+    let interactive = options.file.is_none() && !options.stdin && stdin().is_terminal();
+    match options.mode {
+        Mode::Help => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Mode::Version => {
+            println!("ketamine {}", env!("CARGO_PKG_VERSION"));
+            return 0;
+        }
+        Mode::Run if interactive => return repl::run(renderer, options.optimize),
+        _ => (),
+    }
+
+    let (name, src) = match read_script(options.file.as_deref()) {
+        Ok(script) => script,
+        Err(msg) => {
+            writeln!(err, "error: {}", msg).unwrap();
+            return EXIT_USAGE;
+        }
+    };
+
+    let mut sources = SourceMap::new();
+    sources.add(Some(name.clone()), src.clone());
+    let diagnostics = match options.mode {
+        Mode::Run => run(&name, &src, options.args, options.optimize, &mut sources),
+        Mode::Tokens => dump_tokens(&src),
+        Mode::Ast => dump_ast(&src),
+        Mode::Fmt => format(&src, &options.format),
+        Mode::Lint => lint(&src, options.lints),
+        Mode::Help | Mode::Version => unreachable!(),
+    };
+    for diagnostic in &diagnostics {
+        renderer
+            .render_sources_io(err, diagnostic, &sources)
+            .unwrap();
+        writeln!(err).unwrap();
+    }
+    match diagnostics.iter().any(|diag| diag.level == Level::Error) {
+        true => EXIT_FAILURE,
+        false => 0,
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        mode: Mode::Run,
+        color: stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        format: FormatOptions::default(),
+        lints: LintConfig::new(),
+        optimize: false,
+        file: None,
+        stdin: false,
+        args: vec![],
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tokens" => options.mode = Mode::Tokens,
+            "--ast" => options.mode = Mode::Ast,
+            "--fmt" => options.mode = Mode::Fmt,
+            "--indent" => options.format.indent_width = number_arg(&arg, args.next())?,
+            "--width" => options.format.line_width = number_arg(&arg, args.next())?,
+            "--tabs" => options.format.use_tabs = true,
+            "--lint" => options.mode = Mode::Lint,
+            "--optimize" => options.optimize = true,
+            "--allow" | "--warn" | "--deny" => {
+                let kind = args
+                    .next()
+                    .ok_or_else(|| format!("missing value for `{}`", arg))?
+                    .parse::<LintKind>()?;
+                let level = match arg.as_str() {
+                    "--allow" => lint::Level::Allow,
+                    "--warn" => lint::Level::Warn,
+                    _ => lint::Level::Deny,
+                };
+                options.lints = options.lints.with_level(kind, level);
+            }
+            "--no-color" => options.color = false,
+            "-h" | "--help" => {
+                options.mode = Mode::Help;
+                break;
+            }
+            "-V" | "--version" => {
+                options.mode = Mode::Version;
+                break;
+            }
+            "-" => {
+                options.stdin = true;
+                break;
+            }
+            "--" => {
+                options.file = args.next().filter(|file| file != "-");
+                options.stdin = options.file.is_none();
+                break;
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+            _ => {
+                options.file = Some(arg);
+                break;
+            }
+        }
+    }
+
+    options.args = args.collect();
+    Ok(options)
+}
+
+fn number_arg(option: &str, value: Option<String>) -> Result<usize, String> {
+    let value = value.ok_or_else(|| format!("missing value for `{}`", option))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{}` for `{}`", value, option))
+}
+
+/// Reads the script, returning its name and its source code.
+fn read_script(file: Option<&str>) -> Result<(String, String), String> {
+    match file {
+        Some(path) => std::fs::read_to_string(path)
+            .map(|src| (path.to_owned(), src))
+            .map_err(|err| format!("could not read {}: {}", path, err)),
+        None => {
+            let mut src = String::new();
+            stdin()
+                .read_to_string(&mut src)
+                .map_err(|err| format!("could not read stdin: {}", err))?;
+            Ok(("<stdin>".to_owned(), src))
+        }
+    }
+}
+
+/// Parses `src`, returning every error found in it.
+fn check(src: &str) -> Result<Pos<CodeBlock>, Vec<Diagnostic>> {
+    let tokens = lexer::tokenize(src).map_err(|err| vec![KetamineError::from(err).diagnostic()])?;
+    parser::parse(&tokens).map_err(|errors| {
+        errors
+            .into_iter()
+            .map(|err| KetamineError::from(err).diagnostic())
+            .collect::<Vec<_>>()
+    })
+}
+
+/// Runs the script. Errors of a running script may point into code passed to `eval`, so
+/// `sources` is replaced by the sources of the interpreter.
+fn run(
+    name: &str,
+    src: &str,
+    args: Vec<String>,
+    optimize: bool,
+    sources: &mut SourceMap,
+) -> Vec<Diagnostic> {
+    if let Err(diagnostics) = check(src) {
+        return diagnostics;
+    }
+
+    let mut interpreter = new_interpreter(args).with_optimizer(optimize);
+    let result = interpreter.eval_file(name, src);
+    *sources = interpreter.sources().clone();
+    match result {
+        Ok(..) => vec![],
+        Err(err) => err.diagnostics(),
+    }
+}
+
+/// Creates an interpreter with the standard library and console access.
+fn new_interpreter(args: Vec<String>) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.register(&StandardLibrary);
+    interpreter.register(&Console);
+    let args = args.into_iter().map(Value::String).collect();
+    interpreter
+        .set_readonly_global("args", Array::new(args))
+        .expect("the host may always define globals");
+    interpreter
+}
+
+fn lint(src: &str, config: LintConfig) -> Vec<Diagnostic> {
+    let code = match check(src) {
+        Ok(code) => code,
+        Err(diagnostics) => return diagnostics,
+    };
+
+    // Variables defined by the libraries of the interpreter are known to the linter.
+    let interpreter = new_interpreter(vec![]);
+    let globals = interpreter.globals().map(|global| global.name);
+    let config = config.with_globals(globals);
+    lint::lint(&code.value, &config)
+        .iter()
+        .map(lint::Lint::diagnostic)
+        .collect()
+}
+
+fn dump_tokens(src: &str) -> Vec<Diagnostic> {
+    let tokens = match lexer::tokenize(src) {
+        Ok(tokens) => tokens,
+        Err(err) => return vec![KetamineError::from(err).diagnostic()],
+    };
+    let mut out = stdout();
+    for token in tokens {
+        let span = format!("{}..{}", token.start, token.end);
+        writeln!(out, "{: <10} {:?}", span, token.value).unwrap();
+    }
+    vec![]
+}
+
+fn dump_ast(src: &str) -> Vec<Diagnostic> {
+    let tokens = match lexer::tokenize(src) {
+        Ok(tokens) => tokens,
+        Err(err) => return vec![KetamineError::from(err).diagnostic()],
+    };
+    let (code, errors) = parser::parse_recovering(&tokens);
+    parser::tree::write_tree(&code.value, stdout()).unwrap();
+    errors
+        .into_iter()
+        .map(|err| KetamineError::from(err).diagnostic())
+        .collect()
+}
+
+fn format(src: &str, options: &FormatOptions) -> Vec<Diagnostic> {
+    match parser::fmt::format(src, options) {
+        Ok(formatted) => {
+            print!("{}", formatted);
+            vec![]
+        }
+        Err(FormatError::Lex(err)) => vec![KetamineError::from(err).diagnostic()],
+        Err(FormatError::Parse(errors)) => errors
+            .into_iter()
+            .map(|err| KetamineError::from(err).diagnostic())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interpreter::{ErrorCode, Object};
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Runs the command line interface on a script, returning the exit code and the errors.
+    fn run_script(name: &str, src: &str, options: &[&str]) -> (i32, String) {
+        let path = std::env::temp_dir().join(format!("ketamine-cli-{}.ket", name));
+        std::fs::write(&path, src).unwrap();
+        let path = path.to_str().unwrap();
+        let mut err = vec![];
+        let options = options.iter().copied().chain(["--no-color", path]);
+        let code = run_cli(options.map(str::to_owned), &mut err);
+        (code, String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn parse_arguments() {
+        let options = parse_args(args(&["--fmt", "--width", "80", "x.ket", "--ast", "a"])).unwrap();
+        assert_eq!(options.mode, Mode::Fmt);
+        assert_eq!(options.format.line_width, 80);
+        assert_eq!(options.file.as_deref(), Some("x.ket"));
+        assert_eq!(options.args, vec!["--ast", "a"]);
+
+        let options = parse_args(args(&["--", "-", "a"])).unwrap();
+        assert_eq!((options.file, options.stdin), (None, true));
+        assert_eq!(options.args, vec!["a"]);
+
+        let err = parse_args(args(&["--indent", "wide"])).err().unwrap();
+        assert_eq!(err, "invalid value `wide` for `--indent`");
+        let err = parse_args(args(&["--deny"])).err().unwrap();
+        assert_eq!(err, "missing value for `--deny`");
+        let options = parse_args(args(&["--help", "--bogus"])).unwrap();
+        assert_eq!(options.mode, Mode::Help);
+    }
+
+    #[test]
+    fn exit_codes() {
+        let mut err = vec![];
+        assert_eq!(run_cli(args(&["--bogus", "x.ket"]), &mut err), EXIT_USAGE);
+        let err = String::from_utf8(err).unwrap();
+        assert!(
+            err.starts_with("error: unknown option `--bogus`\n\nUsage:"),
+            "{}",
+            err
+        );
+
+        let mut err = vec![];
+        let missing = "/nonexistent/script.ket";
+        assert_eq!(run_cli(args(&[missing]), &mut err), EXIT_USAGE);
+
+        assert_eq!(run_script("ok", "x = 1 + 2;", &[]), (0, String::new()));
+        let (code, err) = run_script("runtime", "x = 1;\nx()", &[]);
+        assert_eq!(code, EXIT_FAILURE);
+        assert!(err.contains("error[E0204]"), "{}", err);
+        let (code, err) = run_script("syntax", "x = = 1;", &[]);
+        assert_eq!(code, EXIT_FAILURE);
+        assert!(err.contains("error[E0101]"), "{}", err);
+        let (code, _) = run_script(
+            "lint",
+            "print(y);",
+            &["--lint", "--deny", "undefined-variable"],
+        );
+        assert_eq!(code, EXIT_FAILURE);
+
+        let src = "if (1 > 2) { 1 } else { null() }";
+        let (code, err) = run_script("optimized", src, &["--optimize"]);
+        assert_eq!(code, EXIT_FAILURE);
+        assert!(err.contains("error[E0204]"), "{}", err);
+        assert!(err.contains(":1:25"), "{}", err);
+    }
+
+    #[test]
+    fn pass_readonly_args() {
+        let mut interpreter = new_interpreter(vec!["a".to_owned(), "b".to_owned()]);
+        let args = interpreter.eval("args").unwrap();
+        assert_eq!(args.to_string(), "[a, b]");
+        let err = interpreter.eval("args = [];").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ReadonlyAssignment);
+    }
+}
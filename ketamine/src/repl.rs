@@ -0,0 +1,330 @@
+use interpreter::{Interpreter, KetamineError, Object, Value};
+use lexer::{LexingError, TokenValue};
+use report::Renderer;
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+
+use std::io::stdout;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const HELP: &str = "\
+Enter code to evaluate it. Variables are kept between inputs, and an input spans multiple
+lines until all of its braces, brackets, parentheses and strings are closed.
+
+Commands:
+    :help         print this message
+    :reset        discard all variables and start over
+    :type <expr>  print the type of an expression
+    :ast <expr>   print the syntax tree of an expression
+    :quit         exit the REPL (or press Ctrl-D)";
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+
+/// Results longer than this are printed across multiple lines.
+const MAX_WIDTH: usize = 80;
+
+struct Repl {
+    interpreter: Interpreter,
+    renderer: Renderer,
+    /// Whether input is optimized before it is run.
+    optimize: bool,
+}
+
+/// Runs the REPL until the user exits it, returning the exit code.
+pub fn run(renderer: Renderer, optimize: bool) -> i32 {
+    let mut editor = Editor::<()>::new();
+    let history = history_file();
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+
+    let mut repl = Repl {
+        interpreter: crate::new_interpreter(vec![]).with_optimizer(optimize),
+        renderer,
+        optimize,
+    };
+    println!(
+        "ketamine {} - type :help for help",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                if !input.is_empty() {
+                    input.push('\n');
+                }
+                input.push_str(&line);
+                if is_incomplete(&input) {
+                    continue;
+                }
+                editor.add_history_entry(input.as_str());
+                let input = std::mem::take(&mut input);
+                if !repl.handle(input.trim()) {
+                    break;
+                }
+            }
+            Err(ReadlineError::Interrupted) => input.clear(),
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                break;
+            }
+        }
+    }
+
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
+    }
+    0
+}
+
+impl Repl {
+    /// Handles a single input, returning `false` if the REPL should exit.
+    fn handle(&mut self, input: &str) -> bool {
+        let (command, arg) = match input.strip_prefix(':') {
+            Some(command) => {
+                let mut parts = command.splitn(2, char::is_whitespace);
+                let name = parts.next().unwrap_or_default();
+                (Some(name), parts.next().unwrap_or_default().trim())
+            }
+            None => (None, input),
+        };
+
+        match command {
+            None if input.is_empty() => {}
+            None => {
+                if let Some(value) = self.eval(input) {
+                    if value != Value::Null {
+                        println!("{}", pretty(&value, 0, &mut vec![]));
+                    }
+                }
+            }
+            Some("help") => println!("{}", HELP),
+            Some("reset") => {
+                self.interpreter = crate::new_interpreter(vec![]).with_optimizer(self.optimize);
+                println!("all variables have been discarded");
+            }
+            Some("type") => {
+                if let Some(value) = self.eval(arg) {
+                    println!("{}", value.type_name());
+                }
+            }
+            Some("ast") => self.print_ast(arg),
+            Some("quit") | Some("q") => return false,
+            Some(other) => eprintln!("unknown command `:{}`, type :help for help", other),
+        }
+        true
+    }
+
+    fn eval(&mut self, src: &str) -> Option<Value> {
+        match self.interpreter.eval_file("<repl>", src) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                // Errors may point into earlier inputs, e.g. into the body of a function.
+                for diagnostic in err.diagnostics() {
+                    let rendered = self
+                        .renderer
+                        .render_sources_string(&diagnostic, self.interpreter.sources());
+                    eprint!("{}", rendered);
+                }
+                None
+            }
+        }
+    }
+
+    fn print_ast(&self, src: &str) {
+        let tokens = match lexer::tokenize(src) {
+            Ok(tokens) => tokens,
+            Err(err) => return self.report(src, err.into()),
+        };
+        let (code, errors) = parser::parse_recovering(&tokens);
+        parser::tree::write_tree(&code.value, stdout()).unwrap();
+        for err in errors {
+            self.report(src, err.into());
+        }
+    }
+
+    fn report(&self, src: &str, err: KetamineError) {
+        let rendered = self
+            .renderer
+            .render_string(&err.diagnostic(), src, Some("<repl>"));
+        eprint!("{}", rendered);
+    }
+}
+
+/// Checks if `src` contains braces, brackets, parentheses or a string which have not been closed
+/// yet.
+fn is_incomplete(src: &str) -> bool {
+    let tokens = match lexer::tokenize(src) {
+        Ok(tokens) => tokens,
+        // Strings may span multiple lines, so an unterminated one may be continued.
+        Err(LexingError(pos)) => return src[pos..].starts_with('"'),
+    };
+    let depth = tokens.iter().fold(0i64, |depth, token| match token.value {
+        TokenValue::ParenthesesOpen | TokenValue::BracketOpen | TokenValue::BraceOpen => depth + 1,
+        TokenValue::ParenthesesClose | TokenValue::BracketClose | TokenValue::BraceClose => {
+            depth - 1
+        }
+        _ => depth,
+    });
+    depth > 0
+}
+
+fn history_file() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(PathBuf::from(home).join(".ketamine_history"))
+}
+
+/// Formats a value for display, quoting strings and breaking long arrays and objects across
+/// multiple lines. `seen` holds the arrays and objects currently being printed, so that cyclic
+/// values are only printed once.
+fn pretty(value: &Value, indent: usize, seen: &mut Vec<*const ()>) -> String {
+    let compact = compact(value, seen);
+    if indent + compact.len() <= MAX_WIDTH {
+        return compact;
+    }
+
+    let padding = " ".repeat(indent + 4);
+    match value {
+        Value::Array(array) => {
+            let ptr = Rc::as_ptr(&array.0) as *const ();
+            seen.push(ptr);
+            let elements = array
+                .0
+                .deref()
+                .borrow()
+                .iter()
+                .map(|element| format!("{}{},\n", padding, pretty(element, indent + 4, seen)))
+                .collect::<String>();
+            seen.pop();
+            format!("[\n{}{}]", elements, " ".repeat(indent))
+        }
+        Value::Dictionary(dict) => {
+            let ptr = Rc::as_ptr(&dict.0) as *const ();
+            seen.push(ptr);
+            let dict = dict.0.deref().borrow();
+            let mut keys = dict.keys().collect::<Vec<_>>();
+            keys.sort();
+            let pairs = keys
+                .into_iter()
+                .map(|key| {
+                    let value = pretty(&dict[key], indent + 4, seen);
+                    format!("{}{}: {},\n", padding, key, value)
+                })
+                .collect::<String>();
+            seen.pop();
+            format!("{{\n{}{}}}", pairs, " ".repeat(indent))
+        }
+        _ => compact,
+    }
+}
+
+fn compact(value: &Value, seen: &mut Vec<*const ()>) -> String {
+    match value {
+        Value::String(string) => format!("{:?}", string),
+        Value::Array(array) => {
+            let ptr = Rc::as_ptr(&array.0) as *const ();
+            if seen.contains(&ptr) {
+                return "[...]".to_owned();
+            }
+            seen.push(ptr);
+            let elements = array
+                .0
+                .deref()
+                .borrow()
+                .iter()
+                .map(|element| compact(element, seen))
+                .collect::<Vec<_>>()
+                .join(", ");
+            seen.pop();
+            format!("[{}]", elements)
+        }
+        Value::Dictionary(dict) => {
+            let ptr = Rc::as_ptr(&dict.0) as *const ();
+            if seen.contains(&ptr) {
+                return "{...}".to_owned();
+            }
+            seen.push(ptr);
+            let dict = dict.0.deref().borrow();
+            let mut keys = dict.keys().collect::<Vec<_>>();
+            keys.sort();
+            let pairs = keys
+                .into_iter()
+                .map(|key| format!("{}: {}", key, compact(&dict[key], seen)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            seen.pop();
+            format!("{{{}}}", pairs)
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interpreter::library::StandardLibrary;
+
+    #[test]
+    fn detect_incomplete_input() {
+        assert!(!is_incomplete("x = 1;"));
+        assert!(is_incomplete("f = function(a) {"));
+        assert!(is_incomplete("x = [1, (2"));
+        assert!(!is_incomplete("x = [1, (2)]"));
+        assert!(is_incomplete("s = \"first line"));
+        assert!(!is_incomplete("s = \"{ [ (\""));
+        assert!(is_incomplete("s = \"a\\\"b"));
+        // Too many closing delimiters or illegal tokens can't be fixed by more input.
+        assert!(!is_incomplete("x = 1 }"));
+        assert!(!is_incomplete("x = @ {"));
+    }
+
+    fn pretty_eval(src: &str) -> String {
+        let mut interpreter = Interpreter::new();
+        interpreter.register(&StandardLibrary);
+        let value = interpreter.eval(src).unwrap();
+        pretty(&value, 0, &mut vec![])
+    }
+
+    #[test]
+    fn pretty_print_values() {
+        assert_eq!(pretty_eval("[1, \"a\\n\", null]"), "[1, \"a\\n\", null]");
+        assert_eq!(pretty_eval("{b: 2, a: [1]}"), "{a: [1], b: 2}");
+
+        let values = (0..30)
+            .map(|i: i32| format!("{}", i * 1000))
+            .collect::<Vec<_>>();
+        let long = pretty_eval(&format!("{{name: \"x\", values: [{}]}}", values.join(", ")));
+        assert!(
+            long.starts_with("{\n    name: \"x\",\n    values: [\n        0,\n"),
+            "{}",
+            long
+        );
+        assert!(long.ends_with("        29000,\n    ],\n}"), "{}", long);
+    }
+
+    #[test]
+    fn pretty_print_cycles() {
+        let object = pretty_eval("o = {a: 1}; o.self = o; o");
+        assert_eq!(object, "{a: 1, self: {...}}");
+        let array = pretty_eval("a = [1, 0]; a[1] = a; [a, a]");
+        assert_eq!(array, "[[1, [...]], [1, [...]]]");
+
+        let src = "o = {name: \"a long name which needs quite a lot of space\"}; o.self = o; o.list = [o]; o";
+        let long = pretty_eval(src);
+        assert_eq!(
+            long,
+            "{\n    list: [{...}],\n    name: \"a long name which needs quite a lot of space\",\n    self: {...},\n}"
+        );
+    }
+}
@@ -1,13 +1,14 @@
-use crate::{read_semicolon, read_separator, Pos, TokenValue};
+use crate::{Pos, TokenValue};
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::ops::Deref;
 
+/// Checks that a keyword is not just the beginning of a longer identifier, like `format`.
 fn is_separate_token(input: &str) -> bool {
-    input.is_empty()
-        || input.chars().next().unwrap().is_whitespace()
-        || read_separator(0, input).is_some()
-        || read_semicolon(0, input).is_some()
+    match input.chars().next() {
+        Some(c) => !(c.is_ascii_alphanumeric() || c == '_' || c == '$'),
+        None => true,
+    }
 }
 
 pub(crate) fn read_keyword(offset: usize, input: &str) -> Option<Pos<TokenValue>> {
@@ -22,6 +23,9 @@ pub(crate) fn read_keyword(offset: usize, input: &str) -> Option<Pos<TokenValue>
         keywords.insert("for", TokenValue::ForKeyword);
         keywords.insert("in", TokenValue::InKeyword);
         keywords.insert("while", TokenValue::WhileKeyword);
+        keywords.insert("let", TokenValue::LetKeyword);
+        keywords.insert("const", TokenValue::ConstKeyword);
+        keywords.insert("match", TokenValue::MatchKeyword);
         keywords.insert("true", TokenValue::Boolean(true));
         keywords.insert("false", TokenValue::Boolean(false));
         keywords
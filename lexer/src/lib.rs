@@ -13,7 +13,7 @@ mod token;
 use operator::*;
 pub use token::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LexingError(pub usize);
 
 impl Display for LexingError {
@@ -22,6 +22,8 @@ impl Display for LexingError {
     }
 }
 
+impl std::error::Error for LexingError {}
+
 impl LexingError {
     pub fn location(&self) -> usize {
         self.0
@@ -94,8 +96,26 @@ fn read_separator(offset: usize, input: &str) -> Option<Pos<TokenValue>> {
     })
 }
 
+fn read_spread(offset: usize, input: &str) -> Option<Pos<TokenValue>> {
+    if input.starts_with("...") {
+        Some(Pos {
+            start: offset,
+            end: offset + 3,
+            value: TokenValue::Spread,
+        })
+    } else {
+        None
+    }
+}
+
 fn read_range(offset: usize, input: &str) -> Option<Pos<TokenValue>> {
-    if input.starts_with("..") {
+    if input.starts_with("..=") {
+        Some(Pos {
+            start: offset,
+            end: offset + 3,
+            value: TokenValue::InclusiveRange,
+        })
+    } else if input.starts_with("..") {
         Some(Pos {
             start: offset,
             end: offset + 2,
@@ -155,18 +175,9 @@ fn read_colon(offset: usize, input: &str) -> Option<Pos<TokenValue>> {
 }
 
 fn read_string(offset: usize, input: &str) -> Option<Pos<TokenValue>> {
-    // TODO: unescape pasrsed string ("\n" should not be parsed as "\\n")
     let regex = Lazy::new(|| Regex::new(r#"^"(\\.|[^"\\])*""#).unwrap());
     let m = regex.deref().find(input)?;
-
-    let string = &input[(m.start() + 1)..(m.end() - 1)]
-        .replace(r"\n", "\n")
-        .replace(r"\t", "\t")
-        .replace(r"\r", "\r")
-        .replace(r"\0", "\0")
-        .replace(r"\\", r"\")
-        .replace(r#"\""#, r#"""#);
-    let value = TokenValue::String(string.to_owned());
+    let value = TokenValue::String(unescape(&input[(m.start() + 1)..(m.end() - 1)]));
 
     Some(Pos {
         start: offset + m.start(),
@@ -175,14 +186,68 @@ fn read_string(offset: usize, input: &str) -> Option<Pos<TokenValue>> {
     })
 }
 
+/// Replaces escape sequences like `\n` with the characters they stand for.
+/// Unknown escape sequences are kept as they are.
+fn unescape(string: &str) -> String {
+    let mut unescaped = String::with_capacity(string.len());
+    let mut chars = string.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('0') => unescaped.push('\0'),
+            Some('\\') => unescaped.push('\\'),
+            Some('"') => unescaped.push('"'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Reads a comment, which starts with `//` and extends to the end of the line.
+fn read_comment(offset: usize, input: &str) -> Option<Comment> {
+    if !input.starts_with("//") {
+        return None;
+    }
+    let len = input.find('\n').unwrap_or(input.len());
+    let comment = input[..len].trim_end();
+    Some(Pos {
+        start: offset,
+        end: offset + comment.len(),
+        value: comment.to_owned(),
+    })
+}
+
+/// A `//` comment, including the slashes.
+pub type Comment = Pos<String>;
+
 pub struct TokenIterator<'a> {
     input: &'a str,
     pos: usize,
+    comments: Vec<Comment>,
 }
 
 impl<'a> TokenIterator<'a> {
     pub fn new(input: &'a str) -> Self {
-        TokenIterator { input, pos: 0 }
+        TokenIterator {
+            input,
+            pos: 0,
+            comments: vec![],
+        }
+    }
+
+    /// The comments which have been skipped so far.
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
     }
 }
 
@@ -194,6 +259,11 @@ impl<'a> Iterator for TokenIterator<'a> {
         let pos: &mut usize = &mut self.pos;
 
         *pos += skip_whitespace(&input[*pos..]);
+        while let Some(comment) = read_comment(*pos, &input[*pos..]) {
+            *pos = comment.end;
+            *pos += skip_whitespace(&input[*pos..]);
+            self.comments.push(comment);
+        }
         if *pos >= input.len() {
             return None;
         }
@@ -201,6 +271,7 @@ impl<'a> Iterator for TokenIterator<'a> {
         let token = None
             .or_else(|| read_semicolon(*pos, &input[*pos..]))
             .or_else(|| read_comma(*pos, &input[*pos..]))
+            .or_else(|| read_spread(*pos, &input[*pos..]))
             .or_else(|| read_range(*pos, &input[*pos..]))
             .or_else(|| read_dot(*pos, &input[*pos..]))
             .or_else(|| read_colon(*pos, &input[*pos..]))
@@ -222,9 +293,133 @@ impl<'a> Iterator for TokenIterator<'a> {
 }
 
 pub fn tokenize(input: &str) -> Result<Vec<Pos<TokenValue>>, LexingError> {
+    Ok(tokenize_with_comments(input)?.0)
+}
+
+/// Like [tokenize], but the positions start at `offset` instead of zero, e.g. at the offset of
+/// a file within a source map.
+pub fn tokenize_at(input: &str, offset: usize) -> Result<Vec<Pos<TokenValue>>, LexingError> {
+    let mut tokens = tokenize(input).map_err(|LexingError(pos)| LexingError(pos + offset))?;
+    for token in &mut tokens {
+        token.start += offset;
+        token.end += offset;
+    }
+    Ok(tokens)
+}
+
+/// Like [tokenize], but also returns the comments, which are otherwise skipped.
+pub fn tokenize_with_comments(
+    input: &str,
+) -> Result<(Vec<Pos<TokenValue>>, Vec<Comment>), LexingError> {
     let mut tokens = vec![];
-    for token in TokenIterator::new(input) {
+    let mut iter = TokenIterator::new(input);
+    for token in &mut iter {
         tokens.push(token?);
     }
-    Ok(tokens)
+    Ok((tokens, iter.comments))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tokenize, tokenize_with_comments, LexingError, TokenValue};
+
+    fn values(input: &str) -> Vec<TokenValue> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.value)
+            .collect()
+    }
+
+    fn string(value: &str) -> TokenValue {
+        TokenValue::String(value.to_owned())
+    }
+
+    fn ident(name: &str) -> TokenValue {
+        TokenValue::Identifier(name.to_owned())
+    }
+
+    #[test]
+    fn unescape_strings() {
+        assert_eq!(values(r#""a\nb\t\"c\"""#), vec![string("a\nb\t\"c\"")]);
+        // An escaped backslash doesn't start another escape sequence.
+        assert_eq!(values(r#""\\n""#), vec![string("\\n")]);
+        assert_eq!(values(r#""\\\"""#), vec![string("\\\"")]);
+        // Unknown escape sequences are kept as they are.
+        assert_eq!(values(r#""\d\q""#), vec![string("\\d\\q")]);
+        assert_eq!(values("\"two\nlines\""), vec![string("two\nlines")]);
+        assert_eq!(tokenize(r#"x = "open"#), Err(LexingError(4)));
+    }
+
+    #[test]
+    fn skip_comments() {
+        let src = "// leading\nx = 1; // trailing  \n// a // b\ny";
+        let (tokens, comments) = tokenize_with_comments(src).unwrap();
+        let tokens = tokens
+            .into_iter()
+            .map(|token| token.value)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                ident("x"),
+                TokenValue::Assign,
+                TokenValue::Integer(1),
+                TokenValue::Semicolon,
+                ident("y"),
+            ]
+        );
+        let comments = comments
+            .iter()
+            .map(|comment| (comment.start, comment.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            comments,
+            vec![(0, "// leading"), (18, "// trailing"), (32, "// a // b")]
+        );
+        assert_eq!(
+            values("a / b // c"),
+            vec![ident("a"), TokenValue::Div, ident("b")]
+        );
+        assert_eq!(values("\"// no comment\""), vec![string("// no comment")]);
+    }
+
+    #[test]
+    fn separate_keywords_from_identifiers() {
+        assert_eq!(
+            values("format iffy in_range $if if_ truest constant"),
+            ["format", "iffy", "in_range", "$if", "if_", "truest", "constant"]
+                .iter()
+                .copied()
+                .map(ident)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            values("[true, false]"),
+            vec![
+                TokenValue::BracketOpen,
+                TokenValue::Boolean(true),
+                TokenValue::Comma,
+                TokenValue::Boolean(false),
+                TokenValue::BracketClose,
+            ]
+        );
+        assert_eq!(
+            values("for(i in a){}"),
+            vec![
+                TokenValue::ForKeyword,
+                TokenValue::ParenthesesOpen,
+                ident("i"),
+                TokenValue::InKeyword,
+                ident("a"),
+                TokenValue::ParenthesesClose,
+                TokenValue::BraceOpen,
+                TokenValue::BraceClose,
+            ]
+        );
+        assert_eq!(
+            values("true.x"),
+            vec![TokenValue::Boolean(true), TokenValue::Dot, ident("x")]
+        );
+    }
 }
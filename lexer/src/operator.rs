@@ -13,6 +13,7 @@ fn read_single_char(input: &str) -> Option<TokenValue> {
         "<" => Some(TokenValue::LessThan),
         ">" => Some(TokenValue::GreaterThan),
         "!" => Some(TokenValue::Negate),
+        "|" => Some(TokenValue::Pipe),
         _ => None,
     }
 }
@@ -26,6 +27,7 @@ fn read_two_chars(input: &str) -> Option<TokenValue> {
         "<=" => Some(TokenValue::LessEqThan),
         "==" => Some(TokenValue::Eq),
         "!=" => Some(TokenValue::NotEq),
+        "=>" => Some(TokenValue::FatArrow),
         _ => None,
     }
 }
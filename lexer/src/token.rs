@@ -58,6 +58,14 @@ impl<T> Pos<T> {
             value: &self.value,
         }
     }
+
+    pub fn as_mut(&mut self) -> Pos<&mut T> {
+        Pos {
+            start: self.start,
+            end: self.end,
+            value: &mut self.value,
+        }
+    }
 }
 
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
@@ -95,6 +103,10 @@ pub enum TokenValue {
     Dot,
 
     Range,
+    InclusiveRange,
+    Spread,
+    FatArrow,
+    Pipe,
 
     FunctionKeyword,
     ReturnKeyword,
@@ -105,6 +117,9 @@ pub enum TokenValue {
     ForKeyword,
     InKeyword,
     WhileKeyword,
+    LetKeyword,
+    ConstKeyword,
+    MatchKeyword,
 }
 
 impl Display for TokenValue {
@@ -132,6 +147,10 @@ impl TokenValue {
             TokenValue::Comma => ",",
             TokenValue::Dot => ".",
             TokenValue::Range => "..",
+            TokenValue::InclusiveRange => "..=",
+            TokenValue::Spread => "...",
+            TokenValue::FatArrow => "=>",
+            TokenValue::Pipe => "|",
             TokenValue::FunctionKeyword => "function",
             TokenValue::ReturnKeyword => "return",
             TokenValue::BreakKeyword => "break",
@@ -141,6 +160,9 @@ impl TokenValue {
             TokenValue::ForKeyword => "for",
             TokenValue::InKeyword => "in",
             TokenValue::WhileKeyword => "while",
+            TokenValue::LetKeyword => "let",
+            TokenValue::ConstKeyword => "const",
+            TokenValue::MatchKeyword => "match",
             TokenValue::Add => "+",
             TokenValue::Sub => "-",
             TokenValue::Mul => "*",
@@ -0,0 +1,296 @@
+//! Finds likely bugs in Ketamine code without running it.
+
+use lexer::Pos;
+use parser::ast::Statement;
+use report::{Diagnostic, Label};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+mod walker;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    /// A variable is read, but never assigned. Reading it yields `null`.
+    UndefinedVariable,
+    /// A statement follows a `return`, `break` or `continue` in the same block.
+    UnreachableCode,
+    /// A `break` or `continue` is not within a loop.
+    BreakOutsideLoop,
+    /// An object literal contains the same key twice, which fails at runtime.
+    DuplicateKey,
+    /// A function parameter has the same name as a variable of an enclosing scope, or as
+    /// another parameter.
+    ShadowedParameter,
+}
+
+impl LintKind {
+    pub const ALL: [LintKind; 5] = [
+        LintKind::UndefinedVariable,
+        LintKind::UnreachableCode,
+        LintKind::BreakOutsideLoop,
+        LintKind::DuplicateKey,
+        LintKind::ShadowedParameter,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LintKind::UndefinedVariable => "undefined-variable",
+            LintKind::UnreachableCode => "unreachable-code",
+            LintKind::BreakOutsideLoop => "break-outside-loop",
+            LintKind::DuplicateKey => "duplicate-key",
+            LintKind::ShadowedParameter => "shadowed-parameter",
+        }
+    }
+
+    /// The level of the lint, unless it is configured otherwise.
+    pub fn default_level(self) -> Level {
+        match self {
+            LintKind::DuplicateKey => Level::Deny,
+            _ => Level::Warn,
+        }
+    }
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for LintKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        LintKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.as_str() == name)
+            .ok_or_else(|| format!("unknown lint `{}`", name))
+    }
+}
+
+/// How severe a lint is. Lints which are allowed are not reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    levels: HashMap<LintKind, Level>,
+    globals: HashSet<String>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        LintConfig::default()
+    }
+
+    pub fn with_level(mut self, kind: LintKind, level: Level) -> Self {
+        self.levels.insert(kind, level);
+        self
+    }
+
+    /// Declares variables which are defined outside of the code, e.g. by a library.
+    pub fn with_globals<I, S>(mut self, globals: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.globals.extend(globals.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn level(&self, kind: LintKind) -> Level {
+        self.levels
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| kind.default_level())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    /// Either `Warn` or `Deny`, since allowed lints are not reported.
+    pub level: Level,
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+    /// Other locations related to the lint.
+    pub labels: Vec<Pos<String>>,
+    pub notes: Vec<String>,
+}
+
+impl Lint {
+    pub fn diagnostic(&self) -> Diagnostic {
+        let level = match self.level {
+            Level::Deny => report::Level::Error,
+            _ => report::Level::Warning,
+        };
+        let mut diagnostic = Diagnostic::new(level, &self.message)
+            .with_code(self.kind.as_str())
+            .with_label(Label::primary(self.start, self.end));
+        for label in &self.labels {
+            let label = Label::secondary(label.start, label.end).with_message(&label.value);
+            diagnostic = diagnostic.with_label(label);
+        }
+        for note in &self.notes {
+            diagnostic = diagnostic.with_note(note);
+        }
+        diagnostic
+    }
+}
+
+/// Lints a parsed program, returning the lints in the order they were found.
+pub fn lint(code: &[Pos<Statement>], config: &LintConfig) -> Vec<Lint> {
+    walker::Linter::new(config).lint(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lint, Level, LintConfig, LintKind};
+
+    fn lints(src: &str, config: &LintConfig) -> Vec<(LintKind, String)> {
+        let tokens = lexer::tokenize(src).unwrap();
+        let code = parser::parse(&tokens).unwrap();
+        lint(&code.value, config)
+            .into_iter()
+            .map(|lint| (lint.kind, src[lint.start..lint.end].to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn find_lints() {
+        let src = r#"
+            f = function(a, b) {
+                c = a + d;
+                for (x in b) { if (x) { continue; }; };
+                return c;
+                unreachable();
+                print(e);
+            };
+            if (f) { break; };
+            o = { a: 1, b: 2, a: 3 };
+            g = function(f) { this; null; f(g) };
+            h = function(p, p) {};
+        "#;
+        let config = LintConfig::new().with_globals(vec!["print"]);
+        assert_eq!(
+            lints(src, &config),
+            vec![
+                (LintKind::UndefinedVariable, "d".to_owned()),
+                (
+                    LintKind::UnreachableCode,
+                    "unreachable();\n                print(e)".to_owned()
+                ),
+                (LintKind::UndefinedVariable, "unreachable".to_owned()),
+                (LintKind::UndefinedVariable, "e".to_owned()),
+                (LintKind::BreakOutsideLoop, "break".to_owned()),
+                (LintKind::DuplicateKey, "a".to_owned()),
+                (LintKind::ShadowedParameter, "f".to_owned()),
+                (LintKind::ShadowedParameter, "p".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn configure_levels() {
+        let src = "x = y; o = { k: 1, k: 2 };";
+        let config = LintConfig::new()
+            .with_level(LintKind::UndefinedVariable, Level::Allow)
+            .with_level(LintKind::DuplicateKey, Level::Warn);
+        let tokens = lexer::tokenize(src).unwrap();
+        let code = parser::parse(&tokens).unwrap();
+        let found = lint(&code.value, &config);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, LintKind::DuplicateKey);
+        assert_eq!(found[0].level, Level::Warn);
+        assert_eq!(found[0].labels[0].start, 13);
+    }
+
+    #[test]
+    fn hoist_assignments() {
+        let src = "fib = function(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };\n\
+                   for (i in 0..3) { total = i; };\n\
+                   total;";
+        assert_eq!(lints(src, &LintConfig::new()), vec![]);
+
+        let src = "square(4);\n\
+                   function square(x) { half(x) * x };\n\
+                   if (true) { function half(x) { x / 2 }; };\n\
+                   half(square(2));";
+        assert_eq!(lints(src, &LintConfig::new()), vec![]);
+
+        let src = "for (i in 0..3) { if (i > 0) { previous; }; previous = i; };\n\
+                   f = function() { while (true) { x = y; y = 1; } };";
+        assert_eq!(lints(src, &LintConfig::new()), vec![]);
+    }
+
+    #[test]
+    fn read_before_assignment() {
+        let src = "print(z); z = 2;\n\
+                   y = x + 1; x = 1;\n\
+                   half(1); if (true) { function half(n) { n / 2 }; };\n\
+                   f = function() { w; w = 1; g = function() { w + v }; v = 2; };";
+        let config = LintConfig::new().with_globals(vec!["print"]);
+        assert_eq!(
+            lints(src, &config),
+            vec![
+                (LintKind::UndefinedVariable, "z".to_owned()),
+                (LintKind::UndefinedVariable, "x".to_owned()),
+                (LintKind::UndefinedVariable, "half".to_owned()),
+                (LintKind::UndefinedVariable, "w".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn scope_declarations_to_blocks() {
+        let src = "if (true) { let a = 1; const b = a; a = b; };
+                   while (false) { let c = 2; };
+                   let d = a + c;
+                   d;";
+        assert_eq!(
+            lints(src, &LintConfig::new()),
+            vec![
+                (LintKind::UndefinedVariable, "a".to_owned()),
+                (LintKind::UndefinedVariable, "c".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn bind_destructured_names() {
+        let src = "f = function() { total + [first, second] };
+                   [first, second = first, ...others] = [1];
+                   for ({key, value: v = key} in {a: 1}) { total = v; };
+                   {missing = unknown} = {};
+                   [key, value, f(), others, missing];";
+        assert_eq!(
+            lints(src, &LintConfig::new()),
+            vec![
+                (LintKind::UndefinedVariable, "unknown".to_owned()),
+                (LintKind::UndefinedVariable, "key".to_owned()),
+                (LintKind::UndefinedVariable, "value".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn bind_match_arms() {
+        let src = "match (x = [1]) { [a] | {a} if a > 0 => { a }, _ => { b = a } };
+                   [x, a, b];";
+        assert_eq!(
+            lints(src, &LintConfig::new()),
+            vec![
+                (LintKind::UndefinedVariable, "a".to_owned()),
+                (LintKind::UndefinedVariable, "a".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,440 @@
+use crate::{Level, Lint, LintConfig, LintKind};
+use lexer::Pos;
+use parser::ast::{
+    Assignment, Break, Continue, Destructuring, ForLoop, Function, Ident, If, Match, Object,
+    Pattern, Statement, VariableDeclaration, WhileLoop, AST,
+};
+use parser::visit::{
+    walk_assignment, walk_break, walk_code, walk_destructuring, walk_object, walk_statement,
+    walk_variable_declaration, Visitor,
+};
+
+use std::collections::{HashMap, HashSet};
+
+/// Names which are always defined.
+const BUILTINS: &[&str] = &["this", "null"];
+
+struct Scope {
+    /// The variables defined in this scope so far, and where they are first assigned.
+    variables: HashMap<String, Pos<()>>,
+    /// The variables assigned anywhere in the body of a function or loop, which are visible to
+    /// code that may run after the whole body, like nested functions or later iterations.
+    hoisted: HashMap<String, Pos<()>>,
+    /// Like in the interpreter, variables can't be assigned in readonly scopes.
+    readonly: bool,
+}
+
+pub(crate) struct Linter<'a> {
+    config: &'a LintConfig,
+    scopes: Vec<Scope>,
+    /// The number of loops enclosing the current node within the current function.
+    loops: usize,
+    lints: Vec<Lint>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(config: &'a LintConfig) -> Self {
+        Linter {
+            config,
+            scopes: vec![],
+            loops: 0,
+            lints: vec![],
+        }
+    }
+
+    pub fn lint(mut self, code: &[Pos<Statement>]) -> Vec<Lint> {
+        self.push_scope(false);
+        self.hoist(code);
+        self.visit_code(code);
+        self.lints
+    }
+
+    /// Reports a lint unless it is allowed.
+    fn emit(
+        &mut self,
+        kind: LintKind,
+        start: usize,
+        end: usize,
+        message: String,
+    ) -> Option<&mut Lint> {
+        let level = self.config.level(kind);
+        if level == Level::Allow {
+            return None;
+        }
+        self.lints.push(Lint {
+            kind,
+            level,
+            message,
+            start,
+            end,
+            labels: vec![],
+            notes: vec![],
+        });
+        self.lints.last_mut()
+    }
+
+    fn push_scope(&mut self, readonly: bool) {
+        self.scopes.push(Scope {
+            variables: HashMap::new(),
+            hoisted: HashMap::new(),
+            readonly,
+        });
+    }
+
+    /// Finds a variable in flow order: the body of the current function only sees the variables
+    /// assigned before, while the hoisted variables of enclosing functions and loops are visible.
+    fn lookup(&self, name: &str) -> Option<&Pos<()>> {
+        let function = self
+            .scopes
+            .iter()
+            .rposition(|scope| !scope.readonly)
+            .unwrap();
+        self.scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(idx, scope)| match idx == function {
+                true => scope.variables.get(name),
+                false => scope
+                    .variables
+                    .get(name)
+                    .or_else(|| scope.hoisted.get(name)),
+            })
+    }
+
+    /// Defines an assigned variable like the interpreter: unless a variable of the enclosing
+    /// blocks up to the innermost function or a global is assigned, it is defined in the
+    /// innermost scope which is not readonly.
+    fn assign(&mut self, name: &str, start: usize, end: usize) {
+        let function = self
+            .scopes
+            .iter()
+            .rposition(|scope| !scope.readonly)
+            .unwrap();
+        let exists = self.scopes[function..]
+            .iter()
+            .chain(self.scopes.first())
+            .any(|scope| scope.variables.contains_key(name));
+        if !exists {
+            let position = Pos::new(start, end, ());
+            self.scopes[function]
+                .variables
+                .insert(name.to_owned(), position);
+        }
+    }
+
+    /// Defines the names bound by a pattern, visiting the defaults of its elements in between.
+    /// Destructuring assigns the names, while the names of a for loop or a `match` arm are
+    /// defined in the scope of the loop or the arm.
+    fn bind_pattern(&mut self, pattern: &Pos<Pattern>, assign: bool) {
+        match &pattern.value {
+            Pattern::Ident(Ident(name)) if assign => self.assign(name, pattern.start, pattern.end),
+            Pattern::Ident(Ident(name)) => {
+                let position = Pos::new(pattern.start, pattern.end, ());
+                let scope = self.scopes.last_mut().unwrap();
+                scope.variables.insert(name.clone(), position);
+            }
+            Pattern::Wildcard | Pattern::Literal(..) | Pattern::Type(..) => {}
+            Pattern::Or(alternatives) => {
+                for alternative in alternatives {
+                    self.bind_pattern(alternative, assign);
+                }
+            }
+            Pattern::Array(elements) | Pattern::Object(elements) => {
+                for element in elements {
+                    if let Some(default) = &element.value.default {
+                        self.visit_expr(Pos::new(default.start, default.end, &*default.value));
+                    }
+                    self.bind_pattern(&element.value.pattern, assign);
+                }
+            }
+        }
+    }
+
+    /// Visits a block, which can declare variables of its own.
+    fn block(&mut self, code: &[Pos<Statement>]) {
+        self.push_scope(true);
+        self.visit_code(code);
+        self.scopes.pop();
+    }
+
+    /// Collects the variables assigned and the functions declared anywhere within a function
+    /// body, a loop body or the program, except in nested functions, into the innermost scope.
+    /// Functions are called after they are defined, so they may read variables which are assigned
+    /// after them, and so may later iterations of a loop.
+    fn hoist(&mut self, code: &[Pos<Statement>]) {
+        struct Hoist<'l, 'a> {
+            linter: &'l mut Linter<'a>,
+            /// The variables declared so far in each enclosing block, which assignments update
+            /// instead of defining a variable.
+            blocks: Vec<HashSet<String>>,
+        }
+
+        impl Visitor for Hoist<'_, '_> {
+            fn visit_code(&mut self, code: &[Pos<Statement>]) {
+                self.blocks.push(HashSet::new());
+                walk_code(self, code);
+                self.blocks.pop();
+            }
+
+            fn visit_assignment(&mut self, node: Pos<&Assignment>) {
+                let receiver = &node.value.receiver;
+                if let AST::Ident(Ident(name)) = &*receiver.value {
+                    if !self.blocks.iter().any(|block| block.contains(name)) {
+                        self.hoist(name, receiver.start, receiver.end);
+                    }
+                }
+                walk_assignment(self, node);
+            }
+
+            fn visit_destructuring(&mut self, node: Pos<&Destructuring>) {
+                for name in Pattern::bindings(&node.value.pattern) {
+                    if !self
+                        .blocks
+                        .iter()
+                        .any(|block| block.contains(&name.value.0))
+                    {
+                        self.hoist(&name.value.0, name.start, name.end);
+                    }
+                }
+                walk_destructuring(self, node);
+            }
+
+            fn visit_variable_declaration(&mut self, node: Pos<&VariableDeclaration>) {
+                let name = node.value.name.value.0.clone();
+                walk_variable_declaration(self, node);
+                self.blocks.last_mut().unwrap().insert(name);
+            }
+
+            fn visit_statement(&mut self, node: Pos<&Statement>) {
+                if let Some(Function {
+                    name: Some(name), ..
+                }) = node.value.declaration()
+                {
+                    self.hoist(&name.value.0, name.start, name.end);
+                }
+                walk_statement(self, node);
+            }
+
+            fn visit_function(&mut self, _node: Pos<&Function>) {}
+        }
+
+        impl Hoist<'_, '_> {
+            fn hoist(&mut self, name: &str, start: usize, end: usize) {
+                let scope = self.linter.scopes.last_mut().unwrap();
+                let position = Pos::new(start, end, ());
+                scope.hoisted.entry(name.to_owned()).or_insert(position);
+            }
+        }
+
+        let mut hoist = Hoist {
+            linter: self,
+            blocks: vec![],
+        };
+        hoist.visit_code(code);
+    }
+
+    /// Enters a loop. Loops outside of a function can't be exited from within it.
+    fn in_loop(&mut self, code: &[Pos<Statement>]) {
+        self.loops += 1;
+        self.push_scope(true);
+        self.hoist(code);
+        self.visit_code(code);
+        self.scopes.pop();
+        self.loops -= 1;
+    }
+
+    fn exit_outside_loop(&mut self, keyword: &str, start: usize, end: usize) {
+        if self.loops == 0 {
+            let message = format!("`{}` outside of a loop", keyword);
+            self.emit(LintKind::BreakOutsideLoop, start, end, message);
+        }
+    }
+}
+
+impl Visitor for Linter<'_> {
+    fn visit_code(&mut self, code: &[Pos<Statement>]) {
+        // Like in the interpreter, declared functions are defined when their block is entered.
+        for statement in code {
+            if let Some(Function {
+                name: Some(name), ..
+            }) = statement.value.declaration()
+            {
+                self.assign(&name.value.0, name.start, name.end);
+            }
+        }
+
+        let mut exit: Option<&Pos<Statement>> = None;
+        let mut reported = false;
+        for statement in code {
+            if let Some(exit) = exit.take() {
+                // Only the first unreachable statement of a block is reported, spanning the rest.
+                reported = true;
+                let end = code.last().unwrap().end;
+                let message = "unreachable code".to_owned();
+                if let Some(lint) =
+                    self.emit(LintKind::UnreachableCode, statement.start, end, message)
+                {
+                    let message = "any code following this expression is unreachable".to_owned();
+                    lint.labels.push(Pos::new(exit.start, exit.end, message));
+                }
+            }
+            self.visit_statement(statement.as_ref());
+            let ast = statement.value.inner();
+            let exits = matches!(ast, AST::Return(..) | AST::Break(..) | AST::Continue(..));
+            if exits && !reported {
+                exit = Some(statement);
+            }
+        }
+    }
+
+    fn visit_ident(&mut self, ident: Pos<&Ident>) {
+        let name = &ident.value.0;
+        let defined = BUILTINS.contains(&name.as_str())
+            || self.config.globals.contains(name)
+            || self.lookup(name).is_some();
+        if !defined {
+            let message = format!("use of undefined variable `{}`", name);
+            if let Some(lint) =
+                self.emit(LintKind::UndefinedVariable, ident.start, ident.end, message)
+            {
+                let note = "reading an undefined variable yields `null`".to_owned();
+                lint.notes.push(note);
+            }
+        }
+    }
+
+    fn visit_break(&mut self, node: Pos<&Break>) {
+        self.exit_outside_loop("break", node.start, node.end);
+        walk_break(self, node);
+    }
+
+    fn visit_continue(&mut self, node: Pos<&Continue>) {
+        self.exit_outside_loop("continue", node.start, node.end);
+    }
+
+    fn visit_assignment(&mut self, node: Pos<&Assignment>) {
+        let Assignment { receiver, value } = node.value;
+        match &*receiver.value {
+            AST::Ident(Ident(name)) => self.assign(name, receiver.start, receiver.end),
+            receiver_value => {
+                self.visit_expr(Pos::new(receiver.start, receiver.end, receiver_value))
+            }
+        }
+        self.visit_expr(Pos::new(value.start, value.end, &*value.value));
+    }
+
+    fn visit_destructuring(&mut self, node: Pos<&Destructuring>) {
+        let Destructuring { pattern, value } = node.value;
+        self.visit_expr(Pos::new(value.start, value.end, &*value.value));
+        self.bind_pattern(pattern, true);
+    }
+
+    fn visit_variable_declaration(&mut self, node: Pos<&VariableDeclaration>) {
+        let VariableDeclaration { name, value, .. } = node.value;
+        if let Some(value) = value {
+            self.visit_expr(Pos::new(value.start, value.end, &*value.value));
+        }
+        let position = Pos::new(name.start, name.end, ());
+        let scope = self.scopes.last_mut().unwrap();
+        scope.variables.insert(name.value.0.clone(), position);
+    }
+
+    fn visit_if(&mut self, node: Pos<&If>) {
+        let If {
+            if_branch,
+            else_if_branches,
+            else_branch,
+        } = node.value;
+        for branch in std::iter::once(if_branch).chain(else_if_branches) {
+            let condition = &branch.value.condition;
+            self.visit_expr(Pos::new(condition.start, condition.end, &*condition.value));
+            self.block(&branch.value.body);
+        }
+        if let Some(else_branch) = else_branch {
+            self.block(else_branch);
+        }
+    }
+
+    fn visit_match(&mut self, node: Pos<&Match>) {
+        let Match { value, arms } = node.value;
+        self.visit_expr(Pos::new(value.start, value.end, &*value.value));
+        for arm in arms {
+            self.push_scope(true);
+            self.bind_pattern(&arm.value.pattern, false);
+            if let Some(guard) = &arm.value.guard {
+                self.visit_expr(Pos::new(guard.start, guard.end, &*guard.value));
+            }
+            self.visit_code(&arm.value.body);
+            self.scopes.pop();
+        }
+    }
+
+    fn visit_function(&mut self, node: Pos<&Function>) {
+        let Function { params, body, .. } = node.value;
+        let loops = std::mem::replace(&mut self.loops, 0);
+        self.push_scope(false);
+        // Defaults are evaluated in the scope of the call and can use the parameters before them.
+        for param in params {
+            if let Some(default) = &param.value.default {
+                self.visit_expr(Pos::new(default.start, default.end, &*default.value));
+            }
+            let name = &param.value.name.value.0;
+            let shadowed = self.lookup(name).map(|pos| (pos.start, pos.end));
+            if let Some((start, end)) = shadowed {
+                let message = format!("parameter `{}` shadows a variable", name);
+                if let Some(lint) =
+                    self.emit(LintKind::ShadowedParameter, param.start, param.end, message)
+                {
+                    let message = format!("`{}` is defined here", name);
+                    lint.labels.push(Pos::new(start, end, message));
+                }
+            }
+            let scope = self.scopes.last_mut().unwrap();
+            scope
+                .variables
+                .insert(name.clone(), Pos::new(param.start, param.end, ()));
+        }
+
+        self.hoist(body);
+        self.visit_code(body);
+        self.scopes.pop();
+        self.loops = loops;
+    }
+
+    fn visit_object(&mut self, node: Pos<&Object>) {
+        let mut keys: HashMap<&str, &Pos<Ident>> = HashMap::new();
+        for (key, _) in &node.value.0 {
+            if let Some(first) = keys.get(key.value.0.as_str()) {
+                let (first_start, first_end) = (first.start, first.end);
+                let message = format!("duplicate key `{}`", key.value.0);
+                if let Some(lint) = self.emit(LintKind::DuplicateKey, key.start, key.end, message) {
+                    let message = "first defined here".to_owned();
+                    lint.labels.push(Pos::new(first_start, first_end, message));
+                }
+            } else {
+                keys.insert(&key.value.0, key);
+            }
+        }
+        walk_object(self, node);
+    }
+
+    fn visit_while_loop(&mut self, node: Pos<&WhileLoop>) {
+        let condition = &node.value.condition;
+        self.visit_expr(Pos::new(condition.start, condition.end, &*condition.value));
+        self.in_loop(&node.value.body);
+    }
+
+    fn visit_for_loop(&mut self, node: Pos<&ForLoop>) {
+        let ForLoop {
+            binding,
+            iterator,
+            body,
+        } = node.value;
+        self.visit_expr(Pos::new(iterator.start, iterator.end, &*iterator.value));
+        self.push_scope(true);
+        self.bind_pattern(binding, false);
+        self.in_loop(body);
+        self.scopes.pop();
+    }
+}
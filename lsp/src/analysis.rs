@@ -0,0 +1,807 @@
+//! A static approximation of the scoping rules of the interpreter, used to resolve variables and
+//! object fields and to infer the kinds of values they hold.
+
+use interpreter::Span;
+use parser::ast::{
+    Assignment, BinaryOperation, BinaryOperator, Break, Call, CodeBlock, Destructuring,
+    FieldAccess, ForLoop, Function, Ident, If, Index, List, Match, Object, Parentheses, Pattern,
+    Range, Return, Spread, Statement, UnaryOperation, UnaryOperator, VariableDeclaration,
+    WhileLoop, AST,
+};
+use parser::Pos;
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+pub type DefId = usize;
+pub type ShapeId = usize;
+pub type ScopeId = usize;
+
+/// The kind of a value, as far as it can be inferred without running the code.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    Integer,
+    Float,
+    Boolean,
+    String,
+    Array,
+    Range,
+    Object,
+    Function(Vec<String>),
+    Null,
+}
+
+impl Kind {
+    /// The type name used by the interpreter, which identifies the prototype of the kind.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Kind::Integer => "integer",
+            Kind::Float => "float",
+            Kind::Boolean => "boolean",
+            Kind::String => "string",
+            Kind::Array => "array",
+            Kind::Range => "range",
+            Kind::Object => "object",
+            Kind::Function(..) => "function",
+            Kind::Null => "null",
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Kind::Function(params) => write!(f, "function({})", params.join(", ")),
+            other => write!(f, "{}", other.type_name()),
+        }
+    }
+}
+
+/// Formats the possible kinds of a value, like `integer | string`.
+pub fn describe(kinds: &[Kind]) -> String {
+    if kinds.is_empty() {
+        return "unknown".to_owned();
+    }
+    kinds
+        .iter()
+        .map(|kind| kind.to_string())
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+#[derive(Debug)]
+pub struct Definition {
+    pub name: String,
+    /// The identifier which first defines the variable.
+    pub span: Span,
+    /// The whole statement or expression defining the variable.
+    pub range: Span,
+    pub kinds: Vec<Kind>,
+    /// The fields assigned to the variable, if it holds an object.
+    pub shape: ShapeId,
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub span: Span,
+    pub range: Span,
+    pub kinds: Vec<Kind>,
+    pub shape: ShapeId,
+}
+
+#[derive(Debug)]
+struct Scope {
+    parent: Option<ScopeId>,
+    span: Span,
+    /// Variables can't be assigned in readonly scopes, only in the enclosing ones.
+    readonly: bool,
+    /// The object `this` refers to, if it is known.
+    this: Option<ShapeId>,
+    definitions: Vec<DefId>,
+}
+
+/// What an identifier in the source code refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Variable(DefId),
+    Field(ShapeId, String),
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    scopes: Vec<Scope>,
+    definitions: Vec<Definition>,
+    shapes: Vec<Vec<Field>>,
+    references: Vec<(Span, Target)>,
+}
+
+/// The kinds and fields inferred for an expression.
+#[derive(Debug, Clone, Default)]
+struct Info {
+    kinds: Vec<Kind>,
+    shape: Option<ShapeId>,
+}
+
+impl Info {
+    fn of(kind: Kind) -> Info {
+        Info {
+            kinds: vec![kind],
+            shape: None,
+        }
+    }
+}
+
+impl Analysis {
+    pub fn new(code: &Pos<CodeBlock>, len: usize) -> Analysis {
+        let mut analysis = Analysis::default();
+        let root = analysis.push_scope(None, Span::new(0, len), false, None);
+        analysis.hoist(root, &code.value);
+        analysis.code(root, &code.value);
+        analysis
+    }
+
+    pub fn definition(&self, id: DefId) -> &Definition {
+        &self.definitions[id]
+    }
+
+    pub fn fields(&self, shape: ShapeId) -> &[Field] {
+        &self.shapes[shape]
+    }
+
+    pub fn field(&self, shape: ShapeId, name: &str) -> Option<&Field> {
+        self.shapes[shape].iter().find(|field| field.name == name)
+    }
+
+    /// Returns the definitions of the global scope, in order of appearance.
+    pub fn globals(&self) -> impl Iterator<Item = &Definition> {
+        self.scopes[0]
+            .definitions
+            .iter()
+            .map(move |&id| &self.definitions[id])
+    }
+
+    /// Returns the innermost reference containing `offset`.
+    pub fn target_at(&self, offset: usize) -> Option<&(Span, Target)> {
+        self.references
+            .iter()
+            .filter(|(span, _)| span.start <= offset && offset <= span.end)
+            .min_by_key(|(span, _)| span.end - span.start)
+    }
+
+    /// Returns the variables visible at `offset`, with inner variables before outer ones.
+    pub fn visible(&self, offset: usize) -> Vec<&Definition> {
+        self.scope_chain(self.scope_at(offset))
+            .flat_map(|scope| &self.scopes[scope].definitions)
+            .map(|&id| &self.definitions[id])
+            .collect()
+    }
+
+    /// Resolves a variable, or `this`, visible at `offset` to its kinds and fields.
+    pub fn resolve_at(&self, offset: usize, name: &str) -> Option<(Vec<Kind>, ShapeId)> {
+        let scope = self.scope_at(offset);
+        if name == "this" {
+            return self.this(scope).map(|shape| (vec![Kind::Object], shape));
+        }
+        let id = self.lookup(scope, name)?;
+        let definition = &self.definitions[id];
+        Some((definition.kinds.clone(), definition.shape))
+    }
+
+    fn scope_at(&self, offset: usize) -> ScopeId {
+        (0..self.scopes.len())
+            .filter(|&id| {
+                let span = self.scopes[id].span;
+                span.start <= offset && offset <= span.end
+            })
+            .min_by_key(|&id| self.scopes[id].span.end - self.scopes[id].span.start)
+            .unwrap_or(0)
+    }
+
+    fn scope_chain(&self, scope: ScopeId) -> impl Iterator<Item = ScopeId> + '_ {
+        std::iter::successors(Some(scope), move |&scope| self.scopes[scope].parent)
+    }
+
+    fn push_scope(
+        &mut self,
+        parent: Option<ScopeId>,
+        span: Span,
+        readonly: bool,
+        this: Option<ShapeId>,
+    ) -> ScopeId {
+        self.scopes.push(Scope {
+            parent,
+            span,
+            readonly,
+            this,
+            definitions: vec![],
+        });
+        self.scopes.len() - 1
+    }
+
+    fn this(&self, scope: ScopeId) -> Option<ShapeId> {
+        self.scope_chain(scope)
+            .find(|&scope| !self.scopes[scope].readonly)
+            .and_then(|scope| self.scopes[scope].this)
+    }
+
+    fn lookup(&self, scope: ScopeId, name: &str) -> Option<DefId> {
+        self.scope_chain(scope).find_map(|scope| {
+            self.scopes[scope]
+                .definitions
+                .iter()
+                .copied()
+                .find(|&id| self.definitions[id].name == name)
+        })
+    }
+
+    fn new_shape(&mut self) -> ShapeId {
+        self.shapes.push(vec![]);
+        self.shapes.len() - 1
+    }
+
+    fn define(&mut self, scope: ScopeId, name: &str, span: Span, range: Span) -> DefId {
+        let shape = self.new_shape();
+        self.definitions.push(Definition {
+            name: name.to_owned(),
+            span,
+            range,
+            kinds: vec![],
+            shape,
+        });
+        let id = self.definitions.len() - 1;
+        self.scopes[scope].definitions.push(id);
+        id
+    }
+
+    /// The variable `name` defined directly in `scope`.
+    fn local(&self, scope: ScopeId, name: &str) -> Option<DefId> {
+        self.scopes[scope]
+            .definitions
+            .iter()
+            .copied()
+            .find(|&id| self.definitions[id].name == name)
+    }
+
+    /// Returns the variable which an assignment in `scope` writes to, defining it if necessary.
+    /// Like the interpreter, this is a variable of the enclosing blocks up to the innermost
+    /// function or a global, or a new variable in the innermost scope which is not readonly.
+    fn writable(&mut self, scope: ScopeId, name: &str, span: Span, range: Span) -> DefId {
+        let mut blocks = vec![];
+        for scope in self.scope_chain(scope) {
+            blocks.push(scope);
+            if !self.scopes[scope].readonly {
+                break;
+            }
+        }
+        let function = *blocks.last().unwrap();
+        let existing = blocks
+            .into_iter()
+            .chain(Some(0))
+            .find_map(|scope| self.local(scope, name));
+        existing.unwrap_or_else(|| self.define(function, name, span, range))
+    }
+
+    fn field_or_insert(&mut self, shape: ShapeId, name: &str, span: Span, range: Span) -> usize {
+        match self.shapes[shape]
+            .iter()
+            .position(|field| field.name == name)
+        {
+            Some(idx) => idx,
+            None => {
+                let field_shape = self.new_shape();
+                self.shapes[shape].push(Field {
+                    name: name.to_owned(),
+                    span,
+                    range,
+                    kinds: vec![],
+                    shape: field_shape,
+                });
+                self.shapes[shape].len() - 1
+            }
+        }
+    }
+
+    /// Defines all variables assigned directly within a function body or the global scope, so
+    /// that they can be referenced before their assignment, e.g. by recursive functions.
+    fn hoist(&mut self, scope: ScopeId, code: &[Pos<Statement>]) {
+        for statement in code {
+            if let Some(Function {
+                name: Some(name), ..
+            }) = statement.value.declaration()
+            {
+                let span = Span::new(name.start, name.end);
+                let range = Span::new(statement.start, statement.end);
+                self.writable(scope, &name.value.0, span, range);
+                continue;
+            }
+            self.hoist_ast(
+                scope,
+                statement.value.inner(),
+                statement.start,
+                statement.end,
+            );
+        }
+    }
+
+    fn hoist_ast(&mut self, scope: ScopeId, ast: &AST, start: usize, end: usize) {
+        match ast {
+            AST::Assignment(Assignment { receiver, value }) => {
+                if let AST::Ident(Ident(name)) = &*receiver.value {
+                    let span = Span::new(receiver.start, receiver.end);
+                    self.writable(scope, name, span, Span::new(start, end));
+                }
+                self.hoist_ast(scope, &value.value, value.start, value.end);
+            }
+            AST::Destructuring(Destructuring { pattern, value }) => {
+                for name in Pattern::bindings(pattern) {
+                    let span = Span::new(name.start, name.end);
+                    self.writable(scope, &name.value.0, span, Span::new(start, end));
+                }
+                self.hoist_ast(scope, &value.value, value.start, value.end);
+            }
+            AST::If(If {
+                if_branch,
+                else_if_branches,
+                else_branch,
+            }) => {
+                for branch in std::iter::once(if_branch).chain(else_if_branches) {
+                    self.hoist(scope, &branch.value.body);
+                }
+                if let Some(else_branch) = else_branch {
+                    self.hoist(scope, else_branch);
+                }
+            }
+            AST::Match(Match { arms, .. }) => {
+                for arm in arms {
+                    self.hoist(scope, &arm.value.body);
+                }
+            }
+            AST::WhileLoop(WhileLoop { body, .. }) | AST::ForLoop(ForLoop { body, .. }) => {
+                self.hoist(scope, body)
+            }
+            AST::Parentheses(Parentheses(inner)) => {
+                self.hoist_ast(scope, &inner.value, inner.start, inner.end)
+            }
+            _ => {}
+        }
+    }
+
+    fn code(&mut self, scope: ScopeId, code: &[Pos<Statement>]) -> Info {
+        let mut info = Info::default();
+        for statement in code {
+            if let Some(function) = statement.value.declaration() {
+                self.declaration(scope, function, statement.start, statement.end);
+                info = Info::of(Kind::Null);
+                continue;
+            }
+            let ast = statement.value.inner();
+            info = self.ast(scope, ast, statement.start, statement.end, None);
+            if let Statement::Terminated(..) = statement.value {
+                info = Info::of(Kind::Null);
+            }
+        }
+        info
+    }
+
+    fn boxed(&mut self, scope: ScopeId, ast: &Pos<Box<AST>>, target: Option<ShapeId>) -> Info {
+        self.ast(scope, &ast.value, ast.start, ast.end, target)
+    }
+
+    /// Analyzes an expression. If `target` is given, fields of an object literal are added to it.
+    fn ast(
+        &mut self,
+        scope: ScopeId,
+        ast: &AST,
+        start: usize,
+        end: usize,
+        target: Option<ShapeId>,
+    ) -> Info {
+        match ast {
+            AST::Int(..) => Info::of(Kind::Integer),
+            AST::Float(..) => Info::of(Kind::Float),
+            AST::Bool(..) => Info::of(Kind::Boolean),
+            AST::String(..) => Info::of(Kind::String),
+            AST::Ident(Ident(name)) if name == "this" => Info {
+                kinds: vec![Kind::Object],
+                shape: self.this(scope),
+            },
+            AST::Ident(Ident(name)) => match self.lookup(scope, name) {
+                Some(id) => {
+                    self.references
+                        .push((Span::new(start, end), Target::Variable(id)));
+                    let definition = &self.definitions[id];
+                    Info {
+                        kinds: definition.kinds.clone(),
+                        shape: Some(definition.shape),
+                    }
+                }
+                None if name == "null" => Info::of(Kind::Null),
+                None => Info::default(),
+            },
+            AST::Assignment(Assignment { receiver, value }) => {
+                self.assignment(scope, receiver, value, Span::new(start, end));
+                Info::of(Kind::Null)
+            }
+            AST::Destructuring(Destructuring { pattern, value }) => {
+                let info = self.boxed(scope, value, None);
+                self.pattern(scope, pattern, info, true, Span::new(start, end));
+                Info::of(Kind::Null)
+            }
+            AST::VariableDeclaration(VariableDeclaration { name, value, .. }) => {
+                let span = Span::new(name.start, name.end);
+                let id = self.define(scope, &name.value.0, span, Span::new(start, end));
+                let shape = self.definitions[id].shape;
+                let info = match value {
+                    Some(value) => self.boxed(scope, value, Some(shape)),
+                    None => Info::of(Kind::Null),
+                };
+                self.references.push((span, Target::Variable(id)));
+                self.alias(shape, info.shape);
+                add_kinds(&mut self.definitions[id].kinds, info.kinds);
+                Info::of(Kind::Null)
+            }
+            AST::FieldAccess(FieldAccess { value, field }) => {
+                let receiver = self.boxed(scope, value, None);
+                let shape = match receiver.shape {
+                    Some(shape) => shape,
+                    None => return Info::default(),
+                };
+                let name = &field.value.0;
+                let span = Span::new(field.start, field.end);
+                self.references
+                    .push((span, Target::Field(shape, name.clone())));
+                match self.field(shape, name) {
+                    Some(field) => Info {
+                        kinds: field.kinds.clone(),
+                        shape: Some(field.shape),
+                    },
+                    None => Info::default(),
+                }
+            }
+            AST::Index(Index { value, index }) => {
+                self.boxed(scope, value, None);
+                self.boxed(scope, index, None);
+                Info::default()
+            }
+            AST::Function(function) => {
+                let kind = self.function(scope, function, Span::new(start, end), None);
+                Info::of(kind)
+            }
+            AST::If(If {
+                if_branch,
+                else_if_branches,
+                else_branch,
+            }) => {
+                let mut info = Info::default();
+                let mut else_start = start;
+                for branch in std::iter::once(if_branch).chain(else_if_branches) {
+                    self.boxed(scope, &branch.value.condition, None);
+                    let span = Span::new(branch.value.condition.end, branch.end);
+                    let block = self.push_scope(Some(scope), span, true, None);
+                    let branch_info = self.code(block, &branch.value.body);
+                    add_kinds(&mut info.kinds, branch_info.kinds);
+                    else_start = branch.end;
+                }
+                let else_branch = match else_branch {
+                    Some(else_branch) => {
+                        let span = Span::new(else_start, end);
+                        let block = self.push_scope(Some(scope), span, true, None);
+                        self.code(block, else_branch)
+                    }
+                    None => Info::of(Kind::Null),
+                };
+                add_kinds(&mut info.kinds, else_branch.kinds);
+                info
+            }
+            AST::Match(Match { value, arms }) => {
+                let value = self.boxed(scope, value, None);
+                let mut info = Info::default();
+                for arm in arms {
+                    let span = Span::new(arm.start, arm.end);
+                    let arm_scope = self.push_scope(Some(scope), span, true, None);
+                    self.pattern(arm_scope, &arm.value.pattern, value.clone(), false, span);
+                    if let Some(guard) = &arm.value.guard {
+                        self.boxed(arm_scope, guard, None);
+                    }
+                    let arm_info = self.code(arm_scope, &arm.value.body);
+                    add_kinds(&mut info.kinds, arm_info.kinds);
+                }
+                info
+            }
+            AST::BinaryOperation(BinaryOperation { lhs, op, rhs }) => {
+                let lhs = self.boxed(scope, lhs, None);
+                let rhs = self.boxed(scope, rhs, None);
+                Info {
+                    kinds: binary_kinds(op.value, &lhs.kinds, &rhs.kinds),
+                    shape: None,
+                }
+            }
+            AST::UnaryOperation(UnaryOperation { op, value }) => {
+                let value = self.boxed(scope, value, None);
+                match op.value {
+                    UnaryOperator::Negate => Info::of(Kind::Boolean),
+                    UnaryOperator::Minus => Info {
+                        kinds: value.kinds,
+                        shape: None,
+                    },
+                }
+            }
+            AST::Parentheses(Parentheses(inner)) => self.boxed(scope, inner, target),
+            AST::Call(Call { value, args }) => {
+                self.boxed(scope, value, None);
+                for arg in args {
+                    self.ast(scope, &arg.value, arg.start, arg.end, None);
+                }
+                Info::default()
+            }
+            AST::Spread(Spread(value)) => {
+                self.boxed(scope, value, None);
+                Info::default()
+            }
+            AST::List(List(elements)) => {
+                for element in elements {
+                    self.ast(scope, &element.value, element.start, element.end, None);
+                }
+                Info::of(Kind::Array)
+            }
+            AST::Object(Object(pairs)) => {
+                let shape = target.unwrap_or_else(|| self.new_shape());
+                for (key, value) in pairs {
+                    let span = Span::new(key.start, key.end);
+                    let range = Span::new(key.start, value.end);
+                    let idx = self.field_or_insert(shape, &key.value.0, span, range);
+                    self.references
+                        .push((span, Target::Field(shape, key.value.0.clone())));
+
+                    let kinds = match &value.value {
+                        // Methods of object literals can refer to the object using `this`.
+                        AST::Function(function) => {
+                            let span = Span::new(value.start, value.end);
+                            vec![self.function(scope, function, span, Some(shape))]
+                        }
+                        other => {
+                            let field_shape = self.shapes[shape][idx].shape;
+                            let info =
+                                self.ast(scope, other, value.start, value.end, Some(field_shape));
+                            self.alias(field_shape, info.shape);
+                            info.kinds
+                        }
+                    };
+                    add_kinds(&mut self.shapes[shape][idx].kinds, kinds);
+                }
+                Info {
+                    kinds: vec![Kind::Object],
+                    shape: Some(shape),
+                }
+            }
+            AST::Range(Range { from, to, .. }) => {
+                self.boxed(scope, from, None);
+                self.boxed(scope, to, None);
+                Info::of(Kind::Range)
+            }
+            AST::WhileLoop(WhileLoop { condition, body }) => {
+                self.boxed(scope, condition, None);
+                let span = Span::new(condition.end, end);
+                let block = self.push_scope(Some(scope), span, true, None);
+                self.code(block, body);
+                Info::default()
+            }
+            AST::ForLoop(ForLoop {
+                binding,
+                iterator,
+                body,
+            }) => {
+                let is_range = matches!(&*iterator.value, AST::Range(..));
+                self.boxed(scope, iterator, None);
+                let loop_scope = self.push_scope(Some(scope), Span::new(start, end), true, None);
+                let element = match is_range {
+                    true => Info::of(Kind::Integer),
+                    false => Info::default(),
+                };
+                self.pattern(loop_scope, binding, element, false, Span::new(start, end));
+                self.code(loop_scope, body);
+                Info::default()
+            }
+            AST::Return(Return(value)) | AST::Break(Break(value)) => {
+                if let Some(value) = value {
+                    self.boxed(scope, value, None);
+                }
+                Info::default()
+            }
+            AST::Continue(..) | AST::Invalid(..) => Info::default(),
+        }
+    }
+
+    fn assignment(
+        &mut self,
+        scope: ScopeId,
+        receiver: &Pos<Box<AST>>,
+        value: &Pos<Box<AST>>,
+        range: Span,
+    ) {
+        let span = Span::new(receiver.start, receiver.end);
+        match &*receiver.value {
+            AST::Ident(Ident(name)) => {
+                let id = self.writable(scope, name, span, range);
+                self.references.push((span, Target::Variable(id)));
+                let shape = self.definitions[id].shape;
+                let info = self.boxed(scope, value, Some(shape));
+                self.alias(shape, info.shape);
+                add_kinds(&mut self.definitions[id].kinds, info.kinds);
+            }
+            AST::FieldAccess(FieldAccess {
+                value: object,
+                field,
+            }) => {
+                let receiver = self.boxed(scope, object, None);
+                let span = Span::new(field.start, field.end);
+                match receiver.shape {
+                    Some(shape) => {
+                        let name = &field.value.0;
+                        let idx = self.field_or_insert(shape, name, span, range);
+                        self.references
+                            .push((span, Target::Field(shape, name.clone())));
+                        let field_shape = self.shapes[shape][idx].shape;
+                        let info = self.boxed(scope, value, Some(field_shape));
+                        self.alias(field_shape, info.shape);
+                        add_kinds(&mut self.shapes[shape][idx].kinds, info.kinds);
+                    }
+                    None => {
+                        self.boxed(scope, value, None);
+                    }
+                }
+            }
+            other => {
+                self.ast(scope, other, receiver.start, receiver.end, None);
+                self.boxed(scope, value, None);
+            }
+        }
+    }
+
+    /// Defines the names bound by a pattern, either by assigning them or as new variables of
+    /// `scope`. `info` describes the destructured value, which tells the fields read by object
+    /// patterns if its shape is known.
+    fn pattern(
+        &mut self,
+        scope: ScopeId,
+        pattern: &Pos<Pattern>,
+        info: Info,
+        assign: bool,
+        range: Span,
+    ) {
+        let elements = match &pattern.value {
+            Pattern::Ident(Ident(name)) => {
+                let span = Span::new(pattern.start, pattern.end);
+                let id = match assign {
+                    true => self.writable(scope, name, span, range),
+                    false => self.define(scope, name, span, range),
+                };
+                self.references.push((span, Target::Variable(id)));
+                let shape = self.definitions[id].shape;
+                self.alias(shape, info.shape);
+                add_kinds(&mut self.definitions[id].kinds, info.kinds);
+                return;
+            }
+            Pattern::Wildcard | Pattern::Literal(..) | Pattern::Type(..) => return,
+            Pattern::Or(alternatives) => {
+                for alternative in alternatives {
+                    self.pattern(scope, alternative, info.clone(), assign, range);
+                }
+                return;
+            }
+            Pattern::Array(elements) | Pattern::Object(elements) => elements,
+        };
+        for element in elements {
+            let mut element_info = match (&element.value.key, info.shape) {
+                _ if element.value.rest => match &pattern.value {
+                    Pattern::Array(..) => Info::of(Kind::Array),
+                    _ => Info::of(Kind::Object),
+                },
+                (Some(key), Some(shape)) => {
+                    let span = Span::new(key.start, key.end);
+                    self.references
+                        .push((span, Target::Field(shape, key.value.0.clone())));
+                    match self.field(shape, &key.value.0) {
+                        Some(field) => Info {
+                            kinds: field.kinds.clone(),
+                            shape: Some(field.shape),
+                        },
+                        None => Info::default(),
+                    }
+                }
+                _ => Info::default(),
+            };
+            if let Some(default) = &element.value.default {
+                let default = self.boxed(scope, default, None);
+                add_kinds(&mut element_info.kinds, default.kinds);
+            }
+            self.pattern(scope, &element.value.pattern, element_info, assign, range);
+        }
+    }
+
+    /// Analyzes a function declaration, which defines a variable like an assignment.
+    fn declaration(&mut self, scope: ScopeId, function: &Function, start: usize, end: usize) {
+        let name = match &function.name {
+            Some(name) => name,
+            None => return,
+        };
+        let span = Span::new(name.start, name.end);
+        let range = Span::new(start, end);
+        let id = self.writable(scope, &name.value.0, span, range);
+        self.references.push((span, Target::Variable(id)));
+        let kind = self.function(scope, function, range, None);
+        add_kinds(&mut self.definitions[id].kinds, vec![kind]);
+    }
+
+    /// Analyzes a function in a new scope. `this` is the object the function is a method of.
+    fn function(
+        &mut self,
+        scope: ScopeId,
+        function: &Function,
+        span: Span,
+        this: Option<ShapeId>,
+    ) -> Kind {
+        let Function { params, body, .. } = function;
+        let scope = self.push_scope(Some(scope), span, false, this);
+        for param in params {
+            if let Some(default) = &param.value.default {
+                self.boxed(scope, default, None);
+            }
+            let name = &param.value.name;
+            let name_span = Span::new(name.start, name.end);
+            let id = self.define(scope, &name.value.0, name_span, name_span);
+            self.references.push((name_span, Target::Variable(id)));
+        }
+        self.hoist(scope, body);
+        self.code(scope, body);
+        let params = params.iter().map(|param| match param.value.rest {
+            true => format!("...{}", param.value.name.value.0),
+            false => param.value.name.value.0.clone(),
+        });
+        Kind::Function(params.collect())
+    }
+
+    /// Copies the fields of `source` into `shape`, after a value has been assigned to it.
+    fn alias(&mut self, shape: ShapeId, source: Option<ShapeId>) {
+        let source = match source {
+            Some(source) if source != shape => source,
+            _ => return,
+        };
+        for field in self.shapes[source].clone() {
+            if self.field(shape, &field.name).is_none() {
+                self.shapes[shape].push(field);
+            }
+        }
+    }
+}
+
+fn add_kinds(kinds: &mut Vec<Kind>, new: Vec<Kind>) {
+    for kind in new {
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+}
+
+fn binary_kinds(op: BinaryOperator, lhs: &[Kind], rhs: &[Kind]) -> Vec<Kind> {
+    use BinaryOperator::*;
+
+    let numeric = |kinds: &[Kind]| {
+        !kinds.is_empty()
+            && kinds
+                .iter()
+                .all(|kind| *kind == Kind::Integer || *kind == Kind::Float)
+    };
+    match op {
+        Eq | NotEq | GreaterThan | LessThan | GreaterEqThan | LessEqThan => vec![Kind::Boolean],
+        Add if lhs.contains(&Kind::String) || rhs.contains(&Kind::String) => vec![Kind::String],
+        Add | Sub | Mul | Div if numeric(lhs) && numeric(rhs) => {
+            if lhs.contains(&Kind::Float) || rhs.contains(&Kind::Float) {
+                vec![Kind::Float]
+            } else {
+                vec![Kind::Integer]
+            }
+        }
+        _ => vec![],
+    }
+}
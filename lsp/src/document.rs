@@ -0,0 +1,84 @@
+use crate::analysis::Analysis;
+use interpreter::{KetamineError, Span};
+use parser::Pos;
+use serde_json::{json, Value};
+
+/// An open text document, analyzed whenever its text changes.
+pub struct Document {
+    pub text: String,
+    pub analysis: Analysis,
+    pub errors: Vec<KetamineError>,
+    line_starts: Vec<usize>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Document {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        let (analysis, errors) = match lexer::tokenize(&text) {
+            Ok(tokens) => {
+                let (code, errors) = parser::parse_recovering(&tokens);
+                let errors = errors.into_iter().map(KetamineError::from).collect();
+                (Analysis::new(&code, text.len()), errors)
+            }
+            Err(err) => {
+                let code = Pos::new(0, 0, vec![]);
+                (Analysis::new(&code, text.len()), vec![err.into()])
+            }
+        };
+        Document {
+            text,
+            analysis,
+            errors,
+            line_starts,
+        }
+    }
+
+    /// Converts a byte offset into an LSP position, whose character is counted in UTF-16 units.
+    pub fn position(&self, offset: usize) -> Value {
+        let offset = offset.min(self.text.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let start = self.line_starts[line];
+        let character = self.text[start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        json!({ "line": line, "character": character })
+    }
+
+    /// Converts an LSP position into a byte offset, clamping it to the document.
+    pub fn offset(&self, position: &Value) -> usize {
+        let line = position["line"].as_u64().unwrap_or(0) as usize;
+        let character = position["character"].as_u64().unwrap_or(0) as usize;
+        let start = match self.line_starts.get(line) {
+            Some(&start) => start,
+            None => return self.text.len(),
+        };
+        let line_text = self.text[start..].split('\n').next().unwrap_or_default();
+        let mut units = 0;
+        for (idx, c) in line_text.char_indices() {
+            if units >= character {
+                return start + idx;
+            }
+            units += c.len_utf16();
+        }
+        start + line_text.len()
+    }
+
+    pub fn range(&self, span: Span) -> Value {
+        json!({ "start": self.position(span.start), "end": self.position(span.end) })
+    }
+
+    /// Returns the identifier which ends at `offset`, together with its start.
+    pub fn word_before(&self, offset: usize) -> (usize, &str) {
+        let before = &self.text[..offset];
+        let start = before
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+            .map_or(0, |idx| idx + 1);
+        (start, &before[start..])
+    }
+}
@@ -0,0 +1,20 @@
+//! A language server for Ketamine, which communicates with the editor over stdin and stdout.
+
+use std::io::{stdin, stdout};
+use std::process::exit;
+
+mod analysis;
+mod document;
+mod rpc;
+mod server;
+
+fn main() {
+    let stdin = stdin();
+    match server::serve(stdin.lock(), stdout()) {
+        Ok(code) => exit(code),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            exit(1);
+        }
+    }
+}
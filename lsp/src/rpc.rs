@@ -0,0 +1,47 @@
+use serde_json::Value;
+
+use std::io::{BufRead, Error, ErrorKind, Result as IoResult, Write};
+
+/// Reads the content of a single JSON-RPC message, which is preceded by a `Content-Length`
+/// header. Returns `None` once the input is exhausted.
+pub fn read_message(input: &mut impl BufRead) -> IoResult<Option<Vec<u8>>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                let length = value.trim().parse::<usize>().map_err(invalid_data)?;
+                content_length = Some(length);
+            }
+        }
+    }
+
+    let length =
+        content_length.ok_or_else(|| invalid_data("message without a Content-Length header"))?;
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    Ok(Some(content))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> IoResult<()> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Error {
+    Error::new(ErrorKind::InvalidData, err)
+}
@@ -0,0 +1,669 @@
+use crate::analysis::{describe, Kind, ShapeId, Target};
+use crate::document::Document;
+use crate::rpc::{read_message, write_message};
+use interpreter::library::{Console, StandardLibrary};
+use interpreter::{Global, Interpreter, KetamineError, Object, Value as KetamineValue};
+use parser::fmt::FormatOptions;
+use serde_json::{json, Value};
+
+use std::collections::HashMap;
+use std::io::{BufRead, Result as IoResult, Write};
+use std::ops::Deref;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+const KEYWORDS: &[&str] = &[
+    "function", "return", "break", "continue", "if", "else", "for", "in", "while", "match", "let",
+    "const", "true", "false",
+];
+
+mod completion_kind {
+    pub const METHOD: u8 = 2;
+    pub const FUNCTION: u8 = 3;
+    pub const FIELD: u8 = 5;
+    pub const VARIABLE: u8 = 6;
+    pub const KEYWORD: u8 = 14;
+}
+
+mod symbol_kind {
+    pub const METHOD: u8 = 6;
+    pub const FIELD: u8 = 8;
+    pub const FUNCTION: u8 = 12;
+    pub const VARIABLE: u8 = 13;
+    pub const OBJECT: u8 = 19;
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+type RpcResult = Result<Value, RpcError>;
+
+/// Serves LSP requests read from `input` until the client sends `exit`, returning the exit code.
+pub fn serve(mut input: impl BufRead, output: impl Write) -> IoResult<i32> {
+    let mut server = Server::new(output);
+    while let Some(content) = read_message(&mut input)? {
+        match serde_json::from_slice::<Value>(&content) {
+            Ok(message) => {
+                if let Some(code) = server.handle(message)? {
+                    return Ok(code);
+                }
+            }
+            Err(err) => server.respond(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, err.to_string())),
+            )?,
+        }
+    }
+    // The client went away without asking the server to exit.
+    Ok(1)
+}
+
+struct Server<W> {
+    output: W,
+    documents: HashMap<String, Document>,
+    /// The names and type names of the variables defined by the libraries.
+    globals: Vec<(String, &'static str)>,
+    /// The names of the prototype functions, by the type name of their prototype.
+    prototypes: HashMap<String, Vec<String>>,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn new(output: W) -> Self {
+        let mut interpreter = Interpreter::new();
+        interpreter.register(&StandardLibrary);
+        interpreter.register(&Console);
+
+        let mut globals = vec![];
+        let mut prototypes = HashMap::new();
+        for Global { name, value, .. } in interpreter.globals() {
+            match (name.strip_prefix('$'), value) {
+                (Some(type_name), KetamineValue::Dictionary(proto)) => {
+                    let mut names = proto.0.deref().borrow().keys().cloned().collect::<Vec<_>>();
+                    names.sort();
+                    prototypes.insert(type_name.to_owned(), names);
+                }
+                _ => globals.push((name.to_owned(), value.type_name())),
+            }
+        }
+        globals.sort();
+
+        Server {
+            output,
+            documents: HashMap::new(),
+            globals,
+            prototypes,
+            shutdown: false,
+        }
+    }
+
+    /// Handles a single message, returning the exit code once the client requests to exit.
+    fn handle(&mut self, message: Value) -> IoResult<Option<i32>> {
+        let params = &message["params"];
+        match (message.get("id"), message["method"].as_str()) {
+            (_, Some("exit")) => return Ok(Some(if self.shutdown { 0 } else { 1 })),
+            (Some(id), Some(method)) => {
+                let result = self.request(method, params);
+                self.respond(id.clone(), result)?;
+            }
+            (None, Some(method)) => self.notification(method, params)?,
+            // Responses to requests of the server, which never sends any.
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn respond(&mut self, id: Value, result: RpcResult) -> IoResult<()> {
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(RpcError { code, message }) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        write_message(&mut self.output, &response)
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> RpcResult {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                    "documentSymbolProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": { "name": "ketamine-lsp", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/formatting" => self.formatting(params),
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unsupported method `{}`", other),
+            )),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> IoResult<()> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.update(uri, text.to_owned())
+            }
+            // The full text is synchronized, so the last change contains the whole document.
+            "textDocument/didChange" => match params["contentChanges"].as_array() {
+                Some(changes) => match changes.last().and_then(|change| change["text"].as_str()) {
+                    Some(text) => self.update(uri, text.to_owned()),
+                    None => Ok(()),
+                },
+                None => Ok(()),
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                self.publish_diagnostics(uri, vec![])
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: String) -> IoResult<()> {
+        let document = Document::new(text);
+        let diagnostics = document
+            .errors
+            .iter()
+            .map(|err| diagnostic(uri, &document, err))
+            .collect();
+        self.documents.insert(uri.to_owned(), document);
+        self.publish_diagnostics(uri, diagnostics)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Vec<Value>) -> IoResult<()> {
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        });
+        write_message(&mut self.output, &notification)
+    }
+
+    /// Returns the URI and the document a request refers to, and the offset of its position.
+    fn document<'a>(
+        &'a self,
+        params: &'a Value,
+    ) -> Result<(&'a str, &'a Document, usize), RpcError> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("unknown document `{}`", uri)))?;
+        let offset = document.offset(&params["position"]);
+        Ok((uri, document, offset))
+    }
+
+    fn definition(&self, params: &Value) -> RpcResult {
+        let (uri, document, offset) = self.document(params)?;
+        let analysis = &document.analysis;
+        let span = match analysis.target_at(offset) {
+            Some((_, Target::Variable(id))) => analysis.definition(*id).span,
+            Some((_, Target::Field(shape, name))) => match analysis.field(*shape, name) {
+                Some(field) => field.span,
+                None => return Ok(Value::Null),
+            },
+            None => return Ok(Value::Null),
+        };
+        Ok(json!({ "uri": uri, "range": document.range(span) }))
+    }
+
+    fn hover(&self, params: &Value) -> RpcResult {
+        let (_, document, offset) = self.document(params)?;
+        let analysis = &document.analysis;
+        let (span, text) = match analysis.target_at(offset) {
+            Some((span, Target::Variable(id))) => {
+                let definition = analysis.definition(*id);
+                let text = format!("{}: {}", definition.name, describe(&definition.kinds));
+                (*span, text)
+            }
+            Some((span, Target::Field(shape, name))) => {
+                let kinds = analysis
+                    .field(*shape, name)
+                    .map(|field| field.kinds.clone())
+                    .unwrap_or_default();
+                (*span, format!("{}: {}", name, describe(&kinds)))
+            }
+            None => {
+                // Variables defined by the libraries aren't part of the analysis.
+                let end = offset
+                    + document.text[offset..]
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+                        .unwrap_or(document.text.len() - offset);
+                let (start, word) = document.word_before(end);
+                match self.globals.iter().find(|(name, _)| name == word) {
+                    Some((name, type_name)) => (
+                        interpreter::Span::new(start, end),
+                        format!("{}: {}", name, type_name),
+                    ),
+                    None => return Ok(Value::Null),
+                }
+            }
+        };
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": format!("```ketamine\n{}\n```", text) },
+            "range": document.range(span),
+        }))
+    }
+
+    fn completion(&self, params: &Value) -> RpcResult {
+        let (_, document, offset) = self.document(params)?;
+        let analysis = &document.analysis;
+        let (word_start, _) = document.word_before(offset);
+        let before = &document.text[..word_start];
+
+        let mut items = vec![];
+        if before.ends_with('.') && !before.ends_with("..") {
+            let (_, receiver) = document.word_before(word_start - 1);
+            let (kinds, shape) = match analysis.resolve_at(offset, receiver) {
+                Some((kinds, shape)) => (kinds, Some(shape)),
+                None => (vec![], None),
+            };
+            if let Some(shape) = shape {
+                for field in analysis.fields(shape) {
+                    let kind = field_kind(
+                        &field.kinds,
+                        completion_kind::METHOD,
+                        completion_kind::FIELD,
+                    );
+                    items.push(item(&field.name, kind, describe(&field.kinds)));
+                }
+            }
+            // Without any known kinds, the methods of all prototypes are possible.
+            let mut type_names = kinds.iter().map(Kind::type_name).collect::<Vec<_>>();
+            if type_names.is_empty() {
+                type_names = self.prototypes.keys().map(String::as_str).collect();
+                type_names.sort_unstable();
+            }
+            for type_name in type_names {
+                for method in self.prototypes.get(type_name).into_iter().flatten() {
+                    let detail = format!("{} method", type_name);
+                    items.push(item(method, completion_kind::METHOD, detail));
+                }
+            }
+        } else {
+            for definition in analysis.visible(offset) {
+                let kind = field_kind(
+                    &definition.kinds,
+                    completion_kind::FUNCTION,
+                    completion_kind::VARIABLE,
+                );
+                items.push(item(&definition.name, kind, describe(&definition.kinds)));
+            }
+            for (name, type_name) in &self.globals {
+                let kind = match *type_name {
+                    "function" => completion_kind::FUNCTION,
+                    _ => completion_kind::VARIABLE,
+                };
+                items.push(item(name, kind, type_name.to_string()));
+            }
+            for keyword in KEYWORDS {
+                items.push(item(
+                    keyword,
+                    completion_kind::KEYWORD,
+                    "keyword".to_owned(),
+                ));
+            }
+        }
+
+        let mut seen = vec![];
+        items.retain(|item| {
+            let label = item["label"].clone();
+            let unseen = !seen.contains(&label);
+            seen.push(label);
+            unseen
+        });
+        Ok(Value::Array(items))
+    }
+
+    fn document_symbols(&self, params: &Value) -> RpcResult {
+        let (_, document, _) = self.document(params)?;
+        let analysis = &document.analysis;
+        let symbols = analysis
+            .globals()
+            .map(|definition| {
+                let kind = if definition
+                    .kinds
+                    .iter()
+                    .any(|kind| matches!(kind, Kind::Function(..)))
+                {
+                    symbol_kind::FUNCTION
+                } else if definition.kinds.contains(&Kind::Object) {
+                    symbol_kind::OBJECT
+                } else {
+                    symbol_kind::VARIABLE
+                };
+                json!({
+                    "name": definition.name,
+                    "detail": describe(&definition.kinds),
+                    "kind": kind,
+                    "range": document.range(definition.range),
+                    "selectionRange": document.range(definition.span),
+                    "children": field_symbols(document, definition.shape, &mut vec![]),
+                })
+            })
+            .collect();
+        Ok(Value::Array(symbols))
+    }
+
+    /// Formats the whole document. Documents which can't be parsed are left as they are.
+    fn formatting(&self, params: &Value) -> RpcResult {
+        let (_, document, _) = self.document(params)?;
+        let defaults = FormatOptions::default();
+        let options = FormatOptions {
+            indent_width: params["options"]["tabSize"]
+                .as_u64()
+                .map_or(defaults.indent_width, |size| size as usize),
+            use_tabs: !params["options"]["insertSpaces"].as_bool().unwrap_or(true),
+            ..defaults
+        };
+        match parser::fmt::format(&document.text, &options) {
+            Ok(formatted) => {
+                let range = document.range(interpreter::Span::new(0, document.text.len()));
+                Ok(json!([{ "range": range, "newText": formatted }]))
+            }
+            Err(..) => Ok(Value::Null),
+        }
+    }
+}
+
+fn diagnostic(uri: &str, document: &Document, err: &KetamineError) -> Value {
+    let span = err.span().unwrap_or_default();
+    let mut message = err.message();
+    for note in err.notes() {
+        message.push_str("\nnote: ");
+        message.push_str(note);
+    }
+    let related = err
+        .labels()
+        .into_iter()
+        .map(|label| {
+            json!({
+                "location": { "uri": uri, "range": document.range(label.span) },
+                "message": label.message,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "range": document.range(span),
+        "severity": 1,
+        "code": err.code().as_str(),
+        "source": "ketamine",
+        "message": message,
+        "relatedInformation": related,
+    })
+}
+
+fn item(label: &str, kind: u8, detail: String) -> Value {
+    json!({ "label": label, "kind": kind, "detail": detail })
+}
+
+/// Picks `function` if the value may be a function, and `other` otherwise.
+fn field_kind(kinds: &[Kind], function: u8, other: u8) -> u8 {
+    if kinds.iter().any(|kind| matches!(kind, Kind::Function(..))) {
+        function
+    } else {
+        other
+    }
+}
+
+/// Returns the symbols of the fields of an object. `visited` guards against cyclic objects.
+fn field_symbols(document: &Document, shape: ShapeId, visited: &mut Vec<ShapeId>) -> Vec<Value> {
+    if visited.contains(&shape) {
+        return vec![];
+    }
+    visited.push(shape);
+    let symbols = document
+        .analysis
+        .fields(shape)
+        .iter()
+        .map(|field| {
+            let kind = field_kind(&field.kinds, symbol_kind::METHOD, symbol_kind::FIELD);
+            json!({
+                "name": field.name,
+                "detail": describe(&field.kinds),
+                "kind": kind,
+                "range": document.range(field.range),
+                "selectionRange": document.range(field.span),
+                "children": field_symbols(document, field.shape, visited),
+            })
+        })
+        .collect();
+    visited.pop();
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rpc::read_message;
+    use serde_json::{json, Value};
+
+    const URI: &str = "file:///test.ket";
+    const SRC: &str = r#"person = {
+    name: "Bob",
+    greet: function(other) { "hi " + other + ", I'm " + this.name }
+};
+count = 1;
+person.greet("Alice");
+message = "hello";
+message.
+"#;
+
+    /// Runs the server on the given messages, returning its exit code and all messages it sent.
+    fn run(messages: &[Value]) -> (i32, Vec<Value>) {
+        let mut input = vec![];
+        for message in messages {
+            let content = message.to_string();
+            input.extend(format!("Content-Length: {}\r\n\r\n{}", content.len(), content).bytes());
+        }
+        let mut output = vec![];
+        let code = super::serve(&input[..], &mut output).unwrap();
+
+        let mut output = &output[..];
+        let mut responses = vec![];
+        while let Some(content) = read_message(&mut output).unwrap() {
+            responses.push(serde_json::from_slice(&content).unwrap());
+        }
+        (code, responses)
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn notification(method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "method": method, "params": params })
+    }
+
+    fn open(text: &str) -> Value {
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": URI, "languageId": "ketamine", "version": 1, "text": text } }),
+        )
+    }
+
+    fn at(line: u64, character: u64) -> Value {
+        json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+    }
+
+    /// Opens `SRC`, sends a single request and returns its result.
+    fn query(method: &str, params: Value) -> Value {
+        let (_, responses) = run(&[open(SRC), request(1, method, params)]);
+        responses
+            .into_iter()
+            .find(|response| response["id"] == 1)
+            .unwrap()["result"]
+            .clone()
+    }
+
+    #[test]
+    fn lifecycle() {
+        let (code, responses) = run(&[
+            request(1, "initialize", json!({ "capabilities": {} })),
+            notification("initialized", json!({})),
+            request(2, "unknown/method", json!({})),
+            request(3, "shutdown", Value::Null),
+            notification("exit", Value::Null),
+        ]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            responses[0]["result"]["capabilities"]["hoverProvider"],
+            true
+        );
+        assert_eq!(responses[1]["error"]["code"], -32601);
+        assert_eq!(responses[2]["result"], Value::Null);
+
+        let (code, _) = run(&[notification("exit", Value::Null)]);
+        assert_eq!(code, 1);
+    }
+
+    #[test]
+    fn diagnostics() {
+        let change = notification(
+            "textDocument/didChange",
+            json!({ "textDocument": { "uri": URI, "version": 2 }, "contentChanges": [{ "text": "x = 1;" }] }),
+        );
+        let (_, notifications) = run(&[open("x = = 1;\ny = ;"), change]);
+        let diagnostics = &notifications[0]["params"]["diagnostics"];
+        assert_eq!(notifications[0]["params"]["uri"], URI);
+        assert_eq!(diagnostics.as_array().unwrap().len(), 2);
+        assert_eq!(diagnostics[0]["code"], "E0101");
+        assert_eq!(
+            diagnostics[1]["range"],
+            json!({ "start": { "line": 1, "character": 4 }, "end": { "line": 1, "character": 5 } })
+        );
+        assert_eq!(notifications[1]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn definition() {
+        // `person` in `person.greet(..)`
+        let location = query("textDocument/definition", at(5, 2));
+        assert_eq!(
+            location["range"]["start"],
+            json!({ "line": 0, "character": 0 })
+        );
+        // `greet` in `person.greet(..)`
+        let location = query("textDocument/definition", at(5, 9));
+        assert_eq!(
+            location["range"]["start"],
+            json!({ "line": 2, "character": 4 })
+        );
+        // `name` in `this.name`
+        let location = query("textDocument/definition", at(2, 64));
+        assert_eq!(
+            location["range"]["start"],
+            json!({ "line": 1, "character": 4 })
+        );
+        // `other` within the method
+        let location = query("textDocument/definition", at(2, 40));
+        assert_eq!(
+            location["range"]["start"],
+            json!({ "line": 2, "character": 20 })
+        );
+    }
+
+    #[test]
+    fn hover() {
+        let hover = query("textDocument/hover", at(5, 9));
+        assert_eq!(
+            hover["contents"]["value"],
+            "```ketamine\ngreet: function(other)\n```"
+        );
+        let hover = query("textDocument/hover", at(4, 1));
+        assert_eq!(
+            hover["contents"]["value"],
+            "```ketamine\ncount: integer\n```"
+        );
+    }
+
+    #[test]
+    fn completion() {
+        let labels = |items: Value| {
+            items
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["label"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let items = labels(query("textDocument/completion", at(7, 8)));
+        assert_eq!(
+            items,
+            vec!["contains", "length", "to_boolean", "to_float", "to_int"]
+        );
+
+        let items = labels(query("textDocument/completion", at(8, 0)));
+        for expected in &["person", "count", "message", "print", "eval", "while"] {
+            assert!(
+                items.contains(&expected.to_string()),
+                "missing {}",
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn document_symbols() {
+        let symbols = query(
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": URI } }),
+        );
+        let names = symbols
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| symbol["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["person", "count", "message"]);
+        assert_eq!(symbols[0]["kind"], 19);
+        assert_eq!(symbols[0]["children"][1]["name"], "greet");
+        assert_eq!(symbols[0]["children"][1]["kind"], 6);
+    }
+
+    #[test]
+    fn formatting() {
+        let params = json!({ "textDocument": { "uri": URI }, "options": { "tabSize": 2, "insertSpaces": true } });
+        let (_, responses) = run(&[
+            open("f = function(a){ a; a };"),
+            request(1, "textDocument/formatting", params),
+        ]);
+        let edits = &responses[1]["result"];
+        assert_eq!(edits[0]["newText"], "f = function(a) {\n  a;\n  a\n};\n");
+        assert_eq!(
+            edits[0]["range"]["end"],
+            json!({ "line": 0, "character": 24 })
+        );
+    }
+}
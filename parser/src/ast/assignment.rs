@@ -1,6 +1,9 @@
-use crate::ast::AST;
+use crate::ast::{Pattern, AST};
+use crate::error::{Error, Expected, ParseResult, ResultExt};
 use crate::impl_into_enum;
-use crate::Pos;
+use crate::token_ext::TokenExt;
+use crate::{Parse, Pos, Token};
+use lexer::TokenValue;
 
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[derive(Debug, Clone)]
@@ -9,3 +12,42 @@ pub struct Assignment {
     pub value: Pos<Box<AST>>,
 }
 impl_into_enum!(Assignment => AST:Assignment);
+
+/// An assignment to an array or object pattern, like `[a, b] = pair` or `{name, age} = person`.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub struct Destructuring {
+    pub pattern: Pos<Pattern>,
+    pub value: Pos<Box<AST>>,
+}
+impl_into_enum!(Destructuring => AST:Destructuring);
+
+impl Parse for Destructuring {
+    /// Patterns look like array and object literals, so anything that isn't followed by `=` is
+    /// left to be parsed as a literal.
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let open = tokens.peek(pos)?;
+        if !matches!(open.value, TokenValue::BracketOpen | TokenValue::BraceOpen) {
+            let expected = Expected::from(&TokenValue::BracketOpen).with(&TokenValue::BraceOpen);
+            return Err(Pos {
+                start: open.start,
+                end: open.end,
+                value: Error::unexpected(&open.value, expected).recoverable(),
+            });
+        }
+        let pattern = Pattern::parse(pos, tokens).into_recoverable()?;
+        let assign = tokens
+            .pop_expect(pattern.end, &TokenValue::Assign)
+            .into_recoverable()?;
+        Pattern::check_rest(&pattern)?;
+        let value = AST::parse(assign.end, tokens).into_fatal()?;
+        Ok(Pos {
+            start: pattern.start,
+            end: value.end,
+            value: Destructuring {
+                pattern,
+                value: value.map(Box::new),
+            },
+        })
+    }
+}
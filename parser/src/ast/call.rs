@@ -1,5 +1,8 @@
 use crate::ast::AST;
-use crate::{impl_into_enum, Pos};
+use crate::error::{Expected, ParseResult, ResultExt};
+use crate::token_ext::TokenExt;
+use crate::{impl_into_enum, Parse, Pos, Token};
+use lexer::TokenValue;
 
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[derive(Debug, Clone)]
@@ -9,3 +12,35 @@ pub struct Call {
 }
 
 impl_into_enum!(Call => AST:Call);
+
+/// An argument like `...args`, which passes the elements of an array as separate arguments.
+/// It can only appear in the arguments of a call.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub struct Spread(pub Pos<Box<AST>>);
+
+impl_into_enum!(Spread => AST:Spread);
+
+/// An argument of a call, which can be spread.
+pub(crate) struct Argument(pub AST);
+
+impl Parse for Argument {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        match tokens.peek(pos) {
+            Ok(token) if token.value == TokenValue::Spread => {
+                let spread = tokens.pop_unwrap();
+                let value = AST::parse(spread.end, tokens).into_fatal()?;
+                Ok(Pos {
+                    start: spread.start,
+                    end: value.end,
+                    value: Argument(AST::Spread(Spread(value.map(Box::new)))),
+                })
+            }
+            _ => Ok(AST::parse(pos, tokens)?.map(Argument)),
+        }
+    }
+
+    fn continuations() -> Expected {
+        AST::continuations()
+    }
+}
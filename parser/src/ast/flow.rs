@@ -21,7 +21,7 @@ pub struct Continue;
 impl_into_enum!(Continue => AST:Continue);
 
 impl Parse for Return {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let keyword = tokens
             .pop_expect(pos, &TokenValue::ReturnKeyword)
             .into_recoverable()?;
@@ -44,7 +44,7 @@ impl Parse for Return {
 }
 
 impl Parse for Break {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let keyword = tokens
             .pop_expect(pos, &TokenValue::BreakKeyword)
             .into_recoverable()?;
@@ -67,7 +67,7 @@ impl Parse for Break {
 }
 
 impl Parse for Continue {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let kw = tokens
             .pop_expect(pos, &TokenValue::ContinueKeyword)
             .into_recoverable()?;
@@ -1,31 +1,122 @@
 use crate::ast::{parse_delimited_block, CodeBlock, Ident, AST};
-use crate::error::{ParseResult, ResultExt};
-use crate::impl_into_enum;
+use crate::error::{Error, Expectation, Expected, ParseResult, ResultExt};
 use crate::token_ext::TokenExt;
 use crate::{parse_list, Parse, Pos, Token};
 use lexer::TokenValue;
 
+use std::rc::Rc;
+
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[derive(Debug, Clone)]
 pub struct Function {
-    pub params: Vec<Pos<Ident>>,
+    /// The name of a function declared like `function fib(n) {..}`.
+    pub name: Option<Pos<Ident>>,
+    pub params: Vec<Pos<Param>>,
     pub body: CodeBlock,
 }
-impl_into_enum!(Function => AST:Function);
+
+impl From<Function> for AST {
+    fn from(function: Function) -> Self {
+        AST::Function(Rc::new(function))
+    }
+}
+
+/// A parameter of a function, like `a`, `b = 2` or `...rest`.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: Pos<Ident>,
+    /// The value of the parameter if the call passes no argument for it.
+    pub default: Option<Pos<Box<AST>>>,
+    /// Whether the parameter collects all remaining arguments into an array. Only the last
+    /// parameter can be a rest parameter.
+    pub rest: bool,
+}
+
+impl Parse for Param {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let spread = match tokens.peek(pos) {
+            Ok(token) if token.value == TokenValue::Spread => Some(tokens.pop_unwrap()),
+            _ => None,
+        };
+        let name = match spread {
+            Some(spread) => Ident::parse(spread.end, tokens).into_fatal()?,
+            None => {
+                Ident::parse(pos, tokens).also_expecting(Expected::from(&TokenValue::Spread))?
+            }
+        };
+        let default = match tokens.peek(name.end) {
+            Ok(token) if spread.is_none() && token.value == TokenValue::Assign => {
+                let assign = tokens.pop_unwrap();
+                Some(AST::parse(assign.end, tokens).into_fatal()?.map(Box::new))
+            }
+            _ => None,
+        };
+        Ok(Pos {
+            start: spread.map_or(name.start, |spread| spread.start),
+            end: default.as_ref().map_or(name.end, |default| default.end),
+            value: Param {
+                name,
+                default,
+                rest: spread.is_some(),
+            },
+        })
+    }
+
+    fn continuations() -> Expected {
+        Expected(vec![Expectation::Token(TokenValue::Assign)])
+    }
+}
 
 impl Parse for Function {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let keyword = tokens
             .pop_expect(pos, &TokenValue::FunctionKeyword)
             .into_recoverable()?;
-        let args = parse_list(
-            keyword.end,
+        let name = match tokens.peek(keyword.end) {
+            Ok(Pos {
+                start,
+                end,
+                value: TokenValue::Identifier(name),
+            }) => {
+                tokens.pop_unwrap();
+                Some(Pos::new(*start, *end, Ident(name.clone())))
+            }
+            _ => None,
+        };
+        let args: Pos<Vec<Pos<Param>>> = parse_list(
+            name.as_ref().map_or(keyword.end, |name| name.end),
             tokens,
             TokenValue::ParenthesesOpen,
             TokenValue::ParenthesesClose,
             TokenValue::Comma,
         )
-        .into_fatal()?;
+        .into_fatal()
+        .context(
+            keyword.start,
+            keyword.end,
+            "while parsing the parameters of this function",
+        )?;
+
+        if let Some(idx) = args.value.iter().position(|param| param.value.rest) {
+            if let Some(next) = args.value.get(idx + 1) {
+                let found = match next.value.rest {
+                    true => TokenValue::Spread,
+                    false => TokenValue::Identifier(next.value.name.value.0.clone()),
+                };
+                let rest = &args.value[idx];
+                return Err(Pos {
+                    start: next.start,
+                    end: next.end,
+                    value: Error::unexpected(&found, &TokenValue::ParenthesesClose).fatal(),
+                })
+                .context(
+                    rest.start,
+                    rest.end,
+                    "a rest parameter must be the last one",
+                );
+            }
+        }
 
         let body = parse_delimited_block(args.end, tokens).into_fatal()?;
 
@@ -33,6 +124,7 @@ impl Parse for Function {
             start: keyword.start,
             end: body.end,
             value: Function {
+                name,
                 params: args.value,
                 body: body.value,
             },
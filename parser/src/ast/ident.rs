@@ -1,4 +1,4 @@
-use crate::error::{Error, ParseResult};
+use crate::error::{Error, Expected, ParseResult, ResultExt};
 use crate::impl_into_enum;
 use crate::token_ext::TokenExt;
 use crate::AST;
@@ -12,8 +12,10 @@ pub struct Ident(pub String);
 impl_into_enum!(Ident => AST:Ident);
 
 impl Parse for Ident {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
-        let token = tokens.pop(pos)?;
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let token = tokens
+            .pop(pos)
+            .also_expecting(Expected::from("identifier"))?;
         match token.value {
             TokenValue::Identifier(ref ident) => Ok(Pos {
                 start: token.start,
@@ -23,11 +25,7 @@ impl Parse for Ident {
             ref unexpected => Err(Pos {
                 start: token.start,
                 end: token.end,
-                value: Error::Unexpected {
-                    unexpected,
-                    expected: "identifier",
-                }
-                .recoverable(),
+                value: Error::unexpected(unexpected, "identifier").recoverable(),
             }),
         }
     }
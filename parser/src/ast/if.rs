@@ -1,7 +1,7 @@
 use crate::ast::{parse_delimited_block, CodeBlock, Statement, AST};
 use crate::error::{ParseResult, ResultExt, Severity};
 use crate::token_ext::TokenExt;
-use crate::{find_closing_delimiter, impl_into_enum, Parse, Pos, Token};
+use crate::{find_closing_delimiter, impl_into_enum, parse_enclosed, Parse, Pos, Token};
 use lexer::TokenValue;
 
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
@@ -21,7 +21,7 @@ pub struct IfBranch {
 }
 
 impl Parse for IfBranch {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let keyword = tokens
             .pop_expect(pos, &TokenValue::IfKeyword)
             .into_recoverable()?;
@@ -34,9 +34,13 @@ impl Parse for IfBranch {
             1,
         )
         .into_fatal()?;
-        let mut condition_tokens = &tokens[..par_close_idx];
-        let condition = AST::parse(par_open.end, &mut condition_tokens).into_fatal()?;
-        assert!(condition_tokens.is_empty());
+        let condition: Pos<AST> = parse_enclosed(par_open.end, tokens, par_close_idx)
+            .into_fatal()
+            .context(
+                keyword.start,
+                keyword.end,
+                "while parsing the condition of this `if`",
+            )?;
         *tokens = &tokens[par_close_idx..];
         let par_close = tokens.pop_expect(condition.end, &TokenValue::ParenthesesClose)?;
 
@@ -54,8 +58,9 @@ impl Parse for IfBranch {
 }
 
 impl Parse for If {
-    fn parse<'a>(mut pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(mut pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let if_branch = IfBranch::parse(pos, tokens)?;
+        pos = if_branch.end;
         let mut else_if_branches = vec![];
 
         loop {
@@ -100,7 +105,7 @@ impl Parse for If {
     }
 }
 
-fn parse_else_if<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, IfBranch> {
+fn parse_else_if(pos: usize, tokens: &mut &[Token]) -> ParseResult<IfBranch> {
     let else_kw = tokens
         .pop_expect(pos, &TokenValue::ElseKeyword)
         .into_recoverable()?;
@@ -109,7 +114,7 @@ fn parse_else_if<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, If
     Ok(if_branch)
 }
 
-fn parse_else<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, CodeBlock> {
+fn parse_else(pos: usize, tokens: &mut &[Token]) -> ParseResult<CodeBlock> {
     let else_kw = tokens
         .pop_expect(pos, &TokenValue::ElseKeyword)
         .into_recoverable()?;
@@ -0,0 +1,30 @@
+use crate::ast::{Statement, AST};
+use crate::error::ParseError;
+use crate::impl_into_enum;
+use crate::visit::Visitor;
+use crate::Pos;
+
+/// Placeholder for a statement which could not be parsed.
+/// It is left behind by error recovery so that the remaining code can still be parsed.
+/// Besides the error of the statement itself, it keeps the errors of `Invalid` nodes nested in
+/// the part of the statement which was parsed before the error.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub struct Invalid(pub ParseError, pub Vec<ParseError>);
+impl_into_enum!(Invalid => AST:Invalid);
+
+/// Collects the errors of all `Invalid` nodes within the given code, in source order.
+pub fn collect_errors(code: &[Pos<Statement>]) -> Vec<ParseError> {
+    struct Collector(Vec<ParseError>);
+
+    impl Visitor for Collector {
+        fn visit_invalid(&mut self, node: Pos<&Invalid>) {
+            self.0.extend(node.value.1.iter().cloned());
+            self.0.push(node.value.0.clone());
+        }
+    }
+
+    let mut collector = Collector(vec![]);
+    collector.visit_code(code);
+    collector.0
+}
@@ -1,5 +1,5 @@
 use crate::ast::{Ident, AST};
-use crate::error::{ParseResult, ResultExt};
+use crate::error::{Expected, ParseResult, ResultExt};
 use crate::impl_into_enum;
 use crate::token_ext::TokenExt;
 use crate::{parse_list, Parse, Pos, Token};
@@ -16,14 +16,16 @@ pub struct Object(pub Vec<(Pos<Ident>, Pos<AST>)>);
 impl_into_enum!(Object => AST:Object);
 
 impl Parse for List {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let open = tokens.peek(pos)?;
         let list = parse_list(
             pos,
             tokens,
             TokenValue::BracketOpen,
             TokenValue::BracketClose,
             TokenValue::Comma,
-        )?;
+        )
+        .context(open.start, open.end, "while parsing this array")?;
         Ok(Pos {
             start: list.start,
             end: list.end,
@@ -35,7 +37,7 @@ impl Parse for List {
 struct KVPair(Pos<Ident>, Pos<AST>);
 
 impl Parse for KVPair {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let ident = Ident::parse(pos, tokens)?;
         let colon = tokens
             .pop_expect(ident.end, &TokenValue::Colon)
@@ -43,17 +45,23 @@ impl Parse for KVPair {
         let value = AST::parse(colon.end, tokens).into_fatal()?;
         Ok(Pos::new(ident.start, value.end, KVPair(ident, value)))
     }
+
+    fn continuations() -> Expected {
+        AST::continuations()
+    }
 }
 
 impl Parse for Object {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let open = tokens.peek(pos)?;
         let pairs: Pos<Vec<Pos<KVPair>>> = parse_list(
             pos,
             tokens,
             TokenValue::BraceOpen,
             TokenValue::BraceClose,
             TokenValue::Comma,
-        )?;
+        )
+        .context(open.start, open.end, "while parsing this object")?;
         Ok(Pos {
             start: pairs.start,
             end: pairs.end,
@@ -1,4 +1,4 @@
-use crate::ast::{parse_delimited_block, CodeBlock, Ident, AST};
+use crate::ast::{parse_delimited_block, CodeBlock, Pattern, AST};
 use crate::error::{ParseResult, ResultExt};
 use crate::impl_into_enum;
 use crate::token_ext::TokenExt;
@@ -8,7 +8,7 @@ use lexer::TokenValue;
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[derive(Debug, Clone)]
 pub struct ForLoop {
-    pub binding: Pos<Ident>,
+    pub binding: Pos<Pattern>,
     pub iterator: Pos<Box<AST>>,
     pub body: CodeBlock,
 }
@@ -23,21 +23,33 @@ pub struct WhileLoop {
 impl_into_enum!(WhileLoop => AST:WhileLoop);
 
 impl Parse for ForLoop {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let for_kw = tokens
             .pop_expect(pos, &TokenValue::ForKeyword)
             .into_recoverable()?;
         let par_open = tokens
             .pop_expect(for_kw.end, &TokenValue::ParenthesesOpen)
             .into_fatal()?;
-        let binding = Ident::parse(par_open.end, tokens).into_fatal()?;
+        let note = "while parsing the head of this `for` loop";
+        let binding = Pattern::parse(par_open.end, tokens).into_fatal().context(
+            for_kw.start,
+            for_kw.end,
+            note,
+        )?;
+        Pattern::check_rest(&binding).context(for_kw.start, for_kw.end, note)?;
         let in_kw = tokens
             .pop_expect(binding.end, &TokenValue::InKeyword)
-            .into_fatal()?;
-        let iterator = AST::parse(in_kw.end, tokens).into_fatal()?;
+            .into_fatal()
+            .context(for_kw.start, for_kw.end, note)?;
+        let iterator =
+            AST::parse(in_kw.end, tokens)
+                .into_fatal()
+                .context(for_kw.start, for_kw.end, note)?;
         let par_close = tokens
             .pop_expect(iterator.end, &TokenValue::ParenthesesClose)
-            .into_fatal()?;
+            .also_expecting(AST::continuations())
+            .into_fatal()
+            .context(for_kw.start, for_kw.end, note)?;
         let body = parse_delimited_block(par_close.end, tokens).into_fatal()?;
 
         Ok(Pos {
@@ -53,17 +65,24 @@ impl Parse for ForLoop {
 }
 
 impl Parse for WhileLoop {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let while_kw = tokens
             .pop_expect(pos, &TokenValue::WhileKeyword)
             .into_recoverable()?;
         let par_open = tokens
             .pop_expect(while_kw.end, &TokenValue::ParenthesesOpen)
             .into_fatal()?;
-        let condition = AST::parse(par_open.end, tokens).into_fatal()?;
+        let note = "while parsing the condition of this `while` loop";
+        let condition = AST::parse(par_open.end, tokens).into_fatal().context(
+            while_kw.start,
+            while_kw.end,
+            note,
+        )?;
         let par_close = tokens
             .pop_expect(condition.end, &TokenValue::ParenthesesClose)
-            .into_fatal()?;
+            .also_expecting(AST::continuations())
+            .into_fatal()
+            .context(while_kw.start, while_kw.end, note)?;
         let body = parse_delimited_block(par_close.end, tokens).into_fatal()?;
 
         Ok(Pos {
@@ -0,0 +1,102 @@
+use crate::ast::{parse_delimited_block, CodeBlock, Pattern, AST};
+use crate::error::{ParseResult, ResultExt};
+use crate::impl_into_enum;
+use crate::token_ext::TokenExt;
+use crate::{parse_list, Parse, Pos, Token};
+use lexer::TokenValue;
+
+/// An expression like `match (value) { 1 => {..}, [a, b] => {..}, _ => {..} }`, which evaluates
+/// the body of the first arm whose pattern matches the value.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub value: Pos<Box<AST>>,
+    pub arms: Vec<Pos<MatchArm>>,
+}
+impl_into_enum!(Match => AST:Match);
+
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pos<Pattern>,
+    /// A condition like `n if n > 0 => {..}`, which has to hold for the arm to match.
+    pub guard: Option<Pos<Box<AST>>>,
+    pub body: CodeBlock,
+}
+
+impl Parse for MatchArm {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let pattern = Pattern::parse(pos, tokens)?;
+        Pattern::check_rest(&pattern)?;
+        let guard = match tokens.peek(pattern.end) {
+            Ok(token) if token.value == TokenValue::IfKeyword => {
+                let keyword = tokens.pop_unwrap();
+                Some(AST::parse(keyword.end, tokens).into_fatal()?.map(Box::new))
+            }
+            _ => None,
+        };
+        let arrow = match &guard {
+            Some(guard) => tokens
+                .pop_expect(guard.end, &TokenValue::FatArrow)
+                .also_expecting(AST::continuations()),
+            None => tokens
+                .pop_expect(pattern.end, &TokenValue::FatArrow)
+                .also_expecting(Pattern::continuations().with(&TokenValue::IfKeyword)),
+        }
+        .into_fatal()?;
+        let body = parse_delimited_block(arrow.end, tokens).into_fatal()?;
+        Ok(Pos {
+            start: pattern.start,
+            end: body.end,
+            value: MatchArm {
+                pattern,
+                guard,
+                body: body.value,
+            },
+        })
+    }
+}
+
+impl Parse for Match {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let keyword = tokens
+            .pop_expect(pos, &TokenValue::MatchKeyword)
+            .into_recoverable()?;
+        let par_open = tokens
+            .pop_expect(keyword.end, &TokenValue::ParenthesesOpen)
+            .into_fatal()?;
+        let note = "while parsing the value of this `match`";
+        let value = AST::parse(par_open.end, tokens).into_fatal().context(
+            keyword.start,
+            keyword.end,
+            note,
+        )?;
+        let par_close = tokens
+            .pop_expect(value.end, &TokenValue::ParenthesesClose)
+            .also_expecting(AST::continuations())
+            .into_fatal()
+            .context(keyword.start, keyword.end, note)?;
+        let arms: Pos<Vec<Pos<MatchArm>>> = parse_list(
+            par_close.end,
+            tokens,
+            TokenValue::BraceOpen,
+            TokenValue::BraceClose,
+            TokenValue::Comma,
+        )
+        .into_fatal()
+        .context(
+            keyword.start,
+            keyword.end,
+            "while parsing the arms of this `match`",
+        )?;
+
+        Ok(Pos {
+            start: keyword.start,
+            end: arms.end,
+            value: Match {
+                value: value.map(Box::new),
+                arms: arms.value,
+            },
+        })
+    }
+}
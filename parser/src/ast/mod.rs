@@ -9,13 +9,17 @@ mod function;
 mod ident;
 mod r#if;
 mod index;
+mod invalid;
 mod literals;
 mod loops;
+mod r#match;
 mod operation;
 mod parentheses;
+mod pattern;
 mod primitives;
 mod range;
 mod statement;
+mod variable;
 
 pub use assignment::*;
 pub use call::*;
@@ -24,20 +28,25 @@ pub use flow::*;
 pub use function::*;
 pub use ident::*;
 pub use index::*;
+pub use invalid::*;
 pub use literals::*;
 pub use loops::*;
 pub use operation::*;
 pub use parentheses::*;
+pub use pattern::*;
 pub use primitives::*;
 pub use r#if::*;
+pub use r#match::*;
 pub use range::*;
 pub use statement::*;
+pub use variable::*;
 
-use crate::error::{Error, ParseResult, ResultExt};
+use crate::error::{Error, Expectation, Expected, ParseResult, ResultExt};
 use crate::first_value_of;
 use crate::token_ext::TokenExt;
 
 use std::convert::TryFrom;
+use std::rc::Rc;
 
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[derive(Debug, Clone)]
@@ -51,31 +60,41 @@ pub enum AST {
     Continue(Continue),
     Return(Return),
     Assignment(Assignment),
+    Destructuring(Destructuring),
+    VariableDeclaration(VariableDeclaration),
     FieldAccess(FieldAccess),
     Index(Index),
-    Function(Function),
+    /// Functions are shared, so that the function values created when evaluating them don't
+    /// copy their body.
+    Function(Rc<Function>),
     If(If),
+    Match(Match),
     BinaryOperation(BinaryOperation),
     UnaryOperation(UnaryOperation),
     Parentheses(Parentheses),
     Call(Call),
+    Spread(Spread),
     List(List),
     Object(Object),
     Range(Range),
     WhileLoop(WhileLoop),
     ForLoop(ForLoop),
+    Invalid(Invalid),
 }
 
 first_value_of!(
     AtomicValues: Return,
     Break,
     Continue,
+    VariableDeclaration,
     Function,
     If,
+    Match,
     ForLoop,
     WhileLoop,
     UnaryOperation,
     Parentheses,
+    Destructuring,
     List,
     Object,
     Ident,
@@ -83,49 +102,59 @@ first_value_of!(
 );
 
 impl Parse for AST {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let mut ast: Pos<AST> = AST::parse_atomic(pos, tokens)?;
 
-        loop {
-            match tokens.peek(pos).ok() {
-                None
-                | Some(Pos {
-                    value: TokenValue::Semicolon,
-                    ..
-                })
-                | Some(Pos {
-                    value: TokenValue::Comma,
-                    ..
-                })
-                | Some(Pos {
-                    value: TokenValue::ParenthesesClose,
-                    ..
-                })
-                | Some(Pos {
-                    value: TokenValue::BraceClose,
-                    ..
-                })
-                | Some(Pos {
-                    value: TokenValue::BracketClose,
-                    ..
-                }) => return Ok(ast),
-                Some(_next) => ast = AST::append(ast, tokens)?,
-            };
+        while let Ok(next) = tokens.peek(ast.end) {
+            if !AST::continues_with(&next.value) {
+                break;
+            }
+            ast = AST::append(ast, tokens)?;
         }
+        Ok(ast)
+    }
+
+    fn continuations() -> Expected {
+        Expected(vec![
+            Expectation::Token(TokenValue::Dot),
+            Expectation::Token(TokenValue::ParenthesesOpen),
+            Expectation::Token(TokenValue::BracketOpen),
+            Expectation::Token(TokenValue::Range),
+            Expectation::Token(TokenValue::InclusiveRange),
+            Expectation::Token(TokenValue::Assign),
+            Expectation::Kind("an operator"),
+        ])
     }
 }
 
 impl AST {
-    pub fn parse_atomic<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    pub fn parse_atomic(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        tokens
+            .peek(pos)
+            .also_expecting(Expected::from("expression"))?;
         Ok(AtomicValues::parse(pos, tokens)?.map(AST::from))
     }
 
-    fn append<'a>(prev: Pos<Self>, tokens: &mut &'a [Token]) -> ParseResult<'a, AST> {
+    /// Returns whether the token can extend a complete expression, e.g. into a call or a binary
+    /// operation.
+    pub(crate) fn continues_with(token: &TokenValue) -> bool {
+        match token {
+            TokenValue::Assign
+            | TokenValue::Dot
+            | TokenValue::BracketOpen
+            | TokenValue::ParenthesesOpen
+            | TokenValue::Range
+            | TokenValue::InclusiveRange => true,
+            other => BinaryOperator::try_from(other).is_ok(),
+        }
+    }
+
+    fn append(prev: Pos<Self>, tokens: &mut &[Token]) -> ParseResult<AST> {
         let next = tokens.peek_unwrap();
         match &next.value {
             TokenValue::Assign => {
-                tokens.pop_unwrap();
-                let next = AST::parse(prev.end, tokens)?;
+                let assign = tokens.pop_unwrap();
+                let next = AST::parse(assign.end, tokens)?;
                 Ok(Pos {
                     start: prev.start,
                     end: next.end,
@@ -150,7 +179,9 @@ impl AST {
             TokenValue::BracketOpen => {
                 let open = tokens.pop_unwrap();
                 let index = AST::parse(open.end, tokens)?.map(Box::new);
-                let close = tokens.pop_expect(index.end, &TokenValue::BracketClose)?;
+                let close = tokens
+                    .pop_expect(index.end, &TokenValue::BracketClose)
+                    .also_expecting(AST::continuations())?;
                 Ok(Pos {
                     start: prev.start,
                     end: close.end,
@@ -161,25 +192,35 @@ impl AST {
                 })
             }
             TokenValue::ParenthesesOpen => {
-                let args: Pos<Vec<Pos<AST>>> = parse_list(
+                let args: Pos<Vec<Pos<Argument>>> = parse_list(
                     next.start,
                     tokens,
                     TokenValue::ParenthesesOpen,
                     TokenValue::ParenthesesClose,
                     TokenValue::Comma,
                 )
-                .into_fatal()?;
+                .into_fatal()
+                .context(
+                    prev.start,
+                    prev.end,
+                    "while parsing the arguments of this call",
+                )?;
                 Ok(Pos {
                     start: prev.start,
                     end: args.end,
                     value: AST::Call(Call {
                         value: prev.map(Box::new),
-                        args: args.value,
+                        args: args
+                            .value
+                            .into_iter()
+                            .map(|arg| arg.map(|Argument(arg)| arg))
+                            .collect(),
                     }),
                 })
             }
-            TokenValue::Range => {
+            TokenValue::Range | TokenValue::InclusiveRange => {
                 let range_token = tokens.pop_unwrap();
+                let inclusive = range_token.value == TokenValue::InclusiveRange;
                 let to = AST::parse_atomic(range_token.end, tokens).into_fatal()?;
                 Ok(Pos {
                     start: prev.start,
@@ -187,6 +228,7 @@ impl AST {
                     value: AST::Range(Range {
                         from: prev.map(Box::new),
                         to: to.map(Box::new),
+                        inclusive,
                     }),
                 })
             }
@@ -202,20 +244,16 @@ impl AST {
             other => Err(Pos {
                 start: next.start,
                 end: next.end,
-                value: Error::Unexpected {
-                    unexpected: other,
-                    expected: TokenValue::Semicolon.name(),
-                }
-                .fatal(),
+                value: Error::unexpected(other, AST::continuations()).fatal(),
             }),
         }
     }
 }
 
-pub fn parse_delimited_block<'a>(
+pub fn parse_delimited_block(
     pos: usize,
-    tokens: &mut &'a [Token],
-) -> ParseResult<'a, Vec<Pos<Statement>>> {
+    tokens: &mut &[Token],
+) -> ParseResult<Vec<Pos<Statement>>> {
     let brace_open = tokens
         .pop_expect(pos, &TokenValue::BraceOpen)
         .into_recoverable()?;
@@ -122,7 +122,7 @@ pub struct UnaryOperation {
 impl_into_enum!(UnaryOperation => AST:UnaryOperation);
 
 impl Parse for UnaryOperation {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let op_token = tokens.pop(pos)?;
         let op = match &op_token.value {
             TokenValue::Negate => UnaryOperator::Negate,
@@ -131,11 +131,7 @@ impl Parse for UnaryOperation {
                 return Err(Pos {
                     start: op_token.start,
                     end: op_token.end,
-                    value: Error::Unexpected {
-                        unexpected,
-                        expected: "unary operator",
-                    }
-                    .recoverable(),
+                    value: Error::unexpected(unexpected, "unary operator").recoverable(),
                 })
             }
         };
@@ -2,7 +2,7 @@ use crate::ast::AST;
 use crate::error::{ParseResult, ResultExt};
 use crate::impl_into_enum;
 use crate::token_ext::TokenExt;
-use crate::{find_closing_delimiter, Parse, Pos, Token};
+use crate::{find_closing_delimiter, parse_enclosed, Parse, Pos, Token};
 use lexer::TokenValue;
 
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
@@ -12,7 +12,7 @@ pub struct Parentheses(pub Pos<Box<AST>>);
 impl_into_enum!(Parentheses => AST:Parentheses);
 
 impl Parse for Parentheses {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let par_open = tokens
             .pop_expect(pos, &TokenValue::ParenthesesOpen)
             .into_recoverable()?;
@@ -24,9 +24,7 @@ impl Parse for Parentheses {
             1,
         )
         .into_fatal()?;
-        let mut value_tokens = &tokens[..par_close_idx];
-        let value = AST::parse(par_open.end, &mut value_tokens).into_fatal()?;
-        assert!(value_tokens.is_empty());
+        let value: Pos<AST> = parse_enclosed(par_open.end, tokens, par_close_idx).into_fatal()?;
         *tokens = &tokens[par_close_idx..];
         let par_close = tokens
             .pop_expect(value.end, &TokenValue::ParenthesesClose)
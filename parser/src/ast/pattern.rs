@@ -0,0 +1,342 @@
+use crate::ast::{Ident, AST};
+use crate::error::{Error, Expectation, Expected, ParseResult, ResultExt, Severity};
+use crate::token_ext::TokenExt;
+use crate::{parse_list, Parse, Pos, Token};
+use lexer::TokenValue;
+
+/// A pattern which a value is matched against, binding its parts to variables. Patterns are the
+/// receivers of destructuring assignments, the bindings of for loops and the arms of `match`.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Ident(Ident),
+    /// `_`, which matches any value without binding it.
+    Wildcard,
+    /// A literal like `1`, `"x"` or `null`, which matches values equal to it.
+    Literal(Literal),
+    /// A type like `$integer`, which matches any value of the type.
+    Type(String),
+    /// A pattern like `[a, b = 2, ...rest]`, binding the elements of an array.
+    Array(Vec<Pos<PatternElement>>),
+    /// A pattern like `{name, age: years, ...rest}`, binding the fields of an object.
+    Object(Vec<Pos<PatternElement>>),
+    /// Alternatives like `"x" | "y"`, of which the first matching one is used.
+    Or(Vec<Pos<Pattern>>),
+}
+
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Null,
+}
+
+/// An element of an array or object pattern, like `a`, `b = 2`, `name: [first, last]` or
+/// `...rest`.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub struct PatternElement {
+    /// The field an element of an object pattern is read from. Elements of array patterns and
+    /// rest elements have none.
+    pub key: Option<Pos<Ident>>,
+    pub pattern: Pos<Pattern>,
+    /// The value that is bound if the array has no such element or the object no such field.
+    pub default: Option<Pos<Box<AST>>>,
+    /// Whether the element collects the remaining elements or fields. Only the last element of
+    /// a pattern can be a rest element.
+    pub rest: bool,
+}
+
+impl Pattern {
+    /// The names bound by the pattern, in order.
+    pub fn bindings(pattern: &Pos<Pattern>) -> Vec<Pos<&Ident>> {
+        match &pattern.value {
+            Pattern::Ident(ident) => vec![Pos::new(pattern.start, pattern.end, ident)],
+            Pattern::Wildcard | Pattern::Literal(..) | Pattern::Type(..) => vec![],
+            Pattern::Array(elements) | Pattern::Object(elements) => elements
+                .iter()
+                .flat_map(|element| Pattern::bindings(&element.value.pattern))
+                .collect(),
+            Pattern::Or(alternatives) => alternatives.iter().flat_map(Pattern::bindings).collect(),
+        }
+    }
+
+    /// Returns an error for a rest element which is not the last element of its pattern.
+    pub(crate) fn check_rest(pattern: &Pos<Pattern>) -> Result<(), Pos<Severity>> {
+        let (elements, close) = match &pattern.value {
+            Pattern::Array(elements) => (elements, TokenValue::BracketClose),
+            Pattern::Object(elements) => (elements, TokenValue::BraceClose),
+            Pattern::Or(alternatives) => {
+                return alternatives.iter().try_for_each(Pattern::check_rest)
+            }
+            _ => return Ok(()),
+        };
+        if let Some(idx) = elements.iter().position(|element| element.value.rest) {
+            if let Some(next) = elements.get(idx + 1) {
+                let rest = &elements[idx];
+                return Err(Pos {
+                    start: next.start,
+                    end: next.end,
+                    value: Error::unexpected(&next.value.first_token(), &close).fatal(),
+                })
+                .context(
+                    rest.start,
+                    rest.end,
+                    "a rest element must be the last one",
+                );
+            }
+        }
+        elements
+            .iter()
+            .try_for_each(|element| Pattern::check_rest(&element.value.pattern))
+    }
+
+    fn first_token(&self) -> TokenValue {
+        match self {
+            Pattern::Ident(Ident(name)) => TokenValue::Identifier(name.clone()),
+            Pattern::Wildcard => TokenValue::Identifier("_".to_owned()),
+            Pattern::Literal(Literal::Int(int)) if *int < 0 => TokenValue::Sub,
+            Pattern::Literal(Literal::Float(float)) if *float < 0.0 => TokenValue::Sub,
+            Pattern::Literal(Literal::Int(int)) => TokenValue::Integer(*int),
+            Pattern::Literal(Literal::Float(float)) => TokenValue::Float(*float),
+            Pattern::Literal(Literal::Bool(boolean)) => TokenValue::Boolean(*boolean),
+            Pattern::Literal(Literal::String(string)) => TokenValue::String(string.clone()),
+            Pattern::Literal(Literal::Null) => TokenValue::Identifier("null".to_owned()),
+            Pattern::Type(name) => TokenValue::Identifier(format!("${}", name)),
+            Pattern::Array(..) => TokenValue::BracketOpen,
+            Pattern::Object(..) => TokenValue::BraceOpen,
+            Pattern::Or(alternatives) => alternatives[0].value.first_token(),
+        }
+    }
+
+    /// Parses a pattern without alternatives.
+    fn parse_single(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let open = tokens.peek(pos)?;
+        match &open.value {
+            TokenValue::BracketOpen => {
+                let elements: Pos<Vec<Pos<ArrayElement>>> = parse_list(
+                    pos,
+                    tokens,
+                    TokenValue::BracketOpen,
+                    TokenValue::BracketClose,
+                    TokenValue::Comma,
+                )
+                .context(
+                    open.start,
+                    open.end,
+                    "while parsing this array pattern",
+                )?;
+                Ok(elements.map(|elements| {
+                    Pattern::Array(elements.into_iter().map(|e| e.map(|e| e.0)).collect())
+                }))
+            }
+            TokenValue::BraceOpen => {
+                let elements: Pos<Vec<Pos<FieldElement>>> = parse_list(
+                    pos,
+                    tokens,
+                    TokenValue::BraceOpen,
+                    TokenValue::BraceClose,
+                    TokenValue::Comma,
+                )
+                .context(
+                    open.start,
+                    open.end,
+                    "while parsing this object pattern",
+                )?;
+                Ok(elements.map(|elements| {
+                    Pattern::Object(elements.into_iter().map(|e| e.map(|e| e.0)).collect())
+                }))
+            }
+            TokenValue::Sub => {
+                let minus = tokens.pop_unwrap();
+                let number = tokens.pop(minus.end).into_fatal()?;
+                let literal = match number.value {
+                    TokenValue::Integer(int) => Literal::Int(-int),
+                    TokenValue::Float(float) => Literal::Float(-float),
+                    ref other => {
+                        let expected = Expected::from("integer").with("float");
+                        return Err(Pos {
+                            start: number.start,
+                            end: number.end,
+                            value: Error::unexpected(other, expected).fatal(),
+                        });
+                    }
+                };
+                Ok(Pos::new(minus.start, number.end, Pattern::Literal(literal)))
+            }
+            TokenValue::Integer(..)
+            | TokenValue::Float(..)
+            | TokenValue::Boolean(..)
+            | TokenValue::String(..) => {
+                let token = tokens.pop_unwrap();
+                let literal = match &token.value {
+                    TokenValue::Integer(int) => Literal::Int(*int),
+                    TokenValue::Float(float) => Literal::Float(*float),
+                    TokenValue::Boolean(boolean) => Literal::Bool(*boolean),
+                    TokenValue::String(string) => Literal::String(string.clone()),
+                    _ => unreachable!(),
+                };
+                Ok(Pos::new(token.start, token.end, Pattern::Literal(literal)))
+            }
+            _ => {
+                let ident = Ident::parse(pos, tokens).also_expecting(Expected::from("pattern"))?;
+                Ok(ident.map(|Ident(name)| match name.as_str() {
+                    "_" => Pattern::Wildcard,
+                    "null" => Pattern::Literal(Literal::Null),
+                    _ if name.len() > 1 && name.starts_with('$') => {
+                        Pattern::Type(name[1..].to_owned())
+                    }
+                    _ => Pattern::Ident(Ident(name)),
+                }))
+            }
+        }
+    }
+}
+
+impl PatternElement {
+    fn first_token(&self) -> TokenValue {
+        match (&self.key, self.rest) {
+            (_, true) => TokenValue::Spread,
+            (Some(key), _) => TokenValue::Identifier(key.value.0.clone()),
+            (None, _) => self.pattern.value.first_token(),
+        }
+    }
+}
+
+impl Parse for Pattern {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let first = Pattern::parse_single(pos, tokens)?;
+        let mut alternatives = vec![first];
+        while let Ok(next) = tokens.peek(alternatives.last().unwrap().end) {
+            if next.value != TokenValue::Pipe {
+                break;
+            }
+            let pipe = tokens.pop_unwrap();
+            alternatives.push(Pattern::parse_single(pipe.end, tokens).into_fatal()?);
+        }
+        if alternatives.len() == 1 {
+            return Ok(alternatives.pop().unwrap());
+        }
+        Ok(Pos {
+            start: alternatives[0].start,
+            end: alternatives.last().unwrap().end,
+            value: Pattern::Or(alternatives),
+        })
+    }
+
+    fn continuations() -> Expected {
+        Expected(vec![Expectation::Token(TokenValue::Pipe)])
+    }
+}
+
+/// Parses the `...` of a rest element and the pattern following it.
+fn parse_rest(pos: usize, tokens: &mut &[Token]) -> ParseResult<Option<Pos<Pattern>>> {
+    match tokens.peek(pos) {
+        Ok(token) if token.value == TokenValue::Spread => {
+            let spread = tokens.pop_unwrap();
+            let pattern = Pattern::parse(spread.end, tokens).into_fatal()?;
+            Ok(Pos::new(spread.start, pattern.end, Some(pattern)))
+        }
+        _ => Ok(Pos::new(pos, pos, None)),
+    }
+}
+
+fn parse_default(pos: usize, tokens: &mut &[Token]) -> ParseResult<Option<Pos<Box<AST>>>> {
+    match tokens.peek(pos) {
+        Ok(token) if token.value == TokenValue::Assign => {
+            let assign = tokens.pop_unwrap();
+            let default = AST::parse(assign.end, tokens).into_fatal()?;
+            Ok(Pos::new(
+                assign.start,
+                default.end,
+                Some(default.map(Box::new)),
+            ))
+        }
+        _ => Ok(Pos::new(pos, pos, None)),
+    }
+}
+
+struct ArrayElement(PatternElement);
+
+impl Parse for ArrayElement {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let rest = parse_rest(pos, tokens)?;
+        if let Some(pattern) = rest.value {
+            return Ok(Pos {
+                start: rest.start,
+                end: rest.end,
+                value: ArrayElement(PatternElement {
+                    key: None,
+                    pattern,
+                    default: None,
+                    rest: true,
+                }),
+            });
+        }
+        let pattern =
+            Pattern::parse(pos, tokens).also_expecting(Expected::from(&TokenValue::Spread))?;
+        let default = parse_default(pattern.end, tokens)?;
+        Ok(Pos {
+            start: pattern.start,
+            end: default.end.max(pattern.end),
+            value: ArrayElement(PatternElement {
+                key: None,
+                pattern,
+                default: default.value,
+                rest: false,
+            }),
+        })
+    }
+
+    fn continuations() -> Expected {
+        Pattern::continuations().with(&TokenValue::Assign)
+    }
+}
+
+struct FieldElement(PatternElement);
+
+impl Parse for FieldElement {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let rest = parse_rest(pos, tokens)?;
+        if let Some(pattern) = rest.value {
+            return Ok(Pos {
+                start: rest.start,
+                end: rest.end,
+                value: FieldElement(PatternElement {
+                    key: None,
+                    pattern,
+                    default: None,
+                    rest: true,
+                }),
+            });
+        }
+        let key = Ident::parse(pos, tokens).also_expecting(Expected::from(&TokenValue::Spread))?;
+        let pattern = match tokens.peek(key.end) {
+            Ok(token) if token.value == TokenValue::Colon => {
+                let colon = tokens.pop_unwrap();
+                Pattern::parse(colon.end, tokens).into_fatal()?
+            }
+            _ => key.clone().map(Pattern::Ident),
+        };
+        let default = parse_default(pattern.end, tokens)?;
+        Ok(Pos {
+            start: key.start,
+            end: default.end.max(pattern.end),
+            value: FieldElement(PatternElement {
+                key: Some(key),
+                pattern,
+                default: default.value,
+                rest: false,
+            }),
+        })
+    }
+
+    fn continuations() -> Expected {
+        Pattern::continuations()
+            .with(&TokenValue::Colon)
+            .with(&TokenValue::Assign)
+    }
+}
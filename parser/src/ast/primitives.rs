@@ -1,5 +1,5 @@
 use crate::ast::AST;
-use crate::error::{Error, ParseResult};
+use crate::error::{Error, Expected, ParseResult, ResultExt};
 use crate::impl_into_enum;
 use crate::token_ext::TokenExt;
 use crate::{Parse, Pos, Token};
@@ -20,8 +20,10 @@ impl_into_enum!(bool => AST:Bool);
 impl_into_enum!(String => AST:String);
 
 impl Parse for Primitives {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
-        let token = tokens.pop(pos)?;
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let token = tokens
+            .pop(pos)
+            .also_expecting(Expected::from("expression"))?;
         let primitive = match &token.value {
             TokenValue::Integer(int) => AST::Int(*int),
             TokenValue::Float(float) => AST::Float(*float),
@@ -31,11 +33,7 @@ impl Parse for Primitives {
                 return Err(Pos {
                     start: token.start,
                     end: token.end,
-                    value: Error::Unexpected {
-                        unexpected,
-                        expected: "integer",
-                    }
-                    .fatal(),
+                    value: Error::unexpected(unexpected, "expression").fatal(),
                 })
             }
         };
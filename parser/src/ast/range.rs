@@ -8,5 +8,7 @@ use crate::Pos;
 pub struct Range {
     pub from: Pos<Box<AST>>,
     pub to: Pos<Box<AST>>,
+    /// Whether `to` is part of the range, as in `1..=10`.
+    pub inclusive: bool,
 }
 impl_into_enum!(Range => AST:Range);
@@ -1,5 +1,5 @@
-use crate::ast::AST;
-use crate::error::ParseResult;
+use crate::ast::{collect_errors, Function, Invalid, AST};
+use crate::error::{Error, Expected, ParseError, ParseResult};
 use crate::token_ext::TokenExt;
 use crate::{Parse, Pos, Token};
 use lexer::TokenValue;
@@ -25,12 +25,28 @@ impl Statement {
             Statement::Terminated(inner) => inner,
         }
     }
+
+    pub fn inner_mut(&mut self) -> &mut AST {
+        match self {
+            Statement::Unterminated(inner) => inner,
+            Statement::Terminated(inner) => inner,
+        }
+    }
+
+    /// The function declared by this statement, if it consists of a named function like
+    /// `function fib(n) {..}`. Declared functions are defined before their block is executed.
+    pub fn declaration(&self) -> Option<&Function> {
+        match self.inner() {
+            AST::Function(function) if function.name.is_some() => Some(function),
+            _ => None,
+        }
+    }
 }
 
 pub type CodeBlock = Vec<Pos<Statement>>;
 
 impl Parse for Statement {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let mut statement: Pos<AST> = crate::ast::AtomicValues::parse(pos, tokens)?.map(AST::from);
 
         loop {
@@ -45,18 +61,58 @@ impl Parse for Statement {
                 None => {
                     return Ok(statement.map(Box::new).map(Statement::Unterminated));
                 }
-                Some(_next) => statement = AST::append(statement, tokens)?.map(Into::<AST>::into),
+                Some(next) if AST::continues_with(&next.value) => {
+                    statement = AST::append(statement, tokens)?
+                }
+                Some(next) => {
+                    let expected = Expected::from(TokenValue::Semicolon).with(AST::continuations());
+                    return Err(Pos {
+                        start: next.start,
+                        end: next.end,
+                        value: Error::unexpected(&next.value, expected).fatal(),
+                    });
+                }
             };
         }
     }
 }
 
+/// Parses statements until the tokens are exhausted.
+/// A statement which fails to parse does not abort parsing. Instead, an `Invalid` node holding the
+/// error is put in its place, and parsing resumes after the next `;` or before an unmatched `}`.
+/// If the statement is complete up to the error and only lacks its `;`, parsing resumes right at
+/// the error instead.
 impl Parse for Vec<Pos<Statement>> {
-    fn parse<'a>(mut pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(mut pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let start = pos;
         let mut statements = vec![];
         while !tokens.is_empty() {
-            let statement = Statement::parse(pos, tokens)?;
+            let mut statement_tokens = *tokens;
+            let statement = match Statement::parse(pos, &mut statement_tokens) {
+                Ok(statement) => {
+                    *tokens = statement_tokens;
+                    statement
+                }
+                Err(err) => {
+                    let err = ParseError::from(err);
+                    let first = tokens.peek_unwrap();
+                    let (last, nested) = match parse_unterminated(pos, tokens, err.start) {
+                        Some((len, statement)) => {
+                            let last = &tokens[len - 1];
+                            *tokens = &tokens[len..];
+                            (last, collect_errors(&[statement]))
+                        }
+                        None => (skip_statement(tokens), vec![]),
+                    };
+                    let invalid = AST::Invalid(Invalid(err, nested));
+                    let statement = if last.value == TokenValue::Semicolon {
+                        Statement::Terminated(Box::new(invalid))
+                    } else {
+                        Statement::Unterminated(Box::new(invalid))
+                    };
+                    Pos::new(first.start, last.end, statement)
+                }
+            };
             pos = statement.end;
             statements.push(statement);
         }
@@ -67,3 +123,48 @@ impl Parse for Vec<Pos<Statement>> {
         })
     }
 }
+
+/// Parses the tokens before the one starting at `error` as a statement, which succeeds if the
+/// statement failed to parse only because its `;` is missing. Returns the number of tokens in the
+/// statement together with it.
+fn parse_unterminated(
+    pos: usize,
+    tokens: &[Token],
+    error: usize,
+) -> Option<(usize, Pos<Statement>)> {
+    let len = tokens.iter().position(|token| token.start == error)?;
+    if len == 0 {
+        return None;
+    }
+    let mut prefix = &tokens[..len];
+    let statement = Statement::parse(pos, &mut prefix).ok()?;
+    match prefix.is_empty() {
+        true => Some((len, statement)),
+        false => None,
+    }
+}
+
+/// Skips the tokens up to and including the next `;` which is not nested within parentheses,
+/// brackets or braces, returning the last skipped token. An unmatched `}` ends the enclosing
+/// block, so skipping stops before it unless it is the first token.
+fn skip_statement<'a>(tokens: &mut &'a [Token]) -> &'a Token {
+    let mut depth = 0usize;
+    let mut token = tokens.pop_unwrap();
+    loop {
+        match token.value {
+            TokenValue::ParenthesesOpen | TokenValue::BracketOpen | TokenValue::BraceOpen => {
+                depth += 1
+            }
+            TokenValue::ParenthesesClose | TokenValue::BracketClose | TokenValue::BraceClose => {
+                depth = depth.saturating_sub(1)
+            }
+            TokenValue::Semicolon if depth == 0 => return token,
+            _ => (),
+        }
+        match tokens.first() {
+            None => return token,
+            Some(next) if next.value == TokenValue::BraceClose && depth == 0 => return token,
+            Some(_) => token = tokens.pop_unwrap(),
+        }
+    }
+}
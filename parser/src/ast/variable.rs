@@ -0,0 +1,71 @@
+use crate::ast::{Ident, AST};
+use crate::error::{Error, Expected, ParseResult, ResultExt};
+use crate::impl_into_enum;
+use crate::token_ext::TokenExt;
+use crate::{Parse, Pos, Token};
+use lexer::TokenValue;
+
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableKind {
+    Let,
+    Const,
+}
+
+/// A declaration like `let x = 1` or `const y = 2`, which defines a variable in the innermost
+/// block. Constants can't be reassigned.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub struct VariableDeclaration {
+    pub kind: Pos<VariableKind>,
+    pub name: Pos<Ident>,
+    /// The initial value, which is required for constants. Variables without one are `null`.
+    pub value: Option<Pos<Box<AST>>>,
+}
+impl_into_enum!(VariableDeclaration => AST:VariableDeclaration);
+
+impl Parse for VariableDeclaration {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
+        let keywords = Expected::from(&TokenValue::LetKeyword).with(&TokenValue::ConstKeyword);
+        let keyword = tokens
+            .pop(pos)
+            .also_expecting(keywords.clone())
+            .into_recoverable()?;
+        let kind = match keyword.value {
+            TokenValue::LetKeyword => VariableKind::Let,
+            TokenValue::ConstKeyword => VariableKind::Const,
+            ref other => {
+                return Err(Pos {
+                    start: keyword.start,
+                    end: keyword.end,
+                    value: Error::unexpected(other, keywords).recoverable(),
+                })
+            }
+        };
+        let name = Ident::parse(keyword.end, tokens).into_fatal()?;
+        let value = match tokens.peek(name.end) {
+            Ok(token) if token.value == TokenValue::Assign => Some(tokens.pop_unwrap()),
+            _ if kind == VariableKind::Const => Some(
+                tokens
+                    .pop_expect(name.end, &TokenValue::Assign)
+                    .into_fatal()
+                    .context(keyword.start, keyword.end, "constants must be initialized")?,
+            ),
+            _ => None,
+        };
+        let value = match value {
+            Some(assign) => Some(AST::parse(assign.end, tokens).into_fatal()?.map(Box::new)),
+            None => None,
+        };
+
+        Ok(Pos {
+            start: keyword.start,
+            end: value.as_ref().map_or(name.end, |value| value.end),
+            value: VariableDeclaration {
+                kind: Pos::new(keyword.start, keyword.end, kind),
+                name,
+                value,
+            },
+        })
+    }
+}
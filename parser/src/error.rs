@@ -1,38 +1,49 @@
 use lexer::{Pos, TokenValue};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
-pub type ParseResult<'a, T> = Result<Pos<T>, Pos<Severity<'a>>>;
+pub type ParseResult<T> = Result<Pos<T>, Pos<Severity>>;
 
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[derive(Debug)]
-pub enum Severity<'a> {
-    Fatal(Error<'a>),
-    Recoverable(Error<'a>),
+pub enum Severity {
+    Fatal(Error),
+    Recoverable(Error),
 }
 
-impl<'a> Severity<'a> {
-    pub fn into_inner(self) -> Error<'a> {
+impl Severity {
+    pub fn into_inner(self) -> Error {
         match self {
             Severity::Fatal(err) => err,
             Severity::Recoverable(err) => err,
         }
     }
 
-    pub fn into_fatal(self) -> Severity<'a> {
+    pub fn into_fatal(self) -> Severity {
         Severity::Fatal(self.into_inner())
     }
 
-    pub fn into_recoverable(self) -> Severity<'a> {
+    pub fn into_recoverable(self) -> Severity {
         Severity::Recoverable(self.into_inner())
     }
+
+    pub(crate) fn inner_mut(&mut self) -> &mut Error {
+        match self {
+            Severity::Fatal(err) => err,
+            Severity::Recoverable(err) => err,
+        }
+    }
 }
 
 pub trait ResultExt {
     fn into_fatal(self) -> Self;
     fn into_recoverable(self) -> Self;
+    /// Adds tokens which would also have been accepted at the position of the error.
+    fn also_expecting(self, expected: Expected) -> Self;
+    /// Attaches a note describing what was being parsed when the error occurred.
+    fn context(self, start: usize, end: usize, note: &'static str) -> Self;
 }
 
-impl<'a, T> ResultExt for Result<T, Pos<Severity<'a>>> {
+impl<T> ResultExt for Result<T, Pos<Severity>> {
     fn into_fatal(self) -> Self {
         self.map_err(|err| err.map(Severity::into_fatal))
     }
@@ -40,36 +51,258 @@ impl<'a, T> ResultExt for Result<T, Pos<Severity<'a>>> {
     fn into_recoverable(self) -> Self {
         self.map_err(|err| err.map(Severity::into_recoverable))
     }
+
+    fn also_expecting(self, expected: Expected) -> Self {
+        self.map_err(|mut err| {
+            err.value.inner_mut().expected_mut().extend(expected);
+            err
+        })
+    }
+
+    fn context(self, start: usize, end: usize, note: &'static str) -> Self {
+        self.map_err(|mut err| {
+            err.value.inner_mut().notes.push(Pos::new(start, end, note));
+            err
+        })
+    }
 }
 
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
-#[derive(Debug)]
-pub enum Error<'a> {
-    Missing(&'a str),
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    /// Notes describing what was being parsed, innermost first.
+    pub notes: Vec<Pos<&'static str>>,
+}
+
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    Missing(Expected),
     Unexpected {
-        unexpected: &'a TokenValue,
-        expected: &'a str,
+        found: TokenValue,
+        expected: Expected,
     },
 }
 
-impl<'a> Error<'a> {
-    pub fn fatal(self) -> Severity<'a> {
+impl Error {
+    pub fn missing(expected: impl Into<Expected>) -> Self {
+        Error {
+            kind: ErrorKind::Missing(expected.into()),
+            notes: vec![],
+        }
+    }
+
+    pub fn unexpected(found: &TokenValue, expected: impl Into<Expected>) -> Self {
+        Error {
+            kind: ErrorKind::Unexpected {
+                found: found.clone(),
+                expected: expected.into(),
+            },
+            notes: vec![],
+        }
+    }
+
+    pub fn fatal(self) -> Severity {
         Severity::Fatal(self)
     }
 
-    pub fn recoverable(self) -> Severity<'a> {
+    pub fn recoverable(self) -> Severity {
         Severity::Recoverable(self)
     }
+
+    pub fn expected(&self) -> &Expected {
+        match &self.kind {
+            ErrorKind::Missing(expected) => expected,
+            ErrorKind::Unexpected { expected, .. } => expected,
+        }
+    }
+
+    /// The token which was found instead of the expected ones, or `None` if the input ended.
+    pub fn found(&self) -> Option<&TokenValue> {
+        match &self.kind {
+            ErrorKind::Missing(..) => None,
+            ErrorKind::Unexpected { found, .. } => Some(found),
+        }
+    }
+
+    pub(crate) fn expected_mut(&mut self) -> &mut Expected {
+        match &mut self.kind {
+            ErrorKind::Missing(expected) => expected,
+            ErrorKind::Unexpected { expected, .. } => expected,
+        }
+    }
 }
 
-impl<'a> Display for Error<'a> {
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            ErrorKind::Missing(expected) if expected.is_empty() => {
+                write!(f, "unexpected end of input")
+            }
+            ErrorKind::Missing(expected) => {
+                write!(f, "expected {}, found end of input", expected)
+            }
+            ErrorKind::Unexpected { found, expected } => {
+                write!(f, "expected {}, found {}", expected, describe(found))
+            }
+        }
+    }
+}
+
+/// An error returned by the parser, together with the location at which it occurred.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub start: usize,
+    pub end: usize,
+    pub error: Error,
+}
+
+impl ParseError {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.error.kind
+    }
+
+    pub fn expected(&self) -> &Expected {
+        self.error.expected()
+    }
+
+    pub fn found(&self) -> Option<&TokenValue> {
+        self.error.found()
+    }
+
+    pub fn notes(&self) -> &[Pos<&'static str>] {
+        &self.error.notes
+    }
+}
+
+impl From<Pos<Severity>> for ParseError {
+    fn from(Pos { start, end, value }: Pos<Severity>) -> Self {
+        ParseError {
+            start,
+            end,
+            error: value.into_inner(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn describe(token: &TokenValue) -> String {
+    match token {
+        TokenValue::Integer(int) => format!("integer `{}`", int),
+        TokenValue::Float(float) => format!("float `{}`", float),
+        TokenValue::Boolean(boolean) => format!("`{}`", boolean),
+        TokenValue::String(string) => format!("string {:?}", string),
+        TokenValue::Identifier(ident) => format!("identifier `{}`", ident),
+        other => format!("`{}`", other.name()),
+    }
+}
+
+/// Something the parser would have accepted at a given position.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expectation {
+    /// A specific token, like `)` or `else`.
+    Token(TokenValue),
+    /// A class of tokens or a construct, like "identifier" or "expression".
+    Kind(&'static str),
+}
+
+impl Display for Expectation {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
-            Error::Missing(missing) => write!(f, "missing {}", missing),
-            Error::Unexpected {
-                unexpected,
-                expected,
-            } => write!(f, "expected {}, got {}", expected, unexpected),
+            Expectation::Token(token) => write!(f, "`{}`", token.name()),
+            Expectation::Kind(kind) => write!(f, "{}", kind),
+        }
+    }
+}
+
+/// The set of everything the parser would have accepted at a given position, in the order in
+/// which it was encountered.
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Expected(pub Vec<Expectation>);
+
+impl Expected {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, token: &TokenValue) -> bool {
+        self.0.iter().any(|e| match e {
+            Expectation::Token(expected) => expected == token,
+            Expectation::Kind(..) => false,
+        })
+    }
+
+    pub fn push(&mut self, expectation: Expectation) {
+        if !self.0.contains(&expectation) {
+            self.0.push(expectation);
+        }
+    }
+
+    pub fn with(mut self, expected: impl Into<Expected>) -> Self {
+        self.extend(expected.into());
+        self
+    }
+}
+
+impl Extend<Expectation> for Expected {
+    fn extend<T: IntoIterator<Item = Expectation>>(&mut self, iter: T) {
+        for expectation in iter {
+            self.push(expectation);
+        }
+    }
+}
+
+impl IntoIterator for Expected {
+    type Item = Expectation;
+    type IntoIter = std::vec::IntoIter<Expectation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl From<&TokenValue> for Expected {
+    fn from(token: &TokenValue) -> Self {
+        Expected(vec![Expectation::Token(token.clone())])
+    }
+}
+
+impl From<TokenValue> for Expected {
+    fn from(token: TokenValue) -> Self {
+        Expected(vec![Expectation::Token(token)])
+    }
+}
+
+impl From<&'static str> for Expected {
+    fn from(kind: &'static str) -> Self {
+        Expected(vec![Expectation::Kind(kind)])
+    }
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.0.as_slice() {
+            [] => write!(f, "nothing"),
+            [single] => write!(f, "{}", single),
+            [first, second] => write!(f, "{} or {}", first, second),
+            [init @ .., last] => {
+                write!(f, "one of ")?;
+                for expectation in init {
+                    write!(f, "{}, ", expectation)?;
+                }
+                write!(f, "or {}", last)
+            }
         }
     }
 }
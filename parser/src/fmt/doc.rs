@@ -0,0 +1,127 @@
+use crate::fmt::FormatOptions;
+
+/// A document describing the layout of formatted code, in the style of Wadler's "prettier
+/// printer". Groups are printed on a single line if they fit, otherwise their lines are broken.
+#[derive(Debug, Clone)]
+pub(crate) enum Doc {
+    Text(String),
+    /// A space, or a line break if the enclosing group doesn't fit on the line.
+    Line,
+    /// Nothing, or a line break if the enclosing group doesn't fit on the line.
+    SoftLine,
+    /// A line break which is always printed. Groups containing it never fit on a single line.
+    HardLine,
+    Indent(Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    pub fn indent(doc: Doc) -> Doc {
+        Doc::Indent(Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+}
+
+impl From<&str> for Doc {
+    fn from(text: &str) -> Self {
+        Doc::text(text)
+    }
+}
+
+impl From<Vec<Doc>> for Doc {
+    fn from(docs: Vec<Doc>) -> Self {
+        Doc::Concat(docs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Prints `doc`, breaking groups which don't fit into the configured line width.
+/// Indentation is tracked in levels, and only written once a line contains text.
+pub(crate) fn print(doc: &Doc, options: &FormatOptions) -> String {
+    let indent_unit = match options.use_tabs {
+        true => "\t".to_owned(),
+        false => " ".repeat(options.indent_width),
+    };
+    let mut out = String::new();
+    let mut column = 0;
+    let mut stack = vec![(0, Mode::Break, doc)];
+
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                if column == 0 && !text.is_empty() {
+                    out.push_str(&indent_unit.repeat(indent));
+                    column = indent * options.indent_width;
+                }
+                out.push_str(text);
+                column += text.chars().count();
+            }
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                if let Doc::Line = doc {
+                    out.push(' ');
+                    column += 1;
+                }
+            }
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                out.push('\n');
+                column = 0;
+            }
+            Doc::Indent(doc) => stack.push((indent + 1, mode, doc)),
+            Doc::Group(doc) => {
+                let column = column.max(indent * options.indent_width);
+                let remaining = options.line_width as isize - column as isize;
+                let mode = if mode == Mode::Flat || fits(doc, indent, remaining, &stack) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                stack.push((indent, mode, doc));
+            }
+            Doc::Concat(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+            }
+        }
+    }
+
+    out
+}
+
+/// Checks if `doc` fits into the `remaining` columns when printed flat, including anything
+/// following it up to the next possible line break.
+fn fits(doc: &Doc, indent: usize, mut remaining: isize, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut stack = vec![(indent, Mode::Flat, doc)];
+    let mut rest = rest.iter().rev();
+
+    while remaining >= 0 {
+        let (indent, mode, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some(&next) => next,
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line if mode == Mode::Flat => remaining -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::HardLine if mode == Mode::Flat => return false,
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Indent(doc) | Doc::Group(doc) => stack.push((indent, mode, doc)),
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+        }
+    }
+    false
+}
@@ -0,0 +1,642 @@
+//! Formats code in the canonical Ketamine style.
+
+mod doc;
+
+use crate::ast::{
+    Assignment, BinaryOperation, BinaryOperator, Break, Call, CodeBlock, Destructuring,
+    FieldAccess, ForLoop, Function, Ident, If, Index, List, Literal, Match, Object, Param,
+    Parentheses, Pattern, PatternElement, Range, Return, Spread, Statement, UnaryOperation,
+    UnaryOperator, VariableDeclaration, VariableKind, WhileLoop, AST,
+};
+use crate::error::ParseError;
+use doc::Doc;
+use lexer::{Comment, LexingError, Pos};
+
+use std::fmt::{Display, Formatter as FmtFormatter, Result as FmtResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// The number of columns per level of indentation.
+    pub indent_width: usize,
+    /// Whether to indent using tabs instead of spaces.
+    pub use_tabs: bool,
+    /// The number of columns after which lines are broken, if possible.
+    pub line_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent_width: 4,
+            use_tabs: false,
+            line_width: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatError {
+    Lex(LexingError),
+    Parse(Vec<ParseError>),
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut FmtFormatter<'_>) -> FmtResult {
+        match self {
+            FormatError::Lex(err) => write!(f, "{}", err),
+            FormatError::Parse(errors) => write!(f, "{}", errors[0]),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Formats `src`, keeping its comments. Code which can't be parsed is not formatted.
+pub fn format(src: &str, options: &FormatOptions) -> Result<String, FormatError> {
+    let (tokens, comments) = lexer::tokenize_with_comments(src).map_err(FormatError::Lex)?;
+    let code = crate::parse(&tokens).map_err(FormatError::Parse)?;
+
+    let mut formatter = Formatter {
+        src,
+        comments: &comments,
+        next_comment: 0,
+    };
+    let doc = formatter.code(&code.value, src.len());
+    let mut formatted = doc::print(&doc, options);
+    if !formatted.is_empty() {
+        formatted.push('\n');
+    }
+    Ok(formatted)
+}
+
+/// Formats parsed code, e.g. code which was created by a program. Since there is no source,
+/// blank lines and comments of the original code are lost.
+pub fn format_code(code: &[Pos<Statement>], options: &FormatOptions) -> String {
+    let mut formatter = Formatter {
+        src: "",
+        comments: &[],
+        next_comment: 0,
+    };
+    let end = code.last().map_or(0, |statement| statement.end);
+    let doc = formatter.code(code, end);
+    doc::print(&doc, options)
+}
+
+struct Formatter<'a> {
+    src: &'a str,
+    comments: &'a [Comment],
+    /// The index of the first comment which has not been printed yet.
+    next_comment: usize,
+}
+
+impl<'a> Formatter<'a> {
+    /// Takes all comments which have not been printed yet and start before `pos`.
+    fn comments_before(&mut self, pos: usize) -> &'a [Comment] {
+        let start = self.next_comment;
+        while self
+            .comments
+            .get(self.next_comment)
+            .is_some_and(|c| c.start < pos)
+        {
+            self.next_comment += 1;
+        }
+        &self.comments[start..self.next_comment]
+    }
+
+    /// Takes the next comment if it's on the same line as `pos`, and before `limit`.
+    fn trailing_comment(&mut self, pos: usize, limit: usize) -> Option<&'a Comment> {
+        let comment = self.comments.get(self.next_comment)?;
+        if comment.start < limit && !self.src[pos..comment.start].contains('\n') {
+            self.next_comment += 1;
+            Some(comment)
+        } else {
+            None
+        }
+    }
+
+    fn has_blank_line(&self, from: usize, to: usize) -> bool {
+        let text = match self.src.get(from..to) {
+            Some(text) => text,
+            None => return false,
+        };
+        let lines = text.split('\n').skip(1).collect::<Vec<_>>();
+        lines[..lines.len().saturating_sub(1)]
+            .iter()
+            .any(|line| line.trim().is_empty())
+    }
+
+    /// Starts a new line after the previous statement or comment, if any, keeping a single blank
+    /// line if the source has one before `next`.
+    fn separate(&self, docs: &mut Vec<Doc>, previous: Option<usize>, next: usize) {
+        if let Some(previous) = previous {
+            docs.push(Doc::HardLine);
+            if self.has_blank_line(previous, next) {
+                docs.push(Doc::HardLine);
+            }
+        }
+    }
+
+    /// Formats statements and the comments between them, up to `end`.
+    fn code(&mut self, code: &[Pos<Statement>], end: usize) -> Doc {
+        let mut docs = vec![];
+        let mut previous_end = None;
+
+        for (idx, statement) in code.iter().enumerate() {
+            for comment in self.comments_before(statement.start) {
+                self.separate(&mut docs, previous_end, comment.start);
+                docs.push(Doc::text(&comment.value));
+                previous_end = Some(comment.end);
+            }
+            self.separate(&mut docs, previous_end, statement.start);
+            docs.push(self.statement(statement));
+            previous_end = Some(statement.end);
+
+            // Comments within expressions are moved behind the statement.
+            for comment in self.comments_before(statement.end) {
+                docs.push(Doc::HardLine);
+                docs.push(Doc::text(&comment.value));
+                previous_end = Some(comment.end);
+            }
+            let limit = code.get(idx + 1).map_or(end, |next| next.start);
+            if let Some(comment) = self.trailing_comment(statement.end, limit) {
+                docs.push(Doc::text(format!(" {}", comment.value)));
+                previous_end = Some(comment.end);
+            }
+        }
+        for comment in self.comments_before(end) {
+            self.separate(&mut docs, previous_end, comment.start);
+            docs.push(Doc::text(&comment.value));
+            previous_end = Some(comment.end);
+        }
+
+        Doc::Concat(docs)
+    }
+
+    fn statement(&mut self, statement: &Pos<Statement>) -> Doc {
+        let ast = self.ast(statement.value.inner(), statement.start, statement.end);
+        match statement.value {
+            Statement::Terminated(..) => Doc::Concat(vec![ast, Doc::text(";")]),
+            Statement::Unterminated(..) => ast,
+        }
+    }
+
+    /// Formats a block of code enclosed in braces, which ends at `end`.
+    fn block(&mut self, code: &CodeBlock, end: usize) -> Doc {
+        let first_comment = self.next_comment;
+        let body = self.code(code, end);
+        let has_comments = self.next_comment != first_comment;
+
+        if code.is_empty() && !has_comments {
+            return Doc::text("{}");
+        }
+        let line = match code.len() > 1 || has_comments {
+            true => Doc::HardLine,
+            false => Doc::Line,
+        };
+        Doc::group(Doc::Concat(vec![
+            Doc::text("{"),
+            Doc::indent(Doc::Concat(vec![line.clone(), body])),
+            line,
+            Doc::text("}"),
+        ]))
+    }
+
+    /// Formats a comma separated list of items, which ends at `end`. If `padded`, the items are
+    /// separated from the delimiters by spaces when printed on a single line.
+    fn list<T>(
+        &mut self,
+        (open, close): (&str, &str),
+        items: &[T],
+        end: usize,
+        padded: bool,
+        mut item: impl FnMut(&mut Self, &T) -> (usize, Doc),
+    ) -> Doc {
+        let mut docs = vec![];
+        for (idx, element) in items.iter().enumerate() {
+            if idx > 0 {
+                docs.push(Doc::text(","));
+                docs.push(Doc::Line);
+            }
+            let (start, element) = item(self, element);
+            for comment in self.comments_before(start) {
+                docs.push(Doc::text(&comment.value));
+                docs.push(Doc::HardLine);
+            }
+            docs.push(element);
+        }
+        for comment in self.comments_before(end) {
+            if !docs.is_empty() {
+                docs.push(Doc::HardLine);
+            }
+            docs.push(Doc::text(&comment.value));
+            docs.push(Doc::HardLine);
+        }
+
+        if docs.is_empty() {
+            return Doc::text(format!("{}{}", open, close));
+        }
+        let line = match padded {
+            true => Doc::Line,
+            false => Doc::SoftLine,
+        };
+        Doc::group(Doc::Concat(vec![
+            Doc::text(open),
+            Doc::indent(Doc::Concat(vec![line.clone(), Doc::Concat(docs)])),
+            line,
+            Doc::text(close),
+        ]))
+    }
+
+    fn boxed(&mut self, ast: &Pos<Box<AST>>) -> Doc {
+        self.ast(&ast.value, ast.start, ast.end)
+    }
+
+    /// Formats a pattern, shortening fields like `{name: name}` to `{name}`.
+    fn pattern(&mut self, pattern: &Pos<Pattern>) -> Doc {
+        let (delimiters, elements, padded) = match &pattern.value {
+            Pattern::Ident(Ident(name)) => return Doc::text(name),
+            Pattern::Wildcard => return Doc::text("_"),
+            Pattern::Literal(literal) => {
+                return Doc::text(match literal {
+                    Literal::Int(int) => int.to_string(),
+                    Literal::Float(float) => float_literal(*float),
+                    Literal::Bool(boolean) => boolean.to_string(),
+                    Literal::String(string) => string_literal(string),
+                    Literal::Null => "null".to_string(),
+                })
+            }
+            Pattern::Type(name) => return Doc::text(format!("${}", name)),
+            Pattern::Or(alternatives) => {
+                let mut docs = vec![];
+                for (idx, alternative) in alternatives.iter().enumerate() {
+                    if idx > 0 {
+                        docs.push(Doc::text(" | "));
+                    }
+                    docs.push(self.pattern(alternative));
+                }
+                return Doc::Concat(docs);
+            }
+            Pattern::Array(elements) => (("[", "]"), elements, false),
+            Pattern::Object(elements) => (("{", "}"), elements, true),
+        };
+        self.list(
+            delimiters,
+            elements,
+            pattern.end,
+            padded,
+            |this, element| {
+                let PatternElement {
+                    key,
+                    pattern,
+                    default,
+                    rest,
+                } = &element.value;
+                let mut docs = vec![];
+                if *rest {
+                    docs.push(Doc::text("..."));
+                }
+                match (key, &pattern.value) {
+                    (Some(key), Pattern::Ident(name)) if key.value == *name => {}
+                    (Some(key), _) => docs.push(Doc::text(format!("{}: ", key.value.0))),
+                    (None, _) => {}
+                }
+                docs.push(this.pattern(pattern));
+                if let Some(default) = default {
+                    docs.push(Doc::text(" = "));
+                    docs.push(this.boxed(default));
+                }
+                (element.start, Doc::Concat(docs))
+            },
+        )
+    }
+
+    fn ast(&mut self, ast: &AST, start: usize, end: usize) -> Doc {
+        match ast {
+            AST::Ident(Ident(ident)) => Doc::text(ident),
+            AST::Int(int) => Doc::text(int.to_string()),
+            AST::Float(float) => Doc::text(float_literal(*float)),
+            AST::Bool(boolean) => Doc::text(boolean.to_string()),
+            AST::String(string) => Doc::text(string_literal(string)),
+            AST::Break(Break(value)) => self.keyword_with_value("break", value),
+            AST::Return(Return(value)) => self.keyword_with_value("return", value),
+            AST::Continue(..) => Doc::text("continue"),
+            AST::Assignment(Assignment { receiver, value }) => Doc::Concat(vec![
+                self.boxed(receiver),
+                Doc::text(" = "),
+                self.boxed(value),
+            ]),
+            AST::Destructuring(Destructuring { pattern, value }) => Doc::Concat(vec![
+                self.pattern(pattern),
+                Doc::text(" = "),
+                self.boxed(value),
+            ]),
+            AST::VariableDeclaration(VariableDeclaration { kind, name, value }) => {
+                let keyword = match kind.value {
+                    VariableKind::Let => "let",
+                    VariableKind::Const => "const",
+                };
+                let declaration = Doc::text(format!("{} {}", keyword, name.value.0));
+                match value {
+                    Some(value) => {
+                        Doc::Concat(vec![declaration, Doc::text(" = "), self.boxed(value)])
+                    }
+                    None => declaration,
+                }
+            }
+            AST::FieldAccess(FieldAccess { value, field }) => Doc::Concat(vec![
+                self.boxed(value),
+                Doc::text(format!(".{}", field.value.0)),
+            ]),
+            AST::Index(Index { value, index }) => Doc::Concat(vec![
+                self.boxed(value),
+                Doc::text("["),
+                self.boxed(index),
+                Doc::text("]"),
+            ]),
+            AST::Function(function) => {
+                let Function { name, params, body } = &**function;
+                // The parameters end before the body, which contains the rest of the comments.
+                let params_end = params.last().map_or(start, |param| param.end);
+                let params = self.list(("(", ")"), params, params_end, false, |this, param| {
+                    let Param {
+                        name,
+                        default,
+                        rest,
+                    } = &param.value;
+                    let doc = match (default, rest) {
+                        (Some(default), _) => Doc::Concat(vec![
+                            Doc::text(format!("{} = ", name.value.0)),
+                            this.boxed(default),
+                        ]),
+                        (None, true) => Doc::text(format!("...{}", name.value.0)),
+                        (None, false) => Doc::text(&name.value.0),
+                    };
+                    (param.start, doc)
+                });
+                let keyword = match name {
+                    Some(name) => format!("function {}", name.value.0),
+                    None => "function".to_owned(),
+                };
+                Doc::Concat(vec![
+                    Doc::text(keyword),
+                    params,
+                    Doc::text(" "),
+                    self.block(body, end),
+                ])
+            }
+            AST::If(If {
+                if_branch,
+                else_if_branches,
+                else_branch,
+            }) => {
+                let mut docs = vec![];
+                for (idx, branch) in std::iter::once(if_branch)
+                    .chain(else_if_branches)
+                    .enumerate()
+                {
+                    if idx > 0 {
+                        docs.push(Doc::text(" else "));
+                    }
+                    docs.push(Doc::text("if ("));
+                    docs.push(self.boxed(&branch.value.condition));
+                    docs.push(Doc::text(") "));
+                    docs.push(self.block(&branch.value.body, branch.end));
+                }
+                if let Some(else_branch) = else_branch {
+                    docs.push(Doc::text(" else "));
+                    docs.push(self.block(else_branch, end));
+                }
+                Doc::Concat(docs)
+            }
+            AST::Match(Match { value, arms }) => {
+                let value = self.boxed(value);
+                let arms = self.list(("{", "}"), arms, end, true, |this, arm| {
+                    let mut docs = vec![this.pattern(&arm.value.pattern)];
+                    if let Some(guard) = &arm.value.guard {
+                        docs.push(Doc::text(" if "));
+                        docs.push(this.boxed(guard));
+                    }
+                    docs.push(Doc::text(" => "));
+                    docs.push(this.block(&arm.value.body, arm.end));
+                    (arm.start, Doc::Concat(docs))
+                });
+                Doc::Concat(vec![Doc::text("match ("), value, Doc::text(") "), arms])
+            }
+            AST::BinaryOperation(BinaryOperation { lhs, op, rhs }) => Doc::Concat(vec![
+                self.boxed(lhs),
+                Doc::text(format!(" {} ", binary_operator(op.value))),
+                self.boxed(rhs),
+            ]),
+            AST::UnaryOperation(UnaryOperation { op, value }) => {
+                let op = match op.value {
+                    UnaryOperator::Negate => "!",
+                    UnaryOperator::Minus => "-",
+                };
+                Doc::Concat(vec![Doc::text(op), self.boxed(value)])
+            }
+            AST::Parentheses(Parentheses(inner)) => {
+                Doc::Concat(vec![Doc::text("("), self.boxed(inner), Doc::text(")")])
+            }
+            AST::Call(Call { value, args }) => {
+                let value = self.boxed(value);
+                let args = self.list(("(", ")"), args, end, false, |this, arg| {
+                    (arg.start, this.ast(&arg.value, arg.start, arg.end))
+                });
+                Doc::Concat(vec![value, args])
+            }
+            AST::Spread(Spread(value)) => Doc::Concat(vec![Doc::text("..."), self.boxed(value)]),
+            AST::List(List(elements)) => {
+                self.list(("[", "]"), elements, end, false, |this, element| {
+                    let doc = this.ast(&element.value, element.start, element.end);
+                    (element.start, doc)
+                })
+            }
+            AST::Object(Object(pairs)) => {
+                self.list(("{", "}"), pairs, end, true, |this, (key, value)| {
+                    let value = this.ast(&value.value, value.start, value.end);
+                    let doc = Doc::Concat(vec![Doc::text(format!("{}: ", key.value.0)), value]);
+                    (key.start, doc)
+                })
+            }
+            AST::Range(Range {
+                from,
+                to,
+                inclusive,
+            }) => {
+                let operator = match inclusive {
+                    true => "..=",
+                    false => "..",
+                };
+                Doc::Concat(vec![self.boxed(from), Doc::text(operator), self.boxed(to)])
+            }
+            AST::WhileLoop(WhileLoop { condition, body }) => Doc::Concat(vec![
+                Doc::text("while ("),
+                self.boxed(condition),
+                Doc::text(") "),
+                self.block(body, end),
+            ]),
+            AST::ForLoop(ForLoop {
+                binding,
+                iterator,
+                body,
+            }) => Doc::Concat(vec![
+                Doc::text("for ("),
+                self.pattern(binding),
+                Doc::text(" in "),
+                self.boxed(iterator),
+                Doc::text(") "),
+                self.block(body, end),
+            ]),
+            AST::Invalid(..) => Doc::text(self.src.get(start..end).unwrap_or("").trim()),
+        }
+    }
+
+    fn keyword_with_value(&mut self, keyword: &str, value: &Option<Pos<Box<AST>>>) -> Doc {
+        match value {
+            Some(value) => Doc::Concat(vec![Doc::text(format!("{} ", keyword)), self.boxed(value)]),
+            None => Doc::text(keyword),
+        }
+    }
+}
+
+fn binary_operator(op: BinaryOperator) -> &'static str {
+    match op {
+        BinaryOperator::Add => "+",
+        BinaryOperator::Sub => "-",
+        BinaryOperator::Mul => "*",
+        BinaryOperator::Div => "/",
+        BinaryOperator::Eq => "==",
+        BinaryOperator::NotEq => "!=",
+        BinaryOperator::GreaterThan => ">",
+        BinaryOperator::LessThan => "<",
+        BinaryOperator::GreaterEqThan => ">=",
+        BinaryOperator::LessEqThan => "<=",
+    }
+}
+
+/// Prints a float so that it is lexed as a float again, e.g. `1.0` instead of `1`.
+fn float_literal(float: f64) -> String {
+    let literal = float.to_string();
+    match literal.contains('.') {
+        true => literal,
+        false => format!("{}.0", literal),
+    }
+}
+
+fn string_literal(string: &str) -> String {
+    let mut literal = String::with_capacity(string.len() + 2);
+    literal.push('"');
+    for c in string.chars() {
+        match c {
+            '\n' => literal.push_str(r"\n"),
+            '\t' => literal.push_str(r"\t"),
+            '\r' => literal.push_str(r"\r"),
+            '\0' => literal.push_str(r"\0"),
+            '\\' => literal.push_str(r"\\"),
+            '"' => literal.push_str(r#"\""#),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format, FormatOptions};
+
+    const SRC: &str = r#"
+// computes fibonacci numbers
+fib=function(n){if(n<2){return n;}else if (n == 2) { return 1; } else{return fib(n-1)+fib(n-2)}};
+
+
+values = [1,2.5,true,"a \"quoted\"\n string",  -3, !false]; // trailing
+person={name:"Bob",  age:42,
+    // the address is unknown
+    address: null};
+for(i in 0..10){ if (i>5) {break;}; continue; }; for(j in 10..=0) {};
+while (x < (2 + 3) * 4) { x = x + 1; person.age = values[0]; };
+log(function(){}, {}, [], f(a)(b).c[d]);
+g = function (a, b=[1], ...rest) { f(a, ...b, ...rest) };
+let   x; const  y=x+1;
+[a, [b, c]=[1], ...d]=e; for({key, value: v = 0} in obj) {};
+match(v){1|-2.5=>{"a"},[x, ...xs] if x>0=>{xs},{kind:"b",n}=>{n}, $string|null=>{}, _=>{v}};
+// the end
+"#;
+
+    fn token_values(src: &str) -> Vec<lexer::TokenValue> {
+        let tokens = lexer::tokenize(src).unwrap();
+        tokens.into_iter().map(|token| token.value).collect()
+    }
+
+    fn comments(src: &str) -> Vec<String> {
+        let (_, comments) = lexer::tokenize_with_comments(src).unwrap();
+        comments.into_iter().map(|comment| comment.value).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let options_list = vec![
+            FormatOptions::default(),
+            FormatOptions {
+                indent_width: 2,
+                use_tabs: false,
+                line_width: 20,
+            },
+            FormatOptions {
+                indent_width: 8,
+                use_tabs: true,
+                line_width: 60,
+            },
+        ];
+        for options in options_list {
+            let formatted = format(SRC, &options).unwrap();
+            assert_eq!(token_values(SRC), token_values(&formatted));
+            assert_eq!(comments(SRC), comments(&formatted));
+            assert_eq!(format(&formatted, &options).unwrap(), formatted);
+        }
+    }
+
+    #[test]
+    fn format_layout() {
+        let src = "f = function(a,b){ return a+b; };\n\n\nif (x) { y } else { z; w; }";
+        let formatted = format(src, &FormatOptions::default()).unwrap();
+        assert_eq!(
+            formatted,
+            "f = function(a, b) { return a + b; };\n\nif (x) { y } else {\n    z;\n    w;\n}\n"
+        );
+        let formatted = format("function  add (a,b) {a+b}", &FormatOptions::default()).unwrap();
+        assert_eq!(formatted, "function add(a, b) { a + b }\n");
+        let formatted = format(
+            "f=function(a,b = 2,...c){g(...c)}",
+            &FormatOptions::default(),
+        );
+        assert_eq!(
+            formatted.unwrap(),
+            "f = function(a, b = 2, ...c) { g(...c) }\n"
+        );
+        let formatted = format(
+            "{name:name,age:years=0,...rest}=person",
+            &FormatOptions::default(),
+        );
+        assert_eq!(
+            formatted.unwrap(),
+            "{ name, age: years = 0, ...rest } = person\n"
+        );
+        let formatted = format("match(x){0=>{a},n if n>0=>{b}}", &FormatOptions::default());
+        assert_eq!(
+            formatted.unwrap(),
+            "match (x) { 0 => { a }, n if n > 0 => { b } }\n"
+        );
+    }
+
+    #[test]
+    fn break_long_lines() {
+        let options = FormatOptions {
+            line_width: 16,
+            ..FormatOptions::default()
+        };
+        let formatted = format("x = [alpha, beta, gamma];", &options).unwrap();
+        assert_eq!(formatted, "x = [\n    alpha,\n    beta,\n    gamma\n];\n");
+    }
+}
@@ -3,30 +3,34 @@
 
 pub mod ast;
 pub mod error;
+pub mod fmt;
 pub mod macros;
 pub mod token_ext;
 #[cfg(feature = "tree-view")]
 pub mod tree;
+pub mod visit;
 
-use crate::ast::AST;
-use crate::error::{Error, ParseResult, ResultExt, Severity};
+use crate::ast::{collect_errors, CodeBlock, AST};
+use crate::error::{
+    Error, ErrorKind, Expectation, Expected, ParseError, ParseResult, ResultExt, Severity,
+};
 use crate::token_ext::TokenExt;
 
 pub use lexer::Pos;
 use lexer::TokenValue;
 
-fn find_closing_delimiter<'a>(
+fn find_closing_delimiter(
     mut pos: usize,
-    mut tokens: &'a [Token],
+    mut tokens: &[Token],
     open: &TokenValue,
     close: &TokenValue,
     mut current_count: usize,
-) -> Result<usize, Pos<Severity<'a>>> {
+) -> Result<usize, Pos<Severity>> {
     let mut index = 0;
     loop {
         let next = &tokens
             .pop(pos)
-            .map_err(|p| p.map(|_| Error::Missing(close.name()).fatal()))?;
+            .map_err(|p| p.map(|_| Error::missing(close).fatal()))?;
         match &next.value {
             x if x == open => current_count += 1,
             x if x == close => current_count -= 1,
@@ -40,13 +44,13 @@ fn find_closing_delimiter<'a>(
     }
 }
 
-fn parse_list<'a, V>(
+fn parse_list<V>(
     mut pos: usize,
-    tokens: &mut &'a [Token],
+    tokens: &mut &[Token],
     open: TokenValue,
     close: TokenValue,
     delimiter: TokenValue,
-) -> ParseResult<'a, Vec<Pos<V>>>
+) -> ParseResult<Vec<Pos<V>>>
 where
     V: Parse,
 {
@@ -54,22 +58,39 @@ where
     pos = open_token.end;
     let mut list = vec![];
     let close_token = loop {
-        let next = tokens.peek(pos)?;
+        let next = tokens.peek(pos).also_expecting(Expected::from(&close))?;
         if next.value == close {
             tokens.pop_unwrap();
             break next;
         }
 
-        let element = V::parse(pos, tokens)?;
+        let element = V::parse(pos, tokens).map_err(|mut err| {
+            if err.start == next.start {
+                err.value
+                    .inner_mut()
+                    .expected_mut()
+                    .push(Expectation::Token(close.clone()));
+            }
+            err
+        })?;
         pos = element.end;
         list.push(element);
 
-        let next = tokens.peek(pos)?;
+        let expected = Expected::from(&delimiter)
+            .with(&close)
+            .with(V::continuations());
+        let next = tokens.peek(pos).also_expecting(expected.clone())?;
         if next.value == close {
             tokens.pop_unwrap();
             break next;
         } else if next.value == delimiter {
-            tokens.pop_unwrap();
+            pos = tokens.pop_unwrap().end;
+        } else {
+            return Err(Pos {
+                start: next.start,
+                end: next.end,
+                value: Error::unexpected(&next.value, expected).fatal(),
+            });
         }
     };
 
@@ -82,22 +103,154 @@ where
 
 pub type Token = Pos<TokenValue>;
 
+/// Parses the tokens of a whole program, failing with all encountered errors if any part of it
+/// could not be parsed.
+pub fn parse(tokens: &[Token]) -> Result<Pos<CodeBlock>, Vec<ParseError>> {
+    let (code, errors) = parse_recovering(tokens);
+    if errors.is_empty() {
+        Ok(code)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses the tokens of a whole program, recovering from errors at statement boundaries.
+/// Returns the partial program, in which every statement that failed to parse is replaced by an
+/// `Invalid` node, together with all encountered errors.
+pub fn parse_recovering(mut tokens: &[Token]) -> (Pos<CodeBlock>, Vec<ParseError>) {
+    match CodeBlock::parse(0, &mut tokens) {
+        Ok(code) => {
+            let errors = collect_errors(&code.value);
+            (code, errors)
+        }
+        Err(err) => (Pos::new(0, 0, vec![]), vec![err.into()]),
+    }
+}
+
 pub trait Parse: Sized {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self>;
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self>;
+
+    /// Tokens which may directly follow a parsed value to extend it further.
+    fn continuations() -> Expected {
+        Expected::default()
+    }
+}
+
+/// Parses a value from tokens enclosed by delimiters.
+/// `close_idx` is the index of the closing delimiter within `tokens`, and all tokens before it
+/// have to be part of the value.
+fn parse_enclosed<V>(pos: usize, tokens: &[Token], close_idx: usize) -> ParseResult<V>
+where
+    V: Parse,
+{
+    let close = &tokens[close_idx];
+    let mut inner = &tokens[..close_idx];
+    let value = V::parse(pos, &mut inner).map_err(|mut err| {
+        let error = err.value.inner_mut();
+        if let ErrorKind::Missing(expected) = &mut error.kind {
+            let expected = std::mem::take(expected);
+            error.kind = ErrorKind::Unexpected {
+                found: close.value.clone(),
+                expected,
+            };
+            err.start = close.start;
+            err.end = close.end;
+        }
+        err
+    })?;
+
+    match inner.first() {
+        None => Ok(value),
+        Some(next) => {
+            let expected = Expected::from(&close.value).with(V::continuations());
+            Err(Pos {
+                start: next.start,
+                end: next.end,
+                value: Error::unexpected(&next.value, expected).fatal(),
+            })
+        }
+    }
 }
 
 trait TryParse: Sized {
-    fn try_parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self>;
+    fn try_parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self>;
 }
 
 impl<T> TryParse for T
 where
     T: Parse,
 {
-    fn try_parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn try_parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         let mut try_tokens = *tokens;
         let parsed = Self::parse(pos, &mut try_tokens)?;
         *tokens = try_tokens;
         Ok(parsed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::Parse;
+
+    #[test]
+    fn recover_from_errors() {
+        let src = "a = = 1; b = 2; f = function() { c d; e }; g = );";
+        let tokens = lexer::tokenize(src).unwrap();
+        let (code, errors) = crate::parse_recovering(&tokens);
+        assert_eq!(code.value.len(), 4);
+        let positions = errors.iter().map(|e| e.start).collect::<Vec<_>>();
+        assert_eq!(positions, vec![4, 35, 47]);
+    }
+
+    #[test]
+    fn recover_within_and_after_blocks() {
+        let src = "if (true) { 1 + }\nx = 1;\ny = ;";
+        let tokens = lexer::tokenize(src).unwrap();
+        let (code, errors) = crate::parse_recovering(&tokens);
+        let positions = errors.iter().map(|e| e.start).collect::<Vec<_>>();
+        assert_eq!(positions, vec![15, 18, 29]);
+        assert_eq!(code.value.len(), 3);
+        assert!(matches!(
+            code.value[1].value.inner(),
+            crate::ast::AST::Assignment(..)
+        ));
+
+        let tokens = lexer::tokenize("a = 1 }; b = 2;").unwrap();
+        let (code, errors) = crate::parse_recovering(&tokens);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(code.value.len(), 3);
+    }
+
+    #[test]
+    fn report_expected_tokens() {
+        let tokens = lexer::tokenize("f(a b);").unwrap();
+        let err = crate::ast::Statement::parse(0, &mut &tokens[..])
+            .unwrap_err()
+            .value
+            .into_inner();
+        assert_eq!(
+            err.to_string(),
+            "expected one of `,`, `)`, `.`, `(`, `[`, `..`, `..=`, `=`, or an operator, found identifier `b`"
+        );
+        assert_eq!(
+            err.notes[0].value,
+            "while parsing the arguments of this call"
+        );
+
+        let tokens = lexer::tokenize("function(...a, b) {}").unwrap();
+        let err = crate::ast::Statement::parse(0, &mut &tokens[..])
+            .unwrap_err()
+            .value
+            .into_inner();
+        assert_eq!(err.to_string(), "expected `)`, found identifier `b`");
+        assert_eq!(err.notes[0].value, "a rest parameter must be the last one");
+
+        let tokens = lexer::tokenize("for ([x, ...y, z] in pairs) {}").unwrap();
+        let err = crate::ast::Statement::parse(0, &mut &tokens[..])
+            .unwrap_err()
+            .value
+            .into_inner();
+        assert_eq!(err.to_string(), "expected `]`, found identifier `z`");
+        assert_eq!(err.notes[0].value, "a rest element must be the last one");
+    }
+}
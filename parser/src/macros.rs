@@ -22,7 +22,7 @@ where
 }
 
 impl<A: Parse, B: Parse> Parse for ParseEither<A, B> {
-    fn parse<'a>(pos: usize, tokens: &mut &'a [Token]) -> ParseResult<'a, Self> {
+    fn parse(pos: usize, tokens: &mut &[Token]) -> ParseResult<Self> {
         match A::try_parse(pos, tokens) {
             Ok(a) => Ok(a.map(ParseEither::A)),
             Err(
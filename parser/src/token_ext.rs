@@ -1,25 +1,21 @@
-use crate::error::{Error, Severity};
+use crate::error::{Error, Expected, ResultExt, Severity};
 use crate::Pos;
 use lexer::TokenValue;
 
 pub trait TokenExt<'a> {
-    fn peek(&self, pos: usize) -> Result<&'a Pos<TokenValue>, Pos<Severity<'a>>>;
-    fn pop(&mut self, pos: usize) -> Result<&'a Pos<TokenValue>, Pos<Severity<'a>>>;
+    fn peek(&self, pos: usize) -> Result<&'a Pos<TokenValue>, Pos<Severity>>;
+    fn pop(&mut self, pos: usize) -> Result<&'a Pos<TokenValue>, Pos<Severity>>;
     fn pop_expect(
         &mut self,
         pos: usize,
         expect: &TokenValue,
-    ) -> Result<&'a Pos<TokenValue>, Pos<Severity<'a>>> {
-        let token = self.pop(pos)?;
+    ) -> Result<&'a Pos<TokenValue>, Pos<Severity>> {
+        let token = self.pop(pos).also_expecting(Expected::from(expect))?;
         if &token.value != expect {
             Err(Pos {
                 start: token.start,
                 end: token.end,
-                value: Error::Unexpected {
-                    unexpected: &token.value,
-                    expected: expect.name(),
-                }
-                .fatal(),
+                value: Error::unexpected(&token.value, expect).fatal(),
             })
         } else {
             Ok(token)
@@ -34,12 +30,12 @@ pub trait TokenExt<'a> {
 }
 
 impl<'a> TokenExt<'a> for &'a [Pos<TokenValue>] {
-    fn peek(&self, pos: usize) -> Result<&'a Pos<TokenValue>, Pos<Severity<'a>>> {
+    fn peek(&self, pos: usize) -> Result<&'a Pos<TokenValue>, Pos<Severity>> {
         if self.is_empty() {
             Err(Pos {
                 start: pos,
                 end: pos + 1,
-                value: Error::Missing("token").fatal(),
+                value: Error::missing(Expected::default()).fatal(),
             })
         } else {
             let token = &self[0];
@@ -47,7 +43,7 @@ impl<'a> TokenExt<'a> for &'a [Pos<TokenValue>] {
         }
     }
 
-    fn pop(&mut self, pos: usize) -> Result<&'a Pos<TokenValue>, Pos<Severity<'a>>> {
+    fn pop(&mut self, pos: usize) -> Result<&'a Pos<TokenValue>, Pos<Severity>> {
         let token = self.peek(pos)?;
         *self = &self[1..];
         Ok(token)
@@ -1,9 +1,12 @@
 use crate::ast::{
-    Assignment, BinaryOperation, Break, Call, Continue, FieldAccess, ForLoop, Function, Ident, If,
-    IfBranch, Index, List, Object, Parentheses, Range, Return, Statement, UnaryOperation,
-    WhileLoop, AST,
+    Assignment, BinaryOperation, Break, Call, Continue, Destructuring, FieldAccess, ForLoop,
+    Function, Ident, If, IfBranch, Index, Invalid, List, Literal, Match, MatchArm, Object, Param,
+    Parentheses, Pattern, PatternElement, Range, Return, Spread, Statement, UnaryOperation,
+    VariableDeclaration, VariableKind, WhileLoop, AST,
 };
+use lexer::Pos;
 use ptree::TreeBuilder;
+use std::io::{Result as IoResult, Write};
 use std::ops::Deref;
 
 pub trait TreeDisplay {
@@ -12,7 +15,7 @@ pub trait TreeDisplay {
 
 impl TreeDisplay for Ident {
     fn display(&self, tree: &mut TreeBuilder) {
-        tree.add_empty_child(format!("identifier: {}", &self.0));
+        tree.add_empty_child(format!("identifier: {}", self.0));
     }
 }
 
@@ -54,6 +57,102 @@ impl TreeDisplay for Assignment {
     }
 }
 
+impl TreeDisplay for Destructuring {
+    fn display(&self, tree: &mut TreeBuilder) {
+        tree.begin_child("destructuring".to_owned());
+        tree.begin_child("pattern".to_owned());
+        self.pattern.value.display(tree);
+        tree.end_child();
+        tree.begin_child("value".to_owned());
+        self.value.value.display(tree);
+        tree.end_child();
+        tree.end_child();
+    }
+}
+
+impl TreeDisplay for Pattern {
+    fn display(&self, tree: &mut TreeBuilder) {
+        let (name, elements) = match self {
+            Pattern::Ident(ident) => return ident.display(tree),
+            Pattern::Wildcard => {
+                tree.add_empty_child("wildcard".to_owned());
+                return;
+            }
+            Pattern::Literal(literal) => return literal.display(tree),
+            Pattern::Type(name) => {
+                tree.add_empty_child(format!("type: {}", name));
+                return;
+            }
+            Pattern::Or(alternatives) => {
+                tree.begin_child("or_pattern".to_owned());
+                for alternative in alternatives {
+                    alternative.value.display(tree);
+                }
+                tree.end_child();
+                return;
+            }
+            Pattern::Array(elements) => ("array_pattern", elements),
+            Pattern::Object(elements) => ("object_pattern", elements),
+        };
+        tree.begin_child(name.to_owned());
+        for element in elements {
+            element.value.display(tree);
+        }
+        tree.end_child();
+    }
+}
+
+impl TreeDisplay for Literal {
+    fn display(&self, tree: &mut TreeBuilder) {
+        tree.add_empty_child(match self {
+            Literal::Int(int) => format!("integer: {}", int),
+            Literal::Float(float) => format!("float: {}", float),
+            Literal::Bool(boolean) => format!("boolean: {}", boolean),
+            Literal::String(string) => format!("string: {:?}", string),
+            Literal::Null => "null".to_owned(),
+        });
+    }
+}
+
+impl TreeDisplay for PatternElement {
+    fn display(&self, tree: &mut TreeBuilder) {
+        if self.key.is_none() && !self.rest && self.default.is_none() {
+            return self.pattern.value.display(tree);
+        }
+        tree.begin_child("element".to_owned());
+        if let Some(key) = &self.key {
+            tree.add_empty_child(format!("key: {}", key.value.0));
+        }
+        self.pattern.value.display(tree);
+        if self.rest {
+            tree.add_empty_child("rest".to_owned());
+        }
+        if let Some(default) = &self.default {
+            tree.begin_child("default".to_owned());
+            default.value.display(tree);
+            tree.end_child();
+        }
+        tree.end_child();
+    }
+}
+
+impl TreeDisplay for VariableDeclaration {
+    fn display(&self, tree: &mut TreeBuilder) {
+        let keyword = match self.kind.value {
+            VariableKind::Let => "let",
+            VariableKind::Const => "const",
+        };
+        tree.begin_child(format!("variable_declaration: {}", keyword));
+        self.name.value.display(tree);
+        if let Some(value) = &self.value {
+            tree.begin_child("value".to_owned());
+            value.value.display(tree);
+            tree.end_child();
+        }
+        tree.end_child();
+    }
+}
+
 impl TreeDisplay for FieldAccess {
     fn display(&self, tree: &mut TreeBuilder) {
         tree.begin_child("field_access".to_owned());
@@ -83,6 +182,11 @@ impl TreeDisplay for Index {
 impl TreeDisplay for Function {
     fn display(&self, tree: &mut TreeBuilder) {
         tree.begin_child("function".to_owned());
+        if let Some(name) = &self.name {
+            tree.begin_child("name".to_owned());
+            name.value.display(tree);
+            tree.end_child();
+        }
         tree.begin_child("args".to_owned());
         for arg in &self.params {
             arg.value.display(tree);
@@ -97,6 +201,25 @@ impl TreeDisplay for Function {
     }
 }
 
+impl TreeDisplay for Param {
+    fn display(&self, tree: &mut TreeBuilder) {
+        if !self.rest && self.default.is_none() {
+            return self.name.value.display(tree);
+        }
+        tree.begin_child("param".to_owned());
+        self.name.value.display(tree);
+        if self.rest {
+            tree.add_empty_child("rest".to_owned());
+        }
+        if let Some(default) = &self.default {
+            tree.begin_child("default".to_owned());
+            default.value.display(tree);
+            tree.end_child();
+        }
+        tree.end_child();
+    }
+}
+
 impl TreeDisplay for IfBranch {
     fn display(&self, tree: &mut TreeBuilder) {
         tree.begin_child("condition".to_owned());
@@ -110,6 +233,39 @@ impl TreeDisplay for IfBranch {
     }
 }
 
+impl TreeDisplay for MatchArm {
+    fn display(&self, tree: &mut TreeBuilder) {
+        tree.begin_child("arm".to_owned());
+        tree.begin_child("pattern".to_owned());
+        self.pattern.value.display(tree);
+        tree.end_child();
+        if let Some(guard) = &self.guard {
+            tree.begin_child("guard".to_owned());
+            guard.value.display(tree);
+            tree.end_child();
+        }
+        tree.begin_child("body".to_owned());
+        for body_statement in &self.body {
+            body_statement.value.display(tree);
+        }
+        tree.end_child();
+        tree.end_child();
+    }
+}
+
+impl TreeDisplay for Match {
+    fn display(&self, tree: &mut TreeBuilder) {
+        tree.begin_child("match".to_owned());
+        tree.begin_child("value".to_owned());
+        self.value.value.display(tree);
+        tree.end_child();
+        for arm in &self.arms {
+            arm.value.display(tree);
+        }
+        tree.end_child();
+    }
+}
+
 impl TreeDisplay for If {
     fn display(&self, tree: &mut TreeBuilder) {
         tree.begin_child("if".to_owned());
@@ -167,6 +323,14 @@ impl TreeDisplay for Call {
     }
 }
 
+impl TreeDisplay for Spread {
+    fn display(&self, tree: &mut TreeBuilder) {
+        tree.begin_child("spread".to_owned());
+        self.0.value.display(tree);
+        tree.end_child();
+    }
+}
+
 impl TreeDisplay for List {
     fn display(&self, tree: &mut TreeBuilder) {
         tree.begin_child("list".to_owned());
@@ -192,7 +356,11 @@ impl TreeDisplay for Object {
 
 impl TreeDisplay for Range {
     fn display(&self, tree: &mut TreeBuilder) {
-        tree.begin_child("range".to_owned());
+        let name = match self.inclusive {
+            true => "inclusive_range",
+            false => "range",
+        };
+        tree.begin_child(name.to_owned());
         tree.begin_child("from".to_owned());
         self.from.value.display(tree);
         tree.end_child();
@@ -248,6 +416,12 @@ impl TreeDisplay for UnaryOperation {
     }
 }
 
+impl TreeDisplay for Invalid {
+    fn display(&self, tree: &mut TreeBuilder) {
+        tree.add_empty_child(format!("invalid: {}", self.0));
+    }
+}
+
 impl TreeDisplay for AST {
     fn display(&self, tree: &mut TreeBuilder) {
         match self {
@@ -268,19 +442,24 @@ impl TreeDisplay for AST {
             AST::Break(break_) => break_.display(tree),
             AST::Continue(cont) => cont.display(tree),
             AST::Assignment(assignment) => assignment.display(tree),
+            AST::Destructuring(destructuring) => destructuring.display(tree),
             AST::FieldAccess(access) => access.display(tree),
             AST::Index(index) => index.display(tree),
             AST::Function(function) => function.display(tree),
             AST::If(if_expr) => if_expr.display(tree),
+            AST::Match(match_expr) => match_expr.display(tree),
             AST::BinaryOperation(op) => op.display(tree),
             AST::UnaryOperation(op) => op.display(tree),
             AST::Parentheses(par) => par.display(tree),
+            AST::VariableDeclaration(declaration) => declaration.display(tree),
             AST::Call(call) => call.display(tree),
+            AST::Spread(spread) => spread.display(tree),
             AST::List(list) => list.display(tree),
             AST::Object(obj) => obj.display(tree),
             AST::Range(range) => range.display(tree),
             AST::ForLoop(for_loop) => for_loop.display(tree),
             AST::WhileLoop(while_loop) => while_loop.display(tree),
+            AST::Invalid(invalid) => invalid.display(tree),
         };
     }
 }
@@ -301,3 +480,12 @@ impl TreeDisplay for Statement {
         }
     }
 }
+
+/// Writes the syntax tree of `code` to `out`.
+pub fn write_tree(code: &[Pos<Statement>], out: impl Write) -> IoResult<()> {
+    let mut tree = TreeBuilder::new("code".to_owned());
+    for statement in code {
+        statement.value.display(&mut tree);
+    }
+    ptree::write_tree(&tree.build(), out)
+}
@@ -0,0 +1,1043 @@
+//! Traversal of the syntax tree.
+//!
+//! [`Visitor`] walks the tree by reference, [`VisitorMut`] walks it by mutable reference and
+//! [`Fold`] consumes the tree, rebuilding it from the nodes it returns.
+//!
+//! Every method of the visitors has a default implementation which calls the `walk_*` function
+//! of the same name, visiting the children of the node. An implementation only overrides the
+//! methods of the nodes it is interested in, and calls the `walk_*` function from its override
+//! if it still wants the children to be visited.
+
+use crate::ast::{
+    Assignment, BinaryOperation, Break, Call, CodeBlock, Continue, Destructuring, FieldAccess,
+    ForLoop, Function, Ident, If, IfBranch, Index, Invalid, List, Match, MatchArm, Object, Param,
+    Parentheses, Pattern, PatternElement, Range, Return, Spread, Statement, UnaryOperation,
+    VariableDeclaration, WhileLoop, AST,
+};
+use crate::Pos;
+
+use std::rc::Rc;
+
+fn boxed(node: &Pos<Box<AST>>) -> Pos<&AST> {
+    Pos::new(node.start, node.end, &*node.value)
+}
+
+fn boxed_mut(node: &mut Pos<Box<AST>>) -> Pos<&mut AST> {
+    Pos::new(node.start, node.end, &mut *node.value)
+}
+
+pub trait Visitor: Sized {
+    fn visit_code(&mut self, code: &[Pos<Statement>]) {
+        walk_code(self, code)
+    }
+
+    fn visit_statement(&mut self, statement: Pos<&Statement>) {
+        walk_statement(self, statement)
+    }
+
+    /// Dispatches to the method of the node.
+    fn visit_expr(&mut self, expr: Pos<&AST>) {
+        walk_expr(self, expr)
+    }
+
+    /// Called for names which are bound by a node, like function parameters and the names in
+    /// the pattern of a for loop.
+    fn visit_binding(&mut self, _binding: Pos<&Ident>) {}
+
+    /// Called for the patterns of destructuring assignments and for loops, and for the nested
+    /// patterns in them.
+    fn visit_pattern(&mut self, pattern: Pos<&Pattern>) {
+        walk_pattern(self, pattern)
+    }
+
+    fn visit_ident(&mut self, _ident: Pos<&Ident>) {}
+
+    fn visit_int(&mut self, _int: Pos<&i64>) {}
+
+    fn visit_float(&mut self, _float: Pos<&f64>) {}
+
+    fn visit_bool(&mut self, _bool: Pos<&bool>) {}
+
+    fn visit_string(&mut self, _string: Pos<&String>) {}
+
+    fn visit_break(&mut self, node: Pos<&Break>) {
+        walk_break(self, node)
+    }
+
+    fn visit_continue(&mut self, _node: Pos<&Continue>) {}
+
+    fn visit_return(&mut self, node: Pos<&Return>) {
+        walk_return(self, node)
+    }
+
+    fn visit_assignment(&mut self, node: Pos<&Assignment>) {
+        walk_assignment(self, node)
+    }
+
+    fn visit_destructuring(&mut self, node: Pos<&Destructuring>) {
+        walk_destructuring(self, node)
+    }
+
+    fn visit_variable_declaration(&mut self, node: Pos<&VariableDeclaration>) {
+        walk_variable_declaration(self, node)
+    }
+
+    fn visit_field_access(&mut self, node: Pos<&FieldAccess>) {
+        walk_field_access(self, node)
+    }
+
+    fn visit_index(&mut self, node: Pos<&Index>) {
+        walk_index(self, node)
+    }
+
+    fn visit_function(&mut self, node: Pos<&Function>) {
+        walk_function(self, node)
+    }
+
+    fn visit_param(&mut self, node: Pos<&Param>) {
+        walk_param(self, node)
+    }
+
+    fn visit_if(&mut self, node: Pos<&If>) {
+        walk_if(self, node)
+    }
+
+    fn visit_if_branch(&mut self, node: Pos<&IfBranch>) {
+        walk_if_branch(self, node)
+    }
+
+    fn visit_match(&mut self, node: Pos<&Match>) {
+        walk_match(self, node)
+    }
+
+    fn visit_match_arm(&mut self, node: Pos<&MatchArm>) {
+        walk_match_arm(self, node)
+    }
+
+    fn visit_binary_operation(&mut self, node: Pos<&BinaryOperation>) {
+        walk_binary_operation(self, node)
+    }
+
+    fn visit_unary_operation(&mut self, node: Pos<&UnaryOperation>) {
+        walk_unary_operation(self, node)
+    }
+
+    fn visit_parentheses(&mut self, node: Pos<&Parentheses>) {
+        walk_parentheses(self, node)
+    }
+
+    fn visit_call(&mut self, node: Pos<&Call>) {
+        walk_call(self, node)
+    }
+
+    fn visit_spread(&mut self, node: Pos<&Spread>) {
+        walk_spread(self, node)
+    }
+
+    fn visit_list(&mut self, node: Pos<&List>) {
+        walk_list(self, node)
+    }
+
+    fn visit_object(&mut self, node: Pos<&Object>) {
+        walk_object(self, node)
+    }
+
+    fn visit_range(&mut self, node: Pos<&Range>) {
+        walk_range(self, node)
+    }
+
+    fn visit_while_loop(&mut self, node: Pos<&WhileLoop>) {
+        walk_while_loop(self, node)
+    }
+
+    fn visit_for_loop(&mut self, node: Pos<&ForLoop>) {
+        walk_for_loop(self, node)
+    }
+
+    fn visit_invalid(&mut self, _node: Pos<&Invalid>) {}
+}
+
+pub fn walk_code<V: Visitor>(visitor: &mut V, code: &[Pos<Statement>]) {
+    for statement in code {
+        visitor.visit_statement(statement.as_ref());
+    }
+}
+
+pub fn walk_statement<V: Visitor>(visitor: &mut V, statement: Pos<&Statement>) {
+    visitor.visit_expr(statement.map(Statement::inner))
+}
+
+pub fn walk_expr<V: Visitor>(visitor: &mut V, expr: Pos<&AST>) {
+    let (start, end) = (expr.start, expr.end);
+    match expr.value {
+        AST::Ident(node) => visitor.visit_ident(Pos::new(start, end, node)),
+        AST::Int(node) => visitor.visit_int(Pos::new(start, end, node)),
+        AST::Float(node) => visitor.visit_float(Pos::new(start, end, node)),
+        AST::Bool(node) => visitor.visit_bool(Pos::new(start, end, node)),
+        AST::String(node) => visitor.visit_string(Pos::new(start, end, node)),
+        AST::Break(node) => visitor.visit_break(Pos::new(start, end, node)),
+        AST::Continue(node) => visitor.visit_continue(Pos::new(start, end, node)),
+        AST::Return(node) => visitor.visit_return(Pos::new(start, end, node)),
+        AST::Assignment(node) => visitor.visit_assignment(Pos::new(start, end, node)),
+        AST::Destructuring(node) => visitor.visit_destructuring(Pos::new(start, end, node)),
+        AST::VariableDeclaration(node) => {
+            visitor.visit_variable_declaration(Pos::new(start, end, node))
+        }
+        AST::FieldAccess(node) => visitor.visit_field_access(Pos::new(start, end, node)),
+        AST::Index(node) => visitor.visit_index(Pos::new(start, end, node)),
+        AST::Function(node) => visitor.visit_function(Pos::new(start, end, node)),
+        AST::If(node) => visitor.visit_if(Pos::new(start, end, node)),
+        AST::Match(node) => visitor.visit_match(Pos::new(start, end, node)),
+        AST::BinaryOperation(node) => visitor.visit_binary_operation(Pos::new(start, end, node)),
+        AST::UnaryOperation(node) => visitor.visit_unary_operation(Pos::new(start, end, node)),
+        AST::Parentheses(node) => visitor.visit_parentheses(Pos::new(start, end, node)),
+        AST::Call(node) => visitor.visit_call(Pos::new(start, end, node)),
+        AST::Spread(node) => visitor.visit_spread(Pos::new(start, end, node)),
+        AST::List(node) => visitor.visit_list(Pos::new(start, end, node)),
+        AST::Object(node) => visitor.visit_object(Pos::new(start, end, node)),
+        AST::Range(node) => visitor.visit_range(Pos::new(start, end, node)),
+        AST::WhileLoop(node) => visitor.visit_while_loop(Pos::new(start, end, node)),
+        AST::ForLoop(node) => visitor.visit_for_loop(Pos::new(start, end, node)),
+        AST::Invalid(node) => visitor.visit_invalid(Pos::new(start, end, node)),
+    }
+}
+
+pub fn walk_break<V: Visitor>(visitor: &mut V, node: Pos<&Break>) {
+    if let Some(value) = &node.value.0 {
+        visitor.visit_expr(boxed(value));
+    }
+}
+
+pub fn walk_return<V: Visitor>(visitor: &mut V, node: Pos<&Return>) {
+    if let Some(value) = &node.value.0 {
+        visitor.visit_expr(boxed(value));
+    }
+}
+
+pub fn walk_assignment<V: Visitor>(visitor: &mut V, node: Pos<&Assignment>) {
+    visitor.visit_expr(boxed(&node.value.receiver));
+    visitor.visit_expr(boxed(&node.value.value));
+}
+
+/// Visits the value before the pattern, which is evaluated first.
+pub fn walk_destructuring<V: Visitor>(visitor: &mut V, node: Pos<&Destructuring>) {
+    visitor.visit_expr(boxed(&node.value.value));
+    visitor.visit_pattern(node.value.pattern.as_ref());
+}
+
+/// Visits the value before the name, as the value can't refer to the declared variable.
+pub fn walk_variable_declaration<V: Visitor>(visitor: &mut V, node: Pos<&VariableDeclaration>) {
+    if let Some(value) = &node.value.value {
+        visitor.visit_expr(boxed(value));
+    }
+    visitor.visit_binding(node.value.name.as_ref());
+}
+
+/// Visits the value whose field is accessed. The field itself is not an expression.
+pub fn walk_field_access<V: Visitor>(visitor: &mut V, node: Pos<&FieldAccess>) {
+    visitor.visit_expr(boxed(&node.value.value));
+}
+
+pub fn walk_index<V: Visitor>(visitor: &mut V, node: Pos<&Index>) {
+    visitor.visit_expr(boxed(&node.value.value));
+    visitor.visit_expr(boxed(&node.value.index));
+}
+
+pub fn walk_function<V: Visitor>(visitor: &mut V, node: Pos<&Function>) {
+    if let Some(name) = &node.value.name {
+        visitor.visit_binding(name.as_ref());
+    }
+    for param in &node.value.params {
+        visitor.visit_param(param.as_ref());
+    }
+    visitor.visit_code(&node.value.body);
+}
+
+/// Visits the default value before the name, as the default can't refer to the parameter.
+pub fn walk_param<V: Visitor>(visitor: &mut V, node: Pos<&Param>) {
+    if let Some(default) = &node.value.default {
+        visitor.visit_expr(boxed(default));
+    }
+    visitor.visit_binding(node.value.name.as_ref());
+}
+
+pub fn walk_if<V: Visitor>(visitor: &mut V, node: Pos<&If>) {
+    visitor.visit_if_branch(node.value.if_branch.as_ref());
+    for branch in &node.value.else_if_branches {
+        visitor.visit_if_branch(branch.as_ref());
+    }
+    if let Some(else_branch) = &node.value.else_branch {
+        visitor.visit_code(else_branch);
+    }
+}
+
+pub fn walk_if_branch<V: Visitor>(visitor: &mut V, node: Pos<&IfBranch>) {
+    visitor.visit_expr(boxed(&node.value.condition));
+    visitor.visit_code(&node.value.body);
+}
+
+pub fn walk_match<V: Visitor>(visitor: &mut V, node: Pos<&Match>) {
+    visitor.visit_expr(boxed(&node.value.value));
+    for arm in &node.value.arms {
+        visitor.visit_match_arm(arm.as_ref());
+    }
+}
+
+/// Visits the pattern before the guard and the body, which can use the names it binds.
+pub fn walk_match_arm<V: Visitor>(visitor: &mut V, node: Pos<&MatchArm>) {
+    visitor.visit_pattern(node.value.pattern.as_ref());
+    if let Some(guard) = &node.value.guard {
+        visitor.visit_expr(boxed(guard));
+    }
+    visitor.visit_code(&node.value.body);
+}
+
+pub fn walk_binary_operation<V: Visitor>(visitor: &mut V, node: Pos<&BinaryOperation>) {
+    visitor.visit_expr(boxed(&node.value.lhs));
+    visitor.visit_expr(boxed(&node.value.rhs));
+}
+
+pub fn walk_unary_operation<V: Visitor>(visitor: &mut V, node: Pos<&UnaryOperation>) {
+    visitor.visit_expr(boxed(&node.value.value));
+}
+
+pub fn walk_parentheses<V: Visitor>(visitor: &mut V, node: Pos<&Parentheses>) {
+    visitor.visit_expr(boxed(&node.value.0));
+}
+
+pub fn walk_call<V: Visitor>(visitor: &mut V, node: Pos<&Call>) {
+    visitor.visit_expr(boxed(&node.value.value));
+    for arg in &node.value.args {
+        visitor.visit_expr(arg.as_ref());
+    }
+}
+
+pub fn walk_spread<V: Visitor>(visitor: &mut V, node: Pos<&Spread>) {
+    visitor.visit_expr(boxed(&node.value.0));
+}
+
+pub fn walk_list<V: Visitor>(visitor: &mut V, node: Pos<&List>) {
+    for element in &node.value.0 {
+        visitor.visit_expr(element.as_ref());
+    }
+}
+
+/// Visits the values of the object. The keys are not expressions.
+pub fn walk_object<V: Visitor>(visitor: &mut V, node: Pos<&Object>) {
+    for (_, value) in &node.value.0 {
+        visitor.visit_expr(value.as_ref());
+    }
+}
+
+pub fn walk_range<V: Visitor>(visitor: &mut V, node: Pos<&Range>) {
+    visitor.visit_expr(boxed(&node.value.from));
+    visitor.visit_expr(boxed(&node.value.to));
+}
+
+pub fn walk_while_loop<V: Visitor>(visitor: &mut V, node: Pos<&WhileLoop>) {
+    visitor.visit_expr(boxed(&node.value.condition));
+    visitor.visit_code(&node.value.body);
+}
+
+pub fn walk_for_loop<V: Visitor>(visitor: &mut V, node: Pos<&ForLoop>) {
+    visitor.visit_expr(boxed(&node.value.iterator));
+    visitor.visit_pattern(node.value.binding.as_ref());
+    visitor.visit_code(&node.value.body);
+}
+
+/// Visits the elements in order, each default before the names it binds, as the elements are
+/// bound one after another. The keys of object patterns are not bindings.
+pub fn walk_pattern<V: Visitor>(visitor: &mut V, pattern: Pos<&Pattern>) {
+    match pattern.value {
+        Pattern::Ident(ident) => visitor.visit_binding(Pos::new(pattern.start, pattern.end, ident)),
+        Pattern::Wildcard | Pattern::Literal(..) | Pattern::Type(..) => {}
+        Pattern::Array(elements) | Pattern::Object(elements) => {
+            for element in elements {
+                if let Some(default) = &element.value.default {
+                    visitor.visit_expr(boxed(default));
+                }
+                visitor.visit_pattern(element.value.pattern.as_ref());
+            }
+        }
+        Pattern::Or(alternatives) => {
+            for alternative in alternatives {
+                visitor.visit_pattern(alternative.as_ref());
+            }
+        }
+    }
+}
+
+/// Like [`Visitor`], but the nodes can be modified in place.
+pub trait VisitorMut: Sized {
+    fn visit_code_mut(&mut self, code: &mut CodeBlock) {
+        walk_code_mut(self, code)
+    }
+
+    fn visit_statement_mut(&mut self, statement: Pos<&mut Statement>) {
+        walk_statement_mut(self, statement)
+    }
+
+    /// Dispatches to the method of the node. Override it to replace whole expressions.
+    fn visit_expr_mut(&mut self, expr: Pos<&mut AST>) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_binding_mut(&mut self, _binding: Pos<&mut Ident>) {}
+
+    fn visit_pattern_mut(&mut self, pattern: Pos<&mut Pattern>) {
+        walk_pattern_mut(self, pattern)
+    }
+
+    fn visit_ident_mut(&mut self, _ident: Pos<&mut Ident>) {}
+
+    fn visit_int_mut(&mut self, _int: Pos<&mut i64>) {}
+
+    fn visit_float_mut(&mut self, _float: Pos<&mut f64>) {}
+
+    fn visit_bool_mut(&mut self, _bool: Pos<&mut bool>) {}
+
+    fn visit_string_mut(&mut self, _string: Pos<&mut String>) {}
+
+    fn visit_break_mut(&mut self, node: Pos<&mut Break>) {
+        walk_break_mut(self, node)
+    }
+
+    fn visit_continue_mut(&mut self, _node: Pos<&mut Continue>) {}
+
+    fn visit_return_mut(&mut self, node: Pos<&mut Return>) {
+        walk_return_mut(self, node)
+    }
+
+    fn visit_assignment_mut(&mut self, node: Pos<&mut Assignment>) {
+        walk_assignment_mut(self, node)
+    }
+
+    fn visit_destructuring_mut(&mut self, node: Pos<&mut Destructuring>) {
+        walk_destructuring_mut(self, node)
+    }
+
+    fn visit_variable_declaration_mut(&mut self, node: Pos<&mut VariableDeclaration>) {
+        walk_variable_declaration_mut(self, node)
+    }
+
+    fn visit_field_access_mut(&mut self, node: Pos<&mut FieldAccess>) {
+        walk_field_access_mut(self, node)
+    }
+
+    fn visit_index_mut(&mut self, node: Pos<&mut Index>) {
+        walk_index_mut(self, node)
+    }
+
+    fn visit_function_mut(&mut self, node: Pos<&mut Function>) {
+        walk_function_mut(self, node)
+    }
+
+    fn visit_param_mut(&mut self, node: Pos<&mut Param>) {
+        walk_param_mut(self, node)
+    }
+
+    fn visit_if_mut(&mut self, node: Pos<&mut If>) {
+        walk_if_mut(self, node)
+    }
+
+    fn visit_if_branch_mut(&mut self, node: Pos<&mut IfBranch>) {
+        walk_if_branch_mut(self, node)
+    }
+
+    fn visit_match_mut(&mut self, node: Pos<&mut Match>) {
+        walk_match_mut(self, node)
+    }
+
+    fn visit_match_arm_mut(&mut self, node: Pos<&mut MatchArm>) {
+        walk_match_arm_mut(self, node)
+    }
+
+    fn visit_binary_operation_mut(&mut self, node: Pos<&mut BinaryOperation>) {
+        walk_binary_operation_mut(self, node)
+    }
+
+    fn visit_unary_operation_mut(&mut self, node: Pos<&mut UnaryOperation>) {
+        walk_unary_operation_mut(self, node)
+    }
+
+    fn visit_parentheses_mut(&mut self, node: Pos<&mut Parentheses>) {
+        walk_parentheses_mut(self, node)
+    }
+
+    fn visit_call_mut(&mut self, node: Pos<&mut Call>) {
+        walk_call_mut(self, node)
+    }
+
+    fn visit_spread_mut(&mut self, node: Pos<&mut Spread>) {
+        walk_spread_mut(self, node)
+    }
+
+    fn visit_list_mut(&mut self, node: Pos<&mut List>) {
+        walk_list_mut(self, node)
+    }
+
+    fn visit_object_mut(&mut self, node: Pos<&mut Object>) {
+        walk_object_mut(self, node)
+    }
+
+    fn visit_range_mut(&mut self, node: Pos<&mut Range>) {
+        walk_range_mut(self, node)
+    }
+
+    fn visit_while_loop_mut(&mut self, node: Pos<&mut WhileLoop>) {
+        walk_while_loop_mut(self, node)
+    }
+
+    fn visit_for_loop_mut(&mut self, node: Pos<&mut ForLoop>) {
+        walk_for_loop_mut(self, node)
+    }
+
+    fn visit_invalid_mut(&mut self, _node: Pos<&mut Invalid>) {}
+}
+
+pub fn walk_code_mut<V: VisitorMut>(visitor: &mut V, code: &mut CodeBlock) {
+    for statement in code {
+        visitor.visit_statement_mut(statement.as_mut());
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut>(visitor: &mut V, statement: Pos<&mut Statement>) {
+    visitor.visit_expr_mut(statement.map(Statement::inner_mut))
+}
+
+pub fn walk_expr_mut<V: VisitorMut>(visitor: &mut V, expr: Pos<&mut AST>) {
+    let (start, end) = (expr.start, expr.end);
+    match expr.value {
+        AST::Ident(node) => visitor.visit_ident_mut(Pos::new(start, end, node)),
+        AST::Int(node) => visitor.visit_int_mut(Pos::new(start, end, node)),
+        AST::Float(node) => visitor.visit_float_mut(Pos::new(start, end, node)),
+        AST::Bool(node) => visitor.visit_bool_mut(Pos::new(start, end, node)),
+        AST::String(node) => visitor.visit_string_mut(Pos::new(start, end, node)),
+        AST::Break(node) => visitor.visit_break_mut(Pos::new(start, end, node)),
+        AST::Continue(node) => visitor.visit_continue_mut(Pos::new(start, end, node)),
+        AST::Return(node) => visitor.visit_return_mut(Pos::new(start, end, node)),
+        AST::Assignment(node) => visitor.visit_assignment_mut(Pos::new(start, end, node)),
+        AST::Destructuring(node) => visitor.visit_destructuring_mut(Pos::new(start, end, node)),
+        AST::VariableDeclaration(node) => {
+            visitor.visit_variable_declaration_mut(Pos::new(start, end, node))
+        }
+        AST::FieldAccess(node) => visitor.visit_field_access_mut(Pos::new(start, end, node)),
+        AST::Index(node) => visitor.visit_index_mut(Pos::new(start, end, node)),
+        AST::Function(node) => visitor.visit_function_mut(Pos::new(start, end, Rc::make_mut(node))),
+        AST::If(node) => visitor.visit_if_mut(Pos::new(start, end, node)),
+        AST::Match(node) => visitor.visit_match_mut(Pos::new(start, end, node)),
+        AST::BinaryOperation(node) => {
+            visitor.visit_binary_operation_mut(Pos::new(start, end, node))
+        }
+        AST::UnaryOperation(node) => visitor.visit_unary_operation_mut(Pos::new(start, end, node)),
+        AST::Parentheses(node) => visitor.visit_parentheses_mut(Pos::new(start, end, node)),
+        AST::Call(node) => visitor.visit_call_mut(Pos::new(start, end, node)),
+        AST::Spread(node) => visitor.visit_spread_mut(Pos::new(start, end, node)),
+        AST::List(node) => visitor.visit_list_mut(Pos::new(start, end, node)),
+        AST::Object(node) => visitor.visit_object_mut(Pos::new(start, end, node)),
+        AST::Range(node) => visitor.visit_range_mut(Pos::new(start, end, node)),
+        AST::WhileLoop(node) => visitor.visit_while_loop_mut(Pos::new(start, end, node)),
+        AST::ForLoop(node) => visitor.visit_for_loop_mut(Pos::new(start, end, node)),
+        AST::Invalid(node) => visitor.visit_invalid_mut(Pos::new(start, end, node)),
+    }
+}
+
+pub fn walk_break_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Break>) {
+    if let Some(value) = &mut node.value.0 {
+        visitor.visit_expr_mut(boxed_mut(value));
+    }
+}
+
+pub fn walk_return_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Return>) {
+    if let Some(value) = &mut node.value.0 {
+        visitor.visit_expr_mut(boxed_mut(value));
+    }
+}
+
+pub fn walk_assignment_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Assignment>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.receiver));
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.value));
+}
+
+pub fn walk_destructuring_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Destructuring>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.value));
+    visitor.visit_pattern_mut(node.value.pattern.as_mut());
+}
+
+pub fn walk_variable_declaration_mut<V: VisitorMut>(
+    visitor: &mut V,
+    node: Pos<&mut VariableDeclaration>,
+) {
+    if let Some(value) = &mut node.value.value {
+        visitor.visit_expr_mut(boxed_mut(value));
+    }
+    visitor.visit_binding_mut(node.value.name.as_mut());
+}
+
+pub fn walk_field_access_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut FieldAccess>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.value));
+}
+
+pub fn walk_index_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Index>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.value));
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.index));
+}
+
+pub fn walk_function_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Function>) {
+    if let Some(name) = &mut node.value.name {
+        visitor.visit_binding_mut(name.as_mut());
+    }
+    for param in &mut node.value.params {
+        visitor.visit_param_mut(param.as_mut());
+    }
+    visitor.visit_code_mut(&mut node.value.body);
+}
+
+pub fn walk_param_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Param>) {
+    if let Some(default) = &mut node.value.default {
+        visitor.visit_expr_mut(boxed_mut(default));
+    }
+    visitor.visit_binding_mut(node.value.name.as_mut());
+}
+
+pub fn walk_if_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut If>) {
+    visitor.visit_if_branch_mut(node.value.if_branch.as_mut());
+    for branch in &mut node.value.else_if_branches {
+        visitor.visit_if_branch_mut(branch.as_mut());
+    }
+    if let Some(else_branch) = &mut node.value.else_branch {
+        visitor.visit_code_mut(else_branch);
+    }
+}
+
+pub fn walk_if_branch_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut IfBranch>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.condition));
+    visitor.visit_code_mut(&mut node.value.body);
+}
+
+pub fn walk_match_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Match>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.value));
+    for arm in &mut node.value.arms {
+        visitor.visit_match_arm_mut(arm.as_mut());
+    }
+}
+
+pub fn walk_match_arm_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut MatchArm>) {
+    visitor.visit_pattern_mut(node.value.pattern.as_mut());
+    if let Some(guard) = &mut node.value.guard {
+        visitor.visit_expr_mut(boxed_mut(guard));
+    }
+    visitor.visit_code_mut(&mut node.value.body);
+}
+
+pub fn walk_binary_operation_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut BinaryOperation>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.lhs));
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.rhs));
+}
+
+pub fn walk_unary_operation_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut UnaryOperation>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.value));
+}
+
+pub fn walk_parentheses_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Parentheses>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.0));
+}
+
+pub fn walk_call_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Call>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.value));
+    for arg in &mut node.value.args {
+        visitor.visit_expr_mut(arg.as_mut());
+    }
+}
+
+pub fn walk_spread_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Spread>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.0));
+}
+
+pub fn walk_list_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut List>) {
+    for element in &mut node.value.0 {
+        visitor.visit_expr_mut(element.as_mut());
+    }
+}
+
+pub fn walk_object_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Object>) {
+    for (_, value) in &mut node.value.0 {
+        visitor.visit_expr_mut(value.as_mut());
+    }
+}
+
+pub fn walk_range_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut Range>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.from));
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.to));
+}
+
+pub fn walk_while_loop_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut WhileLoop>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.condition));
+    visitor.visit_code_mut(&mut node.value.body);
+}
+
+pub fn walk_for_loop_mut<V: VisitorMut>(visitor: &mut V, node: Pos<&mut ForLoop>) {
+    visitor.visit_expr_mut(boxed_mut(&mut node.value.iterator));
+    visitor.visit_pattern_mut(node.value.binding.as_mut());
+    visitor.visit_code_mut(&mut node.value.body);
+}
+
+pub fn walk_pattern_mut<V: VisitorMut>(visitor: &mut V, pattern: Pos<&mut Pattern>) {
+    match pattern.value {
+        Pattern::Ident(ident) => {
+            visitor.visit_binding_mut(Pos::new(pattern.start, pattern.end, ident))
+        }
+        Pattern::Wildcard | Pattern::Literal(..) | Pattern::Type(..) => {}
+        Pattern::Array(elements) | Pattern::Object(elements) => {
+            for element in elements {
+                if let Some(default) = &mut element.value.default {
+                    visitor.visit_expr_mut(boxed_mut(default));
+                }
+                visitor.visit_pattern_mut(element.value.pattern.as_mut());
+            }
+        }
+        Pattern::Or(alternatives) => {
+            for alternative in alternatives {
+                visitor.visit_pattern_mut(alternative.as_mut());
+            }
+        }
+    }
+}
+
+/// Rewrites a tree by taking it apart and building a new one from the folded nodes.
+///
+/// Unlike [`VisitorMut`], a fold can change the span of a node and replace statements, e.g. to
+/// drop code or to replace an expression by a simpler one.
+pub trait Fold: Sized {
+    fn fold_code(&mut self, code: CodeBlock) -> CodeBlock {
+        fold_code(self, code)
+    }
+
+    fn fold_statement(&mut self, statement: Pos<Statement>) -> Pos<Statement> {
+        fold_statement(self, statement)
+    }
+
+    /// Folds the children of the expression. Override it to replace expressions, calling
+    /// [`fold_expr`] first if the children should be folded as well.
+    fn fold_expr(&mut self, expr: Pos<AST>) -> Pos<AST> {
+        fold_expr(self, expr)
+    }
+}
+
+pub fn fold_code<F: Fold>(folder: &mut F, code: CodeBlock) -> CodeBlock {
+    code.into_iter()
+        .map(|statement| folder.fold_statement(statement))
+        .collect()
+}
+
+pub fn fold_statement<F: Fold>(folder: &mut F, statement: Pos<Statement>) -> Pos<Statement> {
+    let Pos { start, end, value } = statement;
+    let (inner, terminated) = match value {
+        Statement::Terminated(inner) => (inner, true),
+        Statement::Unterminated(inner) => (inner, false),
+    };
+    let inner = folder.fold_expr(Pos::new(start, end, *inner)).map(Box::new);
+    match terminated {
+        true => inner.map(Statement::Terminated),
+        false => inner.map(Statement::Unterminated),
+    }
+}
+
+fn fold_boxed<F: Fold>(folder: &mut F, node: Pos<Box<AST>>) -> Pos<Box<AST>> {
+    folder.fold_expr(node.map(|node| *node)).map(Box::new)
+}
+
+/// Folds the defaults of the pattern, which are the only expressions in it.
+fn fold_pattern<F: Fold>(folder: &mut F, pattern: Pos<Pattern>) -> Pos<Pattern> {
+    pattern.map(|pattern| match pattern {
+        Pattern::Ident(..) | Pattern::Wildcard | Pattern::Literal(..) | Pattern::Type(..) => {
+            pattern
+        }
+        Pattern::Array(elements) => Pattern::Array(fold_elements(folder, elements)),
+        Pattern::Object(elements) => Pattern::Object(fold_elements(folder, elements)),
+        Pattern::Or(alternatives) => Pattern::Or(
+            alternatives
+                .into_iter()
+                .map(|alternative| fold_pattern(folder, alternative))
+                .collect(),
+        ),
+    })
+}
+
+fn fold_elements<F: Fold>(
+    folder: &mut F,
+    elements: Vec<Pos<PatternElement>>,
+) -> Vec<Pos<PatternElement>> {
+    elements
+        .into_iter()
+        .map(|element| {
+            element.map(
+                |PatternElement {
+                     key,
+                     pattern,
+                     default,
+                     rest,
+                 }| PatternElement {
+                    key,
+                    pattern: fold_pattern(folder, pattern),
+                    default: default.map(|default| fold_boxed(folder, default)),
+                    rest,
+                },
+            )
+        })
+        .collect()
+}
+
+fn fold_branch<F: Fold>(folder: &mut F, branch: Pos<IfBranch>) -> Pos<IfBranch> {
+    branch.map(|IfBranch { condition, body }| IfBranch {
+        condition: fold_boxed(folder, condition),
+        body: folder.fold_code(body),
+    })
+}
+
+pub fn fold_expr<F: Fold>(folder: &mut F, expr: Pos<AST>) -> Pos<AST> {
+    let Pos { start, end, value } = expr;
+    let value = match value {
+        AST::Ident(..)
+        | AST::Int(..)
+        | AST::Float(..)
+        | AST::Bool(..)
+        | AST::String(..)
+        | AST::Continue(..)
+        | AST::Invalid(..) => value,
+        AST::Break(Break(value)) => AST::Break(Break(value.map(|value| fold_boxed(folder, value)))),
+        AST::Return(Return(value)) => {
+            AST::Return(Return(value.map(|value| fold_boxed(folder, value))))
+        }
+        AST::Assignment(Assignment { receiver, value }) => AST::Assignment(Assignment {
+            receiver: fold_boxed(folder, receiver),
+            value: fold_boxed(folder, value),
+        }),
+        AST::Destructuring(Destructuring { pattern, value }) => {
+            let value = fold_boxed(folder, value);
+            AST::Destructuring(Destructuring {
+                pattern: fold_pattern(folder, pattern),
+                value,
+            })
+        }
+        AST::VariableDeclaration(VariableDeclaration { kind, name, value }) => {
+            AST::VariableDeclaration(VariableDeclaration {
+                kind,
+                name,
+                value: value.map(|value| fold_boxed(folder, value)),
+            })
+        }
+        AST::FieldAccess(FieldAccess { value, field }) => AST::FieldAccess(FieldAccess {
+            value: fold_boxed(folder, value),
+            field,
+        }),
+        AST::Index(Index { value, index }) => AST::Index(Index {
+            value: fold_boxed(folder, value),
+            index: fold_boxed(folder, index),
+        }),
+        AST::Function(function) => {
+            // The function is only copied if it's shared.
+            let Function { name, params, body } =
+                Rc::try_unwrap(function).unwrap_or_else(|function| Function::clone(&function));
+            AST::from(Function {
+                name,
+                params: params
+                    .into_iter()
+                    .map(|param| {
+                        param.map(
+                            |Param {
+                                 name,
+                                 default,
+                                 rest,
+                             }| Param {
+                                name,
+                                default: default.map(|default| fold_boxed(folder, default)),
+                                rest,
+                            },
+                        )
+                    })
+                    .collect(),
+                body: folder.fold_code(body),
+            })
+        }
+        AST::If(If {
+            if_branch,
+            else_if_branches,
+            else_branch,
+        }) => AST::If(If {
+            if_branch: fold_branch(folder, if_branch),
+            else_if_branches: else_if_branches
+                .into_iter()
+                .map(|branch| fold_branch(folder, branch))
+                .collect(),
+            else_branch: else_branch.map(|code| folder.fold_code(code)),
+        }),
+        AST::Match(Match { value, arms }) => AST::Match(Match {
+            value: fold_boxed(folder, value),
+            arms: arms
+                .into_iter()
+                .map(|arm| {
+                    arm.map(
+                        |MatchArm {
+                             pattern,
+                             guard,
+                             body,
+                         }| MatchArm {
+                            pattern: fold_pattern(folder, pattern),
+                            guard: guard.map(|guard| fold_boxed(folder, guard)),
+                            body: folder.fold_code(body),
+                        },
+                    )
+                })
+                .collect(),
+        }),
+        AST::BinaryOperation(BinaryOperation { lhs, op, rhs }) => {
+            AST::BinaryOperation(BinaryOperation {
+                lhs: fold_boxed(folder, lhs),
+                op,
+                rhs: fold_boxed(folder, rhs),
+            })
+        }
+        AST::UnaryOperation(UnaryOperation { op, value }) => AST::UnaryOperation(UnaryOperation {
+            op,
+            value: fold_boxed(folder, value),
+        }),
+        AST::Parentheses(Parentheses(inner)) => {
+            AST::Parentheses(Parentheses(fold_boxed(folder, inner)))
+        }
+        AST::Call(Call { value, args }) => AST::Call(Call {
+            value: fold_boxed(folder, value),
+            args: args.into_iter().map(|arg| folder.fold_expr(arg)).collect(),
+        }),
+        AST::Spread(Spread(value)) => AST::Spread(Spread(fold_boxed(folder, value))),
+        AST::List(List(elements)) => AST::List(List(
+            elements
+                .into_iter()
+                .map(|element| folder.fold_expr(element))
+                .collect(),
+        )),
+        AST::Object(Object(pairs)) => AST::Object(Object(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (key, folder.fold_expr(value)))
+                .collect(),
+        )),
+        AST::Range(Range {
+            from,
+            to,
+            inclusive,
+        }) => AST::Range(Range {
+            from: fold_boxed(folder, from),
+            to: fold_boxed(folder, to),
+            inclusive,
+        }),
+        AST::WhileLoop(WhileLoop { condition, body }) => AST::WhileLoop(WhileLoop {
+            condition: fold_boxed(folder, condition),
+            body: folder.fold_code(body),
+        }),
+        AST::ForLoop(ForLoop {
+            binding,
+            iterator,
+            body,
+        }) => AST::ForLoop(ForLoop {
+            iterator: fold_boxed(folder, iterator),
+            binding: fold_pattern(folder, binding),
+            body: folder.fold_code(body),
+        }),
+    };
+    Pos::new(start, end, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{Assignment, BinaryOperation, BinaryOperator, Function, Ident, List, AST};
+    use crate::visit::{fold_expr, walk_function, Fold, Visitor, VisitorMut};
+    use crate::Pos;
+
+    fn parse(src: &str) -> crate::ast::CodeBlock {
+        let tokens = lexer::tokenize(src).unwrap();
+        crate::parse(&tokens).unwrap().value
+    }
+
+    #[test]
+    fn visit_identifiers() {
+        #[derive(Default)]
+        struct Names {
+            read: Vec<String>,
+            bound: Vec<String>,
+            functions: usize,
+        }
+
+        impl Visitor for Names {
+            fn visit_binding(&mut self, binding: Pos<&Ident>) {
+                self.bound.push(binding.value.0.clone());
+            }
+
+            fn visit_ident(&mut self, ident: Pos<&Ident>) {
+                self.read.push(ident.value.0.clone());
+            }
+
+            fn visit_function(&mut self, node: Pos<&Function>) {
+                self.functions += 1;
+                walk_function(self, node);
+            }
+        }
+
+        let code = parse("f = function(a) { for (x in a) { print(x, y.z); }; }; f([1, 2])");
+        let mut names = Names::default();
+        names.visit_code(&code);
+        assert_eq!(names.read, vec!["f", "a", "print", "x", "y", "f"]);
+        assert_eq!(names.bound, vec!["a", "x"]);
+        assert_eq!(names.functions, 1);
+    }
+
+    #[test]
+    fn rewrite_tree() {
+        struct Rename;
+
+        impl VisitorMut for Rename {
+            fn visit_ident_mut(&mut self, ident: Pos<&mut Ident>) {
+                ident.value.0.make_ascii_uppercase();
+            }
+        }
+
+        /// Replaces additions of two integers by their sum.
+        struct AddIntegers;
+
+        impl Fold for AddIntegers {
+            fn fold_expr(&mut self, expr: Pos<AST>) -> Pos<AST> {
+                let expr = fold_expr(self, expr);
+                let (start, end) = (expr.start, expr.end);
+                match expr.value {
+                    AST::BinaryOperation(BinaryOperation { lhs, op, rhs })
+                        if matches!(op.value, BinaryOperator::Add) =>
+                    {
+                        match (&*lhs.value, &*rhs.value) {
+                            (AST::Int(lhs), AST::Int(rhs)) => {
+                                Pos::new(start, end, AST::Int(lhs + rhs))
+                            }
+                            _ => Pos::new(start, end, BinaryOperation { lhs, op, rhs }.into()),
+                        }
+                    }
+                    value => Pos::new(start, end, value),
+                }
+            }
+        }
+
+        let mut code = parse("x = [1 + 2 + 3, y + 1]");
+        Rename.visit_code_mut(&mut code);
+        let code = AddIntegers.fold_code(code);
+        let elements = match code[0].value.inner() {
+            AST::Assignment(Assignment { receiver, value }) => {
+                assert!(matches!(&*receiver.value, AST::Ident(Ident(name)) if name == "X"));
+                match &*value.value {
+                    AST::List(List(elements)) => elements,
+                    other => panic!("expected a list, got {:?}", other),
+                }
+            }
+            other => panic!("expected an assignment, got {:?}", other),
+        };
+        assert!(matches!(elements[0].value, AST::Int(6)));
+        assert!(matches!(elements[1].value, AST::BinaryOperation(..)));
+    }
+}
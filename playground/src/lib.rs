@@ -5,12 +5,12 @@ use serde::Serialize;
 use wasm_bindgen::__rt::core::cell::RefCell;
 use wasm_bindgen::prelude::*;
 
-use interpreter::library::Library;
-use interpreter::{Interpreter, NativeFunction, Object, Value};
+use interpreter::library::{Capability, Library};
+use interpreter::{Interpreter, KetamineError, NativeFunction, Object, Span, Value};
 use lexer::{LexingError, Pos, TokenValue};
 use parser::ast::Statement;
-use parser::error::Severity;
-use parser::Parse;
+use parser::fmt::{FormatError, FormatOptions};
+use report::Renderer;
 
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
@@ -31,41 +31,82 @@ pub fn lex(src: &str) -> Result<JsValue, JsValue> {
 #[wasm_bindgen]
 pub fn parse(src: &str) -> Result<JsValue, JsValue> {
     let tokens = run_lexer(src)?;
-    let mut tokens = &tokens[..];
-    let ast = run_parser(src, &mut tokens)?;
+    let ast = run_parser(src, &tokens)?;
     Ok(JsValue::from_serde(&ast).unwrap())
 }
 
+#[wasm_bindgen]
+pub fn format(src: &str) -> Result<JsValue, JsValue> {
+    let formatted = parser::fmt::format(src, &FormatOptions::default()).map_err(|err| {
+        let errors = match err {
+            FormatError::Lex(err) => vec![KetamineError::from(err)],
+            FormatError::Parse(errors) => errors.into_iter().map(KetamineError::from).collect(),
+        };
+        let report = errors
+            .iter()
+            .map(|err| Renderer::plain().render_string(&err.diagnostic(), src, None))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let Span { start, end } = errors[0].span().unwrap_or_default();
+        JsValue::from_serde(&PlaygroundError { start, end, report }).unwrap()
+    })?;
+    Ok(JsValue::from_str(&formatted))
+}
+
 fn run_interpreter(src: &str) -> Result<(Value, String), JsValue> {
     let mut inter = Interpreter::new();
     let lib = BrowserLib::default();
-    lib.register(&mut inter);
-    interpreter::library::StandardLibrary.register(&mut inter);
+    inter.register(&lib);
+    inter.register(&interpreter::library::StandardLibrary);
     inter
         .eval(src)
-        .map_err(|Pos { start, end, value }| {
-            let report = report::report_string(src, start, end, value);
+        .map_err(|err| {
+            // Errors in code passed to `eval` can't be highlighted in the editor.
+            let Span { start, end } = err
+                .span()
+                .filter(|span| span.end <= src.len())
+                .unwrap_or_default();
+            let report = err
+                .diagnostics()
+                .iter()
+                .map(|diagnostic| {
+                    Renderer::plain().render_sources_string(diagnostic, inter.sources())
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
             PlaygroundError { start, end, report }
         })
         .map_err(|err| JsValue::from_serde(&err).unwrap())
         .map(|val| (val, lib.clone_stdout()))
 }
 
-fn run_parser(src: &str, tokens: &mut &[Pos<TokenValue>]) -> Result<Vec<Pos<Statement>>, JsValue> {
-    <Vec<Pos<Statement>>>::parse(0, tokens)
+fn run_parser(src: &str, tokens: &[Pos<TokenValue>]) -> Result<Vec<Pos<Statement>>, JsValue> {
+    parser::parse(tokens)
         .map(|code| code.value)
-        .map_err(|err| err.map(Severity::into_inner))
-        .map_err(|Pos { start, end, value }| {
-            let report = report::report_string(src, start, end, value);
-            PlaygroundError { start, end, report }
+        .map_err(|errors| {
+            let report = errors
+                .iter()
+                .map(|err| {
+                    let diagnostic = KetamineError::from(err.clone()).diagnostic();
+                    Renderer::plain().render_string(&diagnostic, src, None)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let first = &errors[0];
+            PlaygroundError {
+                start: first.start,
+                end: first.end,
+                report,
+            }
         })
         .map_err(|err| JsValue::from_serde(&err).unwrap())
 }
 
 fn run_lexer(src: &str) -> Result<Vec<Pos<TokenValue>>, JsValue> {
     lexer::tokenize(src)
-        .map_err(|LexingError(pos)| {
-            let report = report::report_string(src, pos, pos + 1, "illegal token");
+        .map_err(|err @ LexingError(pos)| {
+            let diagnostic = KetamineError::from(err).diagnostic();
+            let report = Renderer::plain().render_string(&diagnostic, src, None);
             PlaygroundError {
                 start: pos,
                 end: pos + 1,
@@ -101,6 +142,10 @@ impl Default for BrowserLib {
 }
 
 impl Library for BrowserLib {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Output]
+    }
+
     fn register(&self, inter: &mut Interpreter) {
         let out = self.out.clone();
         let print = NativeFunction::new(move |_, _, args| {
@@ -114,8 +159,6 @@ impl Library for BrowserLib {
             out.push_str("\n");
             Ok(Value::Null)
         });
-        inter
-            .scope
-            .push_var("print", Value::NativeFunction(print), false);
+        let _ = inter.set_global("print", print);
     }
 }
@@ -77,6 +77,8 @@ $(document).ready(function () {
 
     $("#run").on("click", run);
 
+    $("#format").on("click", format);
+
     $("#create-permalink").on("click", function () {
         let code = $("#code").val();
         let compressed = LZString.compressToEncodedURIComponent(code);
@@ -127,6 +129,15 @@ function run() {
 }
 
 
+function format() {
+    let code = $("#code").val();
+    try {
+        $("#code").val(wasm.format(code));
+    } catch (e) {
+        displayError(e);
+    }
+}
+
 function parse() {
     let code = $("#code").val();
     try {
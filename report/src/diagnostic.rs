@@ -0,0 +1,131 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+            Level::Help => "help",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A location in the source code which is highlighted in a diagnostic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub start: usize,
+    pub end: usize,
+    pub message: Option<String>,
+    /// Primary labels mark the cause of a diagnostic, secondary labels add context to it.
+    pub primary: bool,
+}
+
+impl Label {
+    pub fn primary(start: usize, end: usize) -> Self {
+        Label {
+            start,
+            end,
+            message: None,
+            primary: true,
+        }
+    }
+
+    pub fn secondary(start: usize, end: usize) -> Self {
+        Label {
+            primary: false,
+            ..Label::primary(start, end)
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Display) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+}
+
+/// A function call which was active when a diagnostic was raised. Frames are shown as a
+/// traceback below the source code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// The name of the called function, if known.
+    pub name: Option<String>,
+    /// The offset of the call, or `None` if the function was called by the host program.
+    pub call_site: Option<usize>,
+}
+
+impl Frame {
+    pub fn new(name: Option<String>, call_site: Option<usize>) -> Self {
+        Frame { name, call_site }
+    }
+}
+
+/// A message about the source code, like an error or a warning, which can be rendered using a
+/// [Renderer](crate::Renderer).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    /// The active calls, innermost first.
+    pub frames: Vec<Frame>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, message: impl Display) -> Self {
+        Diagnostic {
+            level,
+            code: None,
+            message: message.to_string(),
+            labels: vec![],
+            frames: vec![],
+            notes: vec![],
+            help: vec![],
+        }
+    }
+
+    pub fn error(message: impl Display) -> Self {
+        Diagnostic::new(Level::Error, message)
+    }
+
+    pub fn warning(message: impl Display) -> Self {
+        Diagnostic::new(Level::Warning, message)
+    }
+
+    pub fn with_code(mut self, code: impl Display) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_frame(mut self, frame: Frame) -> Self {
+        self.frames.push(frame);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Display) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Display) -> Self {
+        self.help.push(help.to_string());
+        self
+    }
+}
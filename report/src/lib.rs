@@ -1,28 +1,21 @@
+//! Renders diagnostics about source code, like errors and warnings, for display in a terminal.
+
+mod diagnostic;
+mod renderer;
+mod source;
+
+pub use diagnostic::*;
+pub use renderer::*;
+pub use source::*;
+
 use std::fmt::Display;
 use std::fmt::Result as FmtResult;
 use std::fmt::Write as FmtWrite;
 use std::io::Result as IoResult;
 use std::io::Write as IoWrite;
 
-macro_rules! report {
-    ($out:expr, $src:expr, $start:expr, $end:expr, $msg:expr) => {{
-        let (line_idx, char_idx) = get_line_idx($src, $start);
-        let line = $src.lines().nth(line_idx).unwrap();
-        let char_in_line = $start - char_idx;
-
-        let trimmed_line = line.trim_start();
-        let char_in_line = char_in_line - (line.len() - trimmed_line.len());
-
-        writeln!($out, "{: >3} | {}", line_idx + 1, trimmed_line)?;
-        let marker_offset = " ".repeat(char_in_line + 6);
-        writeln!(
-            $out,
-            "{}{}",
-            marker_offset,
-            "^".repeat(($end - $start).max(1).min(trimmed_line.len()))
-        )?;
-        writeln!($out, "      {}", $msg)
-    }};
+fn single_error(start: usize, end: usize, msg: impl Display) -> Diagnostic {
+    Diagnostic::error(msg).with_label(Label::primary(start, end))
 }
 
 pub fn report_io(
@@ -32,7 +25,7 @@ pub fn report_io(
     end: usize,
     msg: impl Display,
 ) -> IoResult<()> {
-    report!(out, src, start, end, msg)
+    Renderer::plain().render_io(out, &single_error(start, end, msg), src, None)
 }
 
 pub fn report_fmt(
@@ -42,23 +35,140 @@ pub fn report_fmt(
     end: usize,
     msg: impl Display,
 ) -> FmtResult {
-    report!(out, src, start, end, msg)
+    Renderer::plain().render_fmt(out, &single_error(start, end, msg), src, None)
 }
 
 pub fn report_string(src: &str, start: usize, end: usize, msg: impl Display) -> String {
-    let mut out = String::new();
-    report_fmt(&mut out, src, start, end, msg).unwrap();
-    out
+    Renderer::plain().render_string(&single_error(start, end, msg), src, None)
 }
 
-fn get_line_idx(src: &str, pos: usize) -> (usize, usize) {
-    src[..pos]
-        .chars()
-        .enumerate()
-        .filter(|(_, c)| *c == '\n')
-        .map(|(char_idx, _c)| char_idx)
-        .enumerate()
-        .map(|(linebreak_idx, char_idx)| (linebreak_idx + 1, char_idx + 1))
-        .last()
-        .unwrap_or((0, 0))
+#[cfg(test)]
+mod tests {
+    use crate::{Diagnostic, Frame, Label, Renderer, SourceMap};
+
+    #[test]
+    fn align_carets_with_tabs_and_unicode() {
+        let src = "a = 1;\n\tb = \"ü\" + true;\n";
+        let start = src.find("true").unwrap();
+        let diagnostic = Diagnostic::error("can't add boolean to string")
+            .with_code("E0202")
+            .with_label(Label::primary(start, start + 4).with_message("boolean"));
+        let expected = concat!(
+            "error[E0202]: can't add boolean to string\n",
+            " --> test.ket:2:12\n",
+            "  |\n",
+            "2 |     b = \"ü\" + true;\n",
+            "  |               ^^^^ boolean\n",
+        );
+        let rendered = Renderer::plain().render_string(&diagnostic, src, Some("test.ket"));
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn render_labels_in_multiple_files() {
+        let mut sources = SourceMap::new();
+        let lib = sources.add(Some("lib.ket".to_owned()), "f = function(x) {\n  x + 1\n};");
+        let main = sources.add(Some("main.ket".to_owned()), "y = f(true);");
+        let lib_offset = sources[lib].offset();
+        let main_offset = sources[main].offset();
+        assert_eq!(sources.lookup(main_offset + 4), Some(main));
+        assert_eq!(sources.lookup(lib_offset + 18), Some(lib));
+        assert_eq!(
+            sources.location(lib_offset + 22).unwrap().to_string(),
+            "lib.ket:2:5"
+        );
+
+        let diagnostic = Diagnostic::error("can't add integer to boolean")
+            .with_label(Label::secondary(main_offset + 4, main_offset + 11).with_message("call"))
+            .with_label(Label::primary(lib_offset + 20, lib_offset + 25));
+        let expected = concat!(
+            "error: can't add integer to boolean\n",
+            " --> lib.ket:2:3\n",
+            "  |\n",
+            "2 |   x + 1\n",
+            "  |   ^^^^^\n",
+            "  |\n",
+            " ::: main.ket:1:5\n",
+            "  |\n",
+            "1 | y = f(true);\n",
+            "  |     ------- call\n",
+        );
+        assert_eq!(
+            Renderer::plain().render_sources_string(&diagnostic, &sources),
+            expected
+        );
+    }
+
+    #[test]
+    fn keep_offsets_of_removed_files() {
+        let mut sources = SourceMap::new();
+        let first = sources.add(None, "a = 1;");
+        let second = sources.add(None, "b = 2;");
+        assert_eq!(sources.remove(second).map(|file| file.offset()), Some(7));
+        let third = sources.add(None, "c = 3;");
+        assert_eq!(sources[third].offset(), 14);
+        assert_eq!(sources.lookup(8), None);
+        assert_eq!(sources.lookup(15), Some(third));
+        assert!(sources.remove(first).is_some());
+        assert_eq!(sources.get(first), None);
+        assert_eq!(sources.files().count(), 1);
+    }
+
+    #[test]
+    fn render_traceback() {
+        let src = "f = function(n) { if (n > 0) { f(n - 1) } else { null() } };\nf(5);";
+        let recursive = src.find("f(n - 1)").unwrap();
+        let mut diagnostic = Diagnostic::error("can't call a value of type null")
+            .with_label(Label::primary(49, 55))
+            .with_note("only functions can be called");
+        for _ in 0..5 {
+            diagnostic = diagnostic.with_frame(Frame::new(Some("f".to_owned()), Some(recursive)));
+        }
+        diagnostic = diagnostic
+            .with_frame(Frame::new(Some("f".to_owned()), src.rfind("f(5)")))
+            .with_frame(Frame::new(None, None));
+        let expected = concat!(
+            "error: can't call a value of type null\n",
+            " --> 1:50\n",
+            "  |\n",
+            "1 | f = function(n) { if (n > 0) { f(n - 1) } else { null() } };\n",
+            "  |                                                  ^^^^^^\n",
+            "  |\n",
+            "  = traceback: in `f`, called at 1:32\n",
+            "               in `f`, called at 1:32\n",
+            "               in `f`, called at 1:32\n",
+            "               [the call above is repeated 2 more times]\n",
+            "               in `f`, called at 2:1\n",
+            "               in function, called by the host\n",
+            "  = note: only functions can be called\n",
+        );
+        assert_eq!(
+            Renderer::plain().render_string(&diagnostic, src, None),
+            expected
+        );
+    }
+
+    #[test]
+    fn render_multiline_labels() {
+        let src = "f = function(x) {\n  x\n};";
+        let diagnostic = Diagnostic::warning("unused function")
+            .with_label(Label::primary(16, src.len() - 1).with_message("body"))
+            .with_note("functions are values");
+        let expected = concat!(
+            "warning: unused function\n",
+            " --> 1:17\n",
+            "  |\n",
+            "1 |   f = function(x) {\n",
+            "  |  _________________^\n",
+            "2 | |   x\n",
+            "3 | | };\n",
+            "  | |_^ body\n",
+            "  |\n",
+            "  = note: functions are values\n",
+        );
+        assert_eq!(
+            Renderer::plain().render_string(&diagnostic, src, None),
+            expected
+        );
+    }
 }
@@ -0,0 +1,612 @@
+use crate::diagnostic::{Diagnostic, Frame, Label, Level};
+use crate::source::{SourceId, SourceMap};
+
+use std::collections::BTreeSet;
+use std::fmt::Result as FmtResult;
+use std::fmt::Write as FmtWrite;
+use std::io::Result as IoResult;
+use std::io::Write as IoWrite;
+use unicode_width::UnicodeWidthChar;
+
+/// Multi-line labels spanning more lines than this only show their first and last lines.
+const MAX_INNER_LINES: usize = 4;
+
+/// Frames of a traceback which are repeated more often than this in a row, like those of a
+/// recursive function, are collapsed into a single line.
+const MAX_REPEATED_FRAMES: usize = 3;
+
+type Style = Option<&'static str>;
+
+const GUTTER: Style = Some("1;34");
+const SECONDARY: Style = Some("1;34");
+const EMPHASIS: Style = Some("1");
+
+fn level_style(level: Level) -> Style {
+    match level {
+        Level::Error => Some("1;31"),
+        Level::Warning => Some("1;33"),
+        Level::Note => Some("1;32"),
+        Level::Help => Some("1;36"),
+    }
+}
+
+/// Renders [Diagnostics](Diagnostic) in a format similar to the one used by rustc.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    color: bool,
+    tab_width: usize,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer {
+            color: false,
+            tab_width: 4,
+        }
+    }
+}
+
+impl Renderer {
+    /// A renderer which doesn't emit any ANSI escape codes.
+    pub fn plain() -> Self {
+        Renderer::default()
+    }
+
+    /// A renderer which colors its output using ANSI escape codes.
+    pub fn colored() -> Self {
+        Renderer::default().with_color(true)
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    pub fn render_string(&self, diagnostic: &Diagnostic, src: &str, file: Option<&str>) -> String {
+        let mut out = String::new();
+        self.render_fmt(&mut out, diagnostic, src, file).unwrap();
+        out
+    }
+
+    pub fn render_io(
+        &self,
+        out: &mut impl IoWrite,
+        diagnostic: &Diagnostic,
+        src: &str,
+        file: Option<&str>,
+    ) -> IoResult<()> {
+        out.write_all(self.render_string(diagnostic, src, file).as_bytes())
+    }
+
+    pub fn render_fmt(
+        &self,
+        out: &mut impl FmtWrite,
+        diagnostic: &Diagnostic,
+        src: &str,
+        file: Option<&str>,
+    ) -> FmtResult {
+        let section = Section {
+            source: Source::new(src),
+            file,
+            labels: diagnostic.labels.clone(),
+        };
+        let locate = |offset| Some(section.location(offset));
+        self.render_sections(out, diagnostic, &[&section], &locate)
+    }
+
+    /// Renders a diagnostic whose labels are offsets into a [SourceMap]. Labels in different
+    /// files are shown in separate snippets, starting with the file of the primary label.
+    pub fn render_sources_string(&self, diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+        let mut out = String::new();
+        self.render_sources_fmt(&mut out, diagnostic, sources)
+            .unwrap();
+        out
+    }
+
+    pub fn render_sources_io(
+        &self,
+        out: &mut impl IoWrite,
+        diagnostic: &Diagnostic,
+        sources: &SourceMap,
+    ) -> IoResult<()> {
+        out.write_all(self.render_sources_string(diagnostic, sources).as_bytes())
+    }
+
+    pub fn render_sources_fmt(
+        &self,
+        out: &mut impl FmtWrite,
+        diagnostic: &Diagnostic,
+        sources: &SourceMap,
+    ) -> FmtResult {
+        let mut labels = diagnostic.labels.iter().collect::<Vec<_>>();
+        labels.sort_by_key(|label| !label.primary);
+
+        let mut sections: Vec<(SourceId, Section)> = vec![];
+        for label in labels {
+            let id = match sources.lookup(label.start) {
+                Some(id) => id,
+                None => continue,
+            };
+            let file = &sources[id];
+            let label = Label {
+                start: label.start - file.offset(),
+                end: label.end.saturating_sub(file.offset()),
+                ..label.clone()
+            };
+            match sections
+                .iter_mut()
+                .find(|(section_id, _)| *section_id == id)
+            {
+                Some((_, section)) => section.labels.push(label),
+                None => sections.push((
+                    id,
+                    Section {
+                        source: Source::new(file.src()),
+                        file: file.name(),
+                        labels: vec![label],
+                    },
+                )),
+            }
+        }
+        let sections = sections
+            .iter()
+            .map(|(_, section)| section)
+            .collect::<Vec<_>>();
+        let locate = |offset| {
+            sources
+                .location(offset)
+                .map(|location| location.to_string())
+        };
+        self.render_sections(out, diagnostic, &sections, &locate)
+    }
+
+    /// Renders the diagnostic, showing its labels in the given sections. `locate` describes the
+    /// location of an offset, like the call site of a frame.
+    fn render_sections(
+        &self,
+        out: &mut impl FmtWrite,
+        diagnostic: &Diagnostic,
+        sections: &[&Section],
+        locate: &dyn Fn(usize) -> Option<String>,
+    ) -> FmtResult {
+        let level = diagnostic.level;
+
+        write!(
+            out,
+            "{}",
+            self.paint(level_style(level), &level.to_string())
+        )?;
+        if let Some(code) = &diagnostic.code {
+            write!(
+                out,
+                "{}",
+                self.paint(level_style(level), &format!("[{}]", code))
+            )?;
+        }
+        writeln!(
+            out,
+            "{}",
+            self.paint(EMPHASIS, &format!(": {}", diagnostic.message))
+        )?;
+
+        let snippets = sections
+            .iter()
+            .map(|section| {
+                let labels = section
+                    .labels
+                    .iter()
+                    .map(|label| SourceLabel::new(&section.source, label))
+                    .collect::<Vec<_>>();
+                let lines = lines_to_show(&labels);
+                (section, labels, lines)
+            })
+            .collect::<Vec<_>>();
+        let gutter_width = snippets
+            .iter()
+            .filter_map(|(_, _, lines)| lines.iter().last())
+            .map(|line| digits(line + 1))
+            .max()
+            .unwrap_or(0);
+        let gutter = " ".repeat(gutter_width);
+        let has_labels = snippets.iter().any(|(_, labels, _)| !labels.is_empty());
+
+        for (idx, (section, labels, lines)) in snippets.iter().enumerate() {
+            let main = match labels
+                .iter()
+                .find(|l| l.label.primary)
+                .or_else(|| labels.first())
+            {
+                Some(main) => main,
+                None => continue,
+            };
+            let location = section.location(main.label.start);
+            // Snippets of other files are introduced like in rustc.
+            let arrow = if idx == 0 {
+                "-->"
+            } else {
+                writeln!(out, "{} {}", gutter, self.paint(GUTTER, "|"))?;
+                ":::"
+            };
+            writeln!(out, "{}{} {}", gutter, self.paint(GUTTER, arrow), location)?;
+            writeln!(out, "{} {}", gutter, self.paint(GUTTER, "|"))?;
+            self.render_snippet(out, &section.source, level, labels, lines, gutter_width)?;
+        }
+
+        let traceback = traceback(&diagnostic.frames, locate);
+        let footers = traceback
+            .iter()
+            .map(|traceback| ("traceback", traceback))
+            .chain(diagnostic.notes.iter().map(|note| ("note", note)))
+            .chain(diagnostic.help.iter().map(|help| ("help", help)));
+        for (idx, (kind, text)) in footers.enumerate() {
+            if idx == 0 && has_labels {
+                writeln!(out, "{} {}", gutter, self.paint(GUTTER, "|"))?;
+            }
+            let indent = " ".repeat(gutter_width + kind.len() + 5);
+            let text = text
+                .lines()
+                .collect::<Vec<_>>()
+                .join(&format!("\n{}", indent));
+            writeln!(
+                out,
+                "{} {} {} {}",
+                gutter,
+                self.paint(GUTTER, "="),
+                self.paint(EMPHASIS, &format!("{}:", kind)),
+                text
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn render_snippet(
+        &self,
+        out: &mut impl FmtWrite,
+        source: &Source,
+        level: Level,
+        labels: &[SourceLabel],
+        lines: &BTreeSet<usize>,
+        gutter_width: usize,
+    ) -> FmtResult {
+        let has_multiline = labels.iter().any(SourceLabel::is_multiline);
+        let empty_gutter = format!("{} {}", " ".repeat(gutter_width), self.paint(GUTTER, "|"));
+        let style_of = |label: &SourceLabel| match label.label.primary {
+            true => level_style(level),
+            false => SECONDARY,
+        };
+        // The margin left of the source code, used to draw multi-line labels.
+        let margin = |line: usize| -> Cells {
+            let mut cells = Cells::default();
+            if let Some(label) = labels
+                .iter()
+                .find(|l| l.is_multiline() && l.start_line < line && line <= l.end_line)
+            {
+                cells.put(0, "|", style_of(label));
+            }
+            if has_multiline {
+                cells.put(1, " ", None);
+            }
+            cells
+        };
+
+        let mut previous: Option<usize> = None;
+        for &line in lines {
+            if previous.is_some_and(|previous| line > previous + 1) {
+                writeln!(out, "{}", self.paint(GUTTER, "..."))?;
+            }
+            previous = Some(line);
+
+            let text = self.expand_tabs(source.line(line));
+            let mut row = margin(line);
+            row.append(&text, None);
+            let number = self.paint(GUTTER, &format!("{: >w$} |", line + 1, w = gutter_width));
+            match self.render_cells(&row).trim_end() {
+                "" => writeln!(out, "{}", number)?,
+                code => writeln!(out, "{} {}", number, code)?,
+            }
+
+            let mut singles = labels
+                .iter()
+                .filter(|l| !l.is_multiline() && l.start_line == line)
+                .map(|l| {
+                    let start = self.display_col(source, l.label.start);
+                    let end = self.display_col(source, l.label.end).max(start + 1);
+                    (l, start, end)
+                })
+                .collect::<Vec<_>>();
+            singles.sort_by_key(|(_, start, _)| *start);
+
+            let offset = margin(line).len();
+            if !singles.is_empty() {
+                let mut row = margin(line);
+                for (label, start, end) in &singles {
+                    let marker = if label.label.primary { "^" } else { "-" };
+                    row.put(offset + start, &marker.repeat(end - start), style_of(label));
+                }
+                let (last, _, end) = singles[singles.len() - 1];
+                if let Some(message) = &last.label.message {
+                    row.put(offset + end + 1, message, style_of(last));
+                }
+                writeln!(out, "{} {}", empty_gutter, self.render_cells(&row))?;
+
+                // Messages of labels which are not the rightmost one are drawn below the line,
+                // connected to their label.
+                let pending = singles[..singles.len() - 1]
+                    .iter()
+                    .filter(|(l, ..)| l.label.message.is_some())
+                    .collect::<Vec<_>>();
+                for idx in (0..pending.len()).rev() {
+                    let mut connectors = margin(line);
+                    for &&(label, start, _) in &pending[..=idx] {
+                        connectors.put(offset + start, "|", style_of(label));
+                    }
+                    writeln!(out, "{} {}", empty_gutter, self.render_cells(&connectors))?;
+
+                    let mut row = margin(line);
+                    for &&(label, start, _) in &pending[..idx] {
+                        row.put(offset + start, "|", style_of(label));
+                    }
+                    let (label, start, _) = *pending[idx];
+                    let message = label.label.message.as_deref().unwrap_or_default();
+                    row.put(offset + start, message, style_of(label));
+                    writeln!(out, "{} {}", empty_gutter, self.render_cells(&row))?;
+                }
+            }
+
+            for label in labels.iter().filter(|l| l.is_multiline()) {
+                if label.start_line == line {
+                    let col = self.display_col(source, label.label.start);
+                    let mut row = Cells::default();
+                    row.put(1, &"_".repeat(col + 1), style_of(label));
+                    row.put(col + 2, "^", style_of(label));
+                    writeln!(out, "{} {}", empty_gutter, self.render_cells(&row))?;
+                } else if label.end_line == line {
+                    let col = self.display_col(source, label.last_char());
+                    let mut row = Cells::default();
+                    row.put(0, "|", style_of(label));
+                    row.put(1, &"_".repeat(col + 1), style_of(label));
+                    row.put(col + 2, "^", style_of(label));
+                    if let Some(message) = &label.label.message {
+                        row.put(col + 4, message, style_of(label));
+                    }
+                    writeln!(out, "{} {}", empty_gutter, self.render_cells(&row))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The column at which the character at `offset` is displayed, taking tabs and wide
+    /// characters into account.
+    fn display_col(&self, source: &Source, offset: usize) -> usize {
+        let offset = source.clamp(offset);
+        let (line, _) = source.location(offset);
+        let line_start = source.starts[line];
+        let line_end = line_start + source.line(line).len();
+        let text = &source.src[line_start..offset.min(line_end)];
+        text.chars().fold(0, |col, c| self.advance(col, c))
+    }
+
+    fn advance(&self, col: usize, c: char) -> usize {
+        match c {
+            '\t' => (col / self.tab_width + 1) * self.tab_width,
+            c => col + c.width().unwrap_or(0),
+        }
+    }
+
+    fn expand_tabs(&self, line: &str) -> String {
+        let mut col = 0;
+        let mut out = String::with_capacity(line.len());
+        for c in line.chars() {
+            let next = self.advance(col, c);
+            match c {
+                '\t' => out.push_str(&" ".repeat(next - col)),
+                c if c.is_control() => {}
+                c => out.push(c),
+            }
+            col = next;
+        }
+        out
+    }
+
+    fn paint(&self, style: Style, text: &str) -> String {
+        match style {
+            Some(code) if self.color && !text.is_empty() => {
+                format!("\x1b[{}m{}\x1b[0m", code, text)
+            }
+            _ => text.to_owned(),
+        }
+    }
+
+    fn render_cells(&self, cells: &Cells) -> String {
+        let mut out = String::new();
+        let mut run = String::new();
+        let mut run_style = None;
+        for &(c, style) in &cells.0 {
+            if style != run_style {
+                out.push_str(&self.paint(run_style, &run));
+                run.clear();
+                run_style = style;
+            }
+            run.push(c);
+        }
+        out.push_str(&self.paint(run_style, &run));
+        out
+    }
+}
+
+/// A row of styled characters which can be written to at arbitrary columns.
+#[derive(Default)]
+struct Cells(Vec<(char, Style)>);
+
+impl Cells {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn put(&mut self, col: usize, text: &str, style: Style) {
+        for (idx, c) in text.chars().enumerate() {
+            let col = col + idx;
+            if self.0.len() <= col {
+                self.0.resize(col + 1, (' ', None));
+            }
+            self.0[col] = (c, style);
+        }
+    }
+
+    fn append(&mut self, text: &str, style: Style) {
+        self.put(self.len(), text, style)
+    }
+}
+
+/// The labels of a diagnostic which point into the same source code.
+struct Section<'a> {
+    source: Source<'a>,
+    file: Option<&'a str>,
+    labels: Vec<Label>,
+}
+
+impl Section<'_> {
+    /// The file, line and column of `offset`.
+    fn location(&self, offset: usize) -> String {
+        let (line, col) = self.source.location(offset);
+        match self.file {
+            Some(file) => format!("{}:{}:{}", file, line + 1, col + 1),
+            None => format!("{}:{}", line + 1, col + 1),
+        }
+    }
+}
+
+/// Describes the frames of a traceback, one per line.
+fn traceback(frames: &[Frame], locate: &dyn Fn(usize) -> Option<String>) -> Option<String> {
+    if frames.is_empty() {
+        return None;
+    }
+    let describe = |frame: &Frame| {
+        let function = match &frame.name {
+            Some(name) => format!("`{}`", name),
+            None => "function".to_owned(),
+        };
+        match frame.call_site.map(locate) {
+            Some(Some(location)) => format!("in {}, called at {}", function, location),
+            Some(None) => format!("in {}", function),
+            None => format!("in {}, called by the host", function),
+        }
+    };
+
+    let mut lines = vec![];
+    let mut idx = 0;
+    while idx < frames.len() {
+        let repeated = frames[idx..]
+            .iter()
+            .take_while(|frame| **frame == frames[idx])
+            .count();
+        let shown = repeated.min(MAX_REPEATED_FRAMES);
+        lines.extend(std::iter::repeat_n(describe(&frames[idx]), shown));
+        if repeated > shown {
+            lines.push(format!(
+                "[the call above is repeated {} more times]",
+                repeated - shown
+            ));
+        }
+        idx += repeated;
+    }
+    Some(lines.join("\n"))
+}
+
+/// The source code, split into lines.
+struct Source<'a> {
+    src: &'a str,
+    /// The byte offset at which each line starts.
+    starts: Vec<usize>,
+}
+
+impl<'a> Source<'a> {
+    fn new(src: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Source { src, starts }
+    }
+
+    /// Moves `offset` into the source code and onto a character boundary.
+    fn clamp(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.src.len());
+        while !self.src.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+
+    /// The zero-based line and column (in characters) of `offset`.
+    fn location(&self, offset: usize) -> (usize, usize) {
+        let offset = self.clamp(offset);
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let col = self.src[self.starts[line]..offset].chars().count();
+        (line, col)
+    }
+
+    fn line(&self, line: usize) -> &'a str {
+        let start = self.starts[line];
+        let end = self.starts.get(line + 1).copied().unwrap_or(self.src.len());
+        self.src[start..end].trim_end_matches(&['\n', '\r'][..])
+    }
+}
+
+struct SourceLabel<'a> {
+    label: &'a Label,
+    start_line: usize,
+    end_line: usize,
+}
+
+impl<'a> SourceLabel<'a> {
+    fn new(source: &Source, label: &'a Label) -> Self {
+        let (start_line, _) = source.location(label.start);
+        let last_char = if label.end > label.start {
+            label.end - 1
+        } else {
+            label.start
+        };
+        let (end_line, _) = source.location(last_char);
+        SourceLabel {
+            label,
+            start_line,
+            end_line,
+        }
+    }
+
+    fn is_multiline(&self) -> bool {
+        self.start_line != self.end_line
+    }
+
+    fn last_char(&self) -> usize {
+        self.label.end.max(self.label.start + 1) - 1
+    }
+}
+
+fn lines_to_show(labels: &[SourceLabel]) -> BTreeSet<usize> {
+    let mut lines = BTreeSet::new();
+    for label in labels {
+        lines.insert(label.start_line);
+        lines.insert(label.end_line);
+        let inner = label.start_line + 1..label.end_line;
+        if inner.len() <= MAX_INNER_LINES {
+            lines.extend(inner);
+        } else {
+            lines.insert(inner.start);
+            lines.insert(inner.end - 1);
+        }
+    }
+    lines
+}
+
+fn digits(n: usize) -> usize {
+    n.to_string().len()
+}
@@ -0,0 +1,158 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::Index;
+
+/// Identifies a source file within a [SourceMap]. Ids are never reused, even if their file has
+/// been removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceId(usize);
+
+impl SourceId {
+    /// The number of files which were added to the map before this one.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A source file which has been added to a [SourceMap].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    id: SourceId,
+    name: Option<String>,
+    src: String,
+    offset: usize,
+}
+
+impl SourceFile {
+    /// The name of the file, e.g. its path. Code which wasn't read from a file, like code
+    /// passed to `eval`, may not have a name.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    /// The offset of the first byte of the file within the source map.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Whether `offset` points into the file or directly behind its last byte.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.offset <= offset && offset <= self.offset + self.src.len()
+    }
+}
+
+/// A position within a source file, as shown to users.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location<'a> {
+    pub file: &'a SourceFile,
+    /// The one-based line.
+    pub line: usize,
+    /// The one-based column, counted in characters.
+    pub column: usize,
+}
+
+impl Display for Location<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.file.name() {
+            Some(name) => write!(f, "{}:{}:{}", name, self.line, self.column),
+            None => write!(f, "{}:{}", self.line, self.column),
+        }
+    }
+}
+
+/// A registry of source files.
+///
+/// Every file is assigned its own range of offsets, so a position within any of the files is a
+/// single offset, just like a position within a single string. The first file starts at offset
+/// zero, so a map with only one file uses the same offsets as the file itself. Offsets of removed
+/// files aren't reused, so stale positions never point into another file.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    /// The files ordered by their id, and therefore by their offset.
+    files: Vec<SourceFile>,
+    next_id: usize,
+    next_offset: usize,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    /// Adds a file to the map. Its offsets start behind those of the previously added file.
+    pub fn add(&mut self, name: Option<String>, src: impl Into<String>) -> SourceId {
+        let id = SourceId(self.next_id);
+        let src = src.into();
+        let offset = self.next_offset;
+        // A gap of one byte keeps the offset behind the end of a file apart from the start of
+        // the next one.
+        self.next_offset += src.len() + 1;
+        self.next_id += 1;
+        self.files.push(SourceFile {
+            id,
+            name,
+            src,
+            offset,
+        });
+        id
+    }
+
+    /// Removes a file from the map, e.g. once no code compiled from it is left.
+    pub fn remove(&mut self, id: SourceId) -> Option<SourceFile> {
+        let idx = self.position(id)?;
+        Some(self.files.remove(idx))
+    }
+
+    pub fn get(&self, id: SourceId) -> Option<&SourceFile> {
+        self.position(id).map(|idx| &self.files[idx])
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (SourceId, &SourceFile)> {
+        self.files.iter().map(|file| (file.id, file))
+    }
+
+    fn position(&self, id: SourceId) -> Option<usize> {
+        self.files.binary_search_by_key(&id, |file| file.id).ok()
+    }
+
+    /// The file containing `offset`.
+    pub fn lookup(&self, offset: usize) -> Option<SourceId> {
+        let idx = self
+            .files
+            .partition_point(|file| file.offset <= offset)
+            .checked_sub(1)?;
+        match self.files[idx].contains(offset) {
+            true => Some(self.files[idx].id),
+            false => None,
+        }
+    }
+
+    /// The file, line and column of `offset`.
+    pub fn location(&self, offset: usize) -> Option<Location<'_>> {
+        let file = &self[self.lookup(offset)?];
+        let mut local = offset - file.offset;
+        while !file.src.is_char_boundary(local) {
+            local -= 1;
+        }
+        let before = &file.src[..local];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        Some(Location {
+            file,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        })
+    }
+}
+
+/// # Panics
+/// Panics if the id belongs to another source map, or if its file has been removed.
+impl Index<SourceId> for SourceMap {
+    type Output = SourceFile;
+
+    fn index(&self, id: SourceId) -> &SourceFile {
+        self.get(id).expect("the source file isn't in the map")
+    }
+}